@@ -2,39 +2,147 @@
 Provides utility functions used in other parts of the library.
 */
 
+pub use self::discretization::{discretize, q_pcwna};
+#[cfg(feature = "alloc")]
+pub use self::vec::{convolve, convolve2d, ConvolutionMode};
+#[cfg(all(feature = "alloc", feature = "rayon"))]
+pub use self::vec::par_convolve;
+
 #[cfg(feature = "alloc")]
 pub(crate) mod vec {
     use alloc::vec::Vec;
     use num_traits::Float;
+    #[cfg(feature = "rayon")]
+    use rayon::prelude::*;
 
-    /// Determines how the convolution is computed. This mostly affects behaviour at the boundaries.
+    /// Determines how the convolution is computed. This mostly affects behaviour at the boundaries
+    /// and the length of the output.
     #[derive(Debug)]
-    pub(crate) enum ConvolutionMode<F> {
+    pub enum ConvolutionMode<F> {
         /// Returns the convolution at each point of overlap, assuming the signals wrap around.
+        /// The output has the length of the longer signal.
         Wrap,
         /// Returns the convolution at each point of overlap, assuming the signals
-        /// are extended by the given value.
+        /// are extended by the given value. The output has the length of the longer signal.
         Extended(F),
+        /// Returns the convolution at each point of overlap, assuming the signals are zero
+        /// outside their bounds. The output has length `a.len() + b.len() - 1`.
+        /// This corresponds to numpy's `full` mode.
+        Full,
+        /// Returns the central part of the `Full` convolution with the length of the longer signal.
+        /// This corresponds to numpy's `same` mode. Note that for even window lengths this is
+        /// aligned one element differently than `Extended(0.0)`.
+        Same,
+        /// Returns only the convolution at points where the signals overlap completely.
+        /// The output has length `max(a.len(), b.len()) - min(a.len(), b.len()) + 1`.
+        /// This corresponds to numpy's `valid` mode.
+        Valid,
     }
 
     /// Compute the discrete convolution of the two slices.
-    /// This might be slow, as this function is not optimised in any way.
-    pub(crate) fn convolve<F: Float>(a: &[F], b: &[F], mode: ConvolutionMode<F>) -> Vec<F> {
+    /// This might be slow, as this function is not optimised in any way. See `par_convolve` for
+    /// a parallel version.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::common::{convolve, ConvolutionMode};
+    ///
+    /// let a = [1.0, 2.0, 3.0];
+    /// let b = [0.0, 1.0, 0.5];
+    ///
+    /// assert_eq!(vec![0.0, 1.0, 2.5, 4.0, 1.5], convolve(&a, &b, ConvolutionMode::Full));
+    /// assert_eq!(vec![1.0, 2.5, 4.0], convolve(&a, &b, ConvolutionMode::Same));
+    /// assert_eq!(vec![2.5], convolve(&a, &b, ConvolutionMode::Valid));
+    /// ```
+    pub fn convolve<F: Float>(a: &[F], b: &[F], mode: ConvolutionMode<F>) -> Vec<F> {
         let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
 
         match mode {
             ConvolutionMode::Wrap => convolve_wrap(a, b),
             ConvolutionMode::Extended(c) => convolve_extended(a, b, c),
+            ConvolutionMode::Full => convolve_full(a, b),
+            ConvolutionMode::Same => {
+                let start = b.len().saturating_sub(1) / 2;
+                let mut full = convolve_full(a, b);
+                full.truncate(start + a.len());
+                full.drain(..start);
+                full
+            }
+            ConvolutionMode::Valid => {
+                let start = b.len().saturating_sub(1);
+                let mut full = convolve_full(a, b);
+                full.truncate(a.len());
+                full.drain(..start);
+                full
+            }
+        }
+    }
+
+    fn convolve_full<F: Float>(signal: &[F], window: &[F]) -> Vec<F> {
+        if signal.is_empty() || window.is_empty() {
+            return Vec::new();
+        }
+
+        let mut result = Vec::with_capacity(signal.len() + window.len() - 1);
+        result.resize_with(signal.len() + window.len() - 1, F::zero);
+
+        for (i, &s_i) in signal.iter().enumerate() {
+            for (j, &w_j) in window.iter().enumerate() {
+                result[i + j] = result[i + j] + s_i * w_j;
+            }
+        }
+        result
+    }
+
+    /// Compute the discrete convolution of the two slices like `convolve`, but compute the
+    /// output elements of the `Wrap` and `Extended` modes in parallel. The other modes are
+    /// computed like in `convolve`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::common::{convolve, par_convolve, ConvolutionMode};
+    ///
+    /// let a = [1.0, 2.0, 3.0];
+    /// let b = [0.0, 1.0, 0.5];
+    ///
+    /// assert_eq!(
+    ///     convolve(&a, &b, ConvolutionMode::Wrap),
+    ///     par_convolve(&a, &b, ConvolutionMode::Wrap)
+    /// );
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_convolve<F>(a: &[F], b: &[F], mode: ConvolutionMode<F>) -> Vec<F>
+        where
+            F: Float + Send + Sync,
+    {
+        let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
+        let m = a.len() as i64;
+
+        match mode {
+            ConvolutionMode::Wrap => (0..m).into_par_iter().map(wrap_at(a, b)).collect(),
+            ConvolutionMode::Extended(c) => {
+                (0..m).into_par_iter().map(extended_at(a, b, c)).collect()
+            }
+            mode => convolve(a, b, mode),
         }
     }
 
     fn convolve_extended<F: Float>(signal: &[F], window: &[F], c: F) -> Vec<F> {
-        let m = signal.len() as i64;
+        (0..signal.len() as i64).map(extended_at(signal, window, c)).collect()
+    }
+
+    fn convolve_wrap<F: Float>(signal: &[F], window: &[F]) -> Vec<F> {
+        (0..signal.len() as i64).map(wrap_at(signal, window)).collect()
+    }
+
+    /// Returns the function computing the output element `i` of the `Extended` convolution.
+    fn extended_at<'a, F: Float>(signal: &'a [F], window: &'a [F], c: F) -> impl Fn(i64) -> F + 'a {
         let n = window.len() as i64;
-        debug_assert!(m >= n);
+        debug_assert!(signal.len() as i64 >= n);
 
-        let mut result = Vec::default();
-        for i in 0..m {
+        move |i: i64| {
             let mut x = F::zero();
             for j in 0..n {
                 let s_ij = {
@@ -44,30 +152,28 @@ pub(crate) mod vec {
                 let w_ij = *window.get(j as usize).unwrap_or(&c);
                 x = x + s_ij * w_ij;
             }
-            result.push(x)
+            x
         }
-        result
     }
 
-    fn convolve_wrap<F: Float>(signal: &[F], window: &[F]) -> Vec<F> {
+    /// Returns the function computing the output element `i` of the `Wrap` convolution.
+    fn wrap_at<'a, F: Float>(signal: &'a [F], window: &'a [F]) -> impl Fn(i64) -> F + 'a {
         let m = signal.len() as i64;
         let n = window.len() as i64;
         debug_assert!(m >= n);
 
-        let mut result = Vec::with_capacity(m as usize);
-        result.resize_with(m as usize, F::zero);
-
-        for i in 0..m {
+        move |i: i64| {
+            let mut x = F::zero();
             for j in 0..n {
                 let s_ij = {
                     let ix = (m + i - j + (n / 2)) % m;
                     signal[ix as usize]
                 };
                 let w_ij = window[j as usize];
-                result[i as usize] = result[i as usize] + (s_ij * w_ij);
+                x = x + (s_ij * w_ij);
             }
+            x
         }
-        result
     }
 
     pub(super) fn roll<T: Copy>(a: &[T], shift: i64) -> Vec<T> {
@@ -100,10 +206,475 @@ pub(crate) mod vec {
             ShiftMode::Extend(c) => shift_extend(a, shift, c),
         }
     }
+
+    /// Compute the discrete convolution of the 2D `signal` with the 2D `kernel`. Both are given
+    /// as flat slices in row-major order together with their `(rows, cols)` shape.
+    /// The result is returned in the same representation. It has the shape of `signal`, except for
+    /// `ConvolutionMode::Full`, where it has shape `(rows + kernel_rows - 1, cols + kernel_cols - 1)`,
+    /// and `ConvolutionMode::Valid`, where it has shape `(rows - kernel_rows + 1, cols - kernel_cols + 1)`.
+    /// Unlike `convolve`, the arguments are not interchangeable.
+    /// This might be slow, as this function is not optimised in any way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::common::{convolve2d, ConvolutionMode};
+    ///
+    /// let signal = [
+    ///     1.0, 2.0,
+    ///     3.0, 4.0,
+    /// ];
+    /// let kernel = [1.0, 1.0];
+    ///
+    /// let result = convolve2d(&signal, (2, 2), &kernel, (1, 2), ConvolutionMode::Full);
+    /// assert_eq!(vec![1.0, 3.0, 2.0, 3.0, 7.0, 4.0], result);
+    /// ```
+    pub fn convolve2d<F: Float>(
+        signal: &[F],
+        signal_shape: (usize, usize),
+        kernel: &[F],
+        kernel_shape: (usize, usize),
+        mode: ConvolutionMode<F>,
+    ) -> Vec<F> {
+        let (m_r, m_c) = (signal_shape.0 as i64, signal_shape.1 as i64);
+        let (n_r, n_c) = (kernel_shape.0 as i64, kernel_shape.1 as i64);
+        debug_assert_eq!(signal.len() as i64, m_r * m_c);
+        debug_assert_eq!(kernel.len() as i64, n_r * n_c);
+
+        // Shape of the output and the position of its first element in the (zero extended) signal.
+        let (rows, cols, start_r, start_c) = match mode {
+            ConvolutionMode::Wrap | ConvolutionMode::Extended(_) => (m_r, m_c, n_r / 2, n_c / 2),
+            ConvolutionMode::Full => (m_r + n_r - 1, m_c + n_c - 1, 0, 0),
+            ConvolutionMode::Same => (m_r, m_c, (n_r - 1) / 2, (n_c - 1) / 2),
+            ConvolutionMode::Valid => (m_r - n_r + 1, m_c - n_c + 1, n_r - 1, n_c - 1),
+        };
+
+        let sample = |r: i64, c: i64| match mode {
+            ConvolutionMode::Wrap => {
+                let r = r.rem_euclid(m_r);
+                let c = c.rem_euclid(m_c);
+                signal[(r * m_c + c) as usize]
+            }
+            ConvolutionMode::Extended(value) => {
+                if r < 0 || r >= m_r || c < 0 || c >= m_c {
+                    value
+                } else {
+                    signal[(r * m_c + c) as usize]
+                }
+            }
+            ConvolutionMode::Full | ConvolutionMode::Same | ConvolutionMode::Valid => {
+                if r < 0 || r >= m_r || c < 0 || c >= m_c {
+                    F::zero()
+                } else {
+                    signal[(r * m_c + c) as usize]
+                }
+            }
+        };
+
+        let mut result = Vec::with_capacity((rows.max(0) * cols.max(0)) as usize);
+        for r in 0..rows {
+            for c in 0..cols {
+                let mut x = F::zero();
+                for j in 0..n_r {
+                    for k in 0..n_c {
+                        let s_ij = sample(r - j + start_r, c - k + start_c);
+                        let w_ij = kernel[(j * n_c + k) as usize];
+                        x = x + s_ij * w_ij;
+                    }
+                }
+                result.push(x)
+            }
+        }
+        result
+    }
+
+    /// Shift the 2D array `a`, given as a flat slice in row-major order with the given
+    /// `(rows, cols)` shape, by `shift` rows and columns.
+    pub(crate) fn shift2d<T: Copy>(
+        a: &[T],
+        shape: (usize, usize),
+        shift: (i64, i64),
+        mode: ShiftMode<T>,
+    ) -> Vec<T> {
+        let (rows, cols) = (shape.0 as i64, shape.1 as i64);
+        debug_assert_eq!(a.len() as i64, rows * cols);
+
+        let mut out = Vec::with_capacity(a.len());
+        for r in 0..rows {
+            for c in 0..cols {
+                let (ix_r, ix_c) = (r - shift.0, c - shift.1);
+                out.push(match mode {
+                    ShiftMode::Wrap => {
+                        a[(ix_r.rem_euclid(rows) * cols + ix_c.rem_euclid(cols)) as usize]
+                    }
+                    ShiftMode::Extend(value) => {
+                        if ix_r < 0 || ix_r >= rows || ix_c < 0 || ix_c >= cols {
+                            value
+                        } else {
+                            a[(ix_r * cols + ix_c) as usize]
+                        }
+                    }
+                })
+            }
+        }
+        out
+    }
+}
+
+pub(crate) mod discretization {
+    use nalgebra::allocator::Allocator;
+    use nalgebra::base::dimension::DimName;
+    use nalgebra::{DefaultAllocator, MatrixMN, RealField};
+
+    /// Upper block triangular matrix `[[X, Y], [0, Z]]`. The matrix exponential of such a matrix
+    /// is again upper block triangular, so it can be computed without building the full matrix.
+    #[allow(non_snake_case)]
+    struct BlockTriangular<F, N, M>
+        where
+            F: RealField,
+            N: DimName,
+            M: DimName,
+            DefaultAllocator: Allocator<F, N, N> + Allocator<F, N, M> + Allocator<F, M, M>,
+    {
+        X: MatrixMN<F, N, N>,
+        Y: MatrixMN<F, N, M>,
+        Z: MatrixMN<F, M, M>,
+    }
+
+    #[allow(non_snake_case)]
+    impl<F, N, M> BlockTriangular<F, N, M>
+        where
+            F: RealField,
+            N: DimName,
+            M: DimName,
+            DefaultAllocator: Allocator<F, N, N> + Allocator<F, N, M> + Allocator<F, M, M>,
+    {
+        fn identity() -> Self {
+            BlockTriangular {
+                X: MatrixMN::<F, N, N>::identity(),
+                Y: MatrixMN::<F, N, M>::zeros(),
+                Z: MatrixMN::<F, M, M>::identity(),
+            }
+        }
+
+        fn mul(&self, other: &Self) -> Self {
+            BlockTriangular {
+                X: &self.X * &other.X,
+                Y: &self.X * &other.Y + &self.Y * &other.Z,
+                Z: &self.Z * &other.Z,
+            }
+        }
+
+        fn add_scaled(&mut self, other: &Self, c: F) {
+            self.X += &other.X * c;
+            self.Y += &other.Y * c;
+            self.Z += &other.Z * c;
+        }
+
+        fn scale(&mut self, c: F) {
+            self.X *= c;
+            self.Y *= c;
+            self.Z *= c;
+        }
+
+        fn norm(&self) -> F {
+            (self.X.norm_squared() + self.Y.norm_squared() + self.Z.norm_squared()).sqrt()
+        }
+
+        fn is_finite(&self) -> bool {
+            self.X.iter().chain(self.Y.iter()).chain(self.Z.iter()).all(|v| v.is_finite())
+        }
+
+        /// Returns the inverse, using `[[X, Y], [0, Z]]^-1 = [[X^-1, -X^-1 Y Z^-1], [0, Z^-1]]`.
+        fn try_inverse(&self) -> Option<Self> {
+            let XI = self.X.clone().try_inverse()?;
+            let ZI = self.Z.clone().try_inverse()?;
+            Some(BlockTriangular {
+                Y: -(&XI * &self.Y * &ZI),
+                X: XI,
+                Z: ZI,
+            })
+        }
+
+        /// Returns the matrix exponential, using the Padé approximation of order 6 with scaling
+        /// and squaring.
+        ///
+        /// Panics if an element is not finite, as the scaling would never bring the norm below
+        /// 1/2.
+        fn exp(&self) -> Self {
+            const ORDER: usize = 6;
+            let half: F = nalgebra::convert(0.5);
+            assert!(self.is_finite(), "matrix exponential of a matrix that is not finite");
+
+            let mut squarings = 0;
+            let mut scaled = BlockTriangular {
+                X: self.X.clone(),
+                Y: self.Y.clone(),
+                Z: self.Z.clone(),
+            };
+            while scaled.norm() > half {
+                scaled.scale(half);
+                squarings += 1;
+            }
+
+            let mut numerator = Self::identity();
+            let mut denominator = Self::identity();
+            let mut power = Self::identity();
+            let mut c = F::one();
+            for k in 1..=ORDER {
+                c *= nalgebra::convert((ORDER - k + 1) as f64 / (k * (2 * ORDER - k + 1)) as f64);
+                power = power.mul(&scaled);
+                numerator.add_scaled(&power, c);
+                denominator.add_scaled(&power, if k % 2 == 0 { c } else { -c });
+            }
+
+            // The denominator is close to the identity because the norm was scaled below 1/2.
+            let mut result = denominator.try_inverse().unwrap().mul(&numerator);
+            for _ in 0..squarings {
+                result = result.mul(&result);
+            }
+            result
+        }
+    }
+
+    /// Discretizes the continuous-time model `x' = A x + B u + w`, where `w` is white noise with
+    /// the spectral density `q_continuous`, for the time step `dt`.
+    ///
+    /// Returns the state transition `F`, the control transition `B_d` and the process noise `Q_d`
+    /// of the equivalent discrete model `x_k+1 = F x_k + B_d u_k + w_k`, assuming `u` is constant
+    /// over the time step. The matrix exponentials are computed with the method of van Loan.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::common::discretize;
+    /// use nalgebra::{Matrix2, Vector2};
+    ///
+    /// // Constant velocity model, driven by white noise acceleration.
+    /// let a = Matrix2::new(
+    ///     0.0, 1.0,
+    ///     0.0, 0.0,
+    /// );
+    /// let b = Vector2::new(0.0, 1.0);
+    /// let q = Matrix2::new(
+    ///     0.0, 0.0,
+    ///     0.0, 1.0,
+    /// );
+    ///
+    /// let (f, b_d, q_d) = discretize(&a, &b, &q, 0.1_f64);
+    /// assert!((f - Matrix2::new(1.0, 0.1, 0.0, 1.0)).norm() < 1e-12);
+    /// assert!((b_d - Vector2::new(0.005, 0.1)).norm() < 1e-12);
+    /// assert!((q_d[(1, 1)] - 0.1).abs() < 1e-12);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `A`, `B`, `q_continuous` or `dt` contain values that are not finite.
+    ///
+    /// # References
+    /// * Van Loan, "Computing integrals involving the matrix exponential".
+    ///   IEEE Transactions on Automatic Control, 23(3), 1978.
+    #[allow(non_snake_case, clippy::type_complexity)]
+    pub fn discretize<F, DimX, DimU>(
+        A: &MatrixMN<F, DimX, DimX>,
+        B: &MatrixMN<F, DimX, DimU>,
+        q_continuous: &MatrixMN<F, DimX, DimX>,
+        dt: F,
+    ) -> (MatrixMN<F, DimX, DimX>, MatrixMN<F, DimX, DimU>, MatrixMN<F, DimX, DimX>)
+        where
+            F: RealField,
+            DimX: DimName,
+            DimU: DimName,
+            DefaultAllocator: Allocator<F, DimX, DimX>
+            + Allocator<F, DimX, DimU>
+            + Allocator<F, DimU, DimU>,
+    {
+        // exp([[A, B], [0, 0]] dt) = [[F, B_d], [0, I]]
+        let control = BlockTriangular {
+            X: A * dt,
+            Y: B * dt,
+            Z: MatrixMN::<F, DimU, DimU>::zeros(),
+        }
+        .exp();
+
+        // exp([[-A, Q_c], [0, A^T]] dt) = [[., F^-1 Q_d], [0, F^T]]
+        let noise = BlockTriangular {
+            X: -A * dt,
+            Y: q_continuous * dt,
+            Z: A.transpose() * dt,
+        }
+        .exp();
+        let F = noise.Z.transpose();
+        let Q = &F * noise.Y;
+
+        (F, control.Y, Q)
+    }
+
+    /// Returns the process noise `Q` of the piecewise constant white noise acceleration (PCWNA)
+    /// model with the variance `var` and the time step `dt`, for a state of 2, 3 or 4 variables,
+    /// e.g., `[position, velocity]` or `[position, velocity, acceleration]`.
+    ///
+    /// The model assumes that the highest derivative of the state changes by an independent
+    /// random amount at the start of each time step and stays constant during it, so `Q` is
+    /// `G G^T var` with `G = [dt^2 / 2, dt]` for 2 variables, `[dt^2 / 2, dt, 1]` for 3 and
+    /// `[dt^3 / 6, dt^2 / 2, dt, 1]` for 4. This is the matrix filterpy returns from
+    /// `Q_discrete_white_noise`. Its rank is one, as all noise comes from a single random
+    /// variable. In contrast, the continuous white noise model, where the derivative changes
+    /// continuously during the step, results in a full rank `Q`, e.g.,
+    /// `[[dt^3 / 3, dt^2 / 2], [dt^2 / 2, dt]] q` for 2 variables, which is computed by
+    /// `discretize`.
+    ///
+    /// # Panics
+    /// Panics if `DimX` is not 2, 3 or 4.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::common::q_pcwna;
+    /// use nalgebra::{Matrix2, U2};
+    ///
+    /// let q = q_pcwna::<f64, U2>(0.5, 4.0);
+    /// assert_eq!(q, Matrix2::new(
+    ///     0.0625, 0.25,
+    ///     0.25, 1.0,
+    /// ));
+    /// ```
+    ///
+    /// # References
+    /// * Bar-Shalom, Li, Kirubarajan, "Estimation with Applications to Tracking and Navigation",
+    ///   sections 6.3.2 and 6.3.3. John Wiley and Sons, 2001.
+    pub fn q_pcwna<F, DimX>(dt: F, var: F) -> MatrixMN<F, DimX, DimX>
+        where
+            F: RealField,
+            DimX: DimName,
+            DefaultAllocator: Allocator<F, DimX, DimX>,
+    {
+        let dim = DimX::dim();
+        assert!((2..=4).contains(&dim), "dimension must be 2, 3 or 4");
+
+        // Effect of the noise on each state variable: dt^k / k!, where the last variable of the
+        // 3 and 4 dimensional models is the noisy derivative itself.
+        let order = if dim == 2 { 2 } else { dim - 1 };
+        let g = |i: usize| {
+            let k = order - i;
+            let factorial = (1..=k).fold(F::one(), |f, j| f * nalgebra::convert(j as f64));
+            dt.powi(k as i32) / factorial
+        };
+        MatrixMN::<F, DimX, DimX>::from_fn(|r, c| g(r) * g(c) * var)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use nalgebra::{Matrix2, Matrix3, Vector2, Vector3, Vector4, U2, U3, U4};
+
+        use super::*;
+
+        #[test]
+        fn test_discretize_constant_velocity() {
+            let a = Matrix2::new(
+                0.0, 1.0,
+                0.0, 0.0,
+            );
+            let b = Vector2::new(0.0, 1.0);
+            let q = Matrix2::new(
+                0.0, 0.0,
+                0.0, 2.0,
+            );
+
+            for &dt in &[0.01, 0.5, 3.0] {
+                let (f, b_d, q_d) = discretize(&a, &b, &q, dt);
+
+                let f_expected = Matrix2::new(
+                    1.0, dt,
+                    0.0, 1.0,
+                );
+                let b_expected = Vector2::new(dt * dt / 2.0, dt);
+                let q_expected = Matrix2::new(
+                    dt * dt * dt / 3.0, dt * dt / 2.0,
+                    dt * dt / 2.0, dt,
+                ) * 2.0;
+
+                assert!((f - f_expected).norm() < 1e-12);
+                assert!((b_d - b_expected).norm() < 1e-12);
+                assert!((q_d - q_expected).norm() < 1e-10 * q_expected.norm());
+            }
+        }
+
+        #[test]
+        fn test_q_pcwna() {
+            let dt: f64 = 0.2;
+            let var = 3.0;
+
+            let q2 = q_pcwna::<f64, U2>(dt, var);
+            let q2_expected = Matrix2::new(
+                dt.powi(4) / 4.0, dt.powi(3) / 2.0,
+                dt.powi(3) / 2.0, dt.powi(2),
+            ) * var;
+            assert!((q2 - q2_expected).norm() < 1e-15);
+
+            let q3 = q_pcwna::<f64, U3>(dt, var);
+            let q3_expected = Matrix3::new(
+                dt.powi(4) / 4.0, dt.powi(3) / 2.0, dt.powi(2) / 2.0,
+                dt.powi(3) / 2.0, dt.powi(2), dt,
+                dt.powi(2) / 2.0, dt, 1.0,
+            ) * var;
+            assert!((q3 - q3_expected).norm() < 1e-15);
+
+            let q4 = q_pcwna::<f64, U4>(dt, var);
+            let g = Vector4::new(dt.powi(3) / 6.0, dt.powi(2) / 2.0, dt, 1.0);
+            assert!((q4 - g * g.transpose() * var).norm() < 1e-15);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_q_pcwna_unsupported_dimension() {
+            q_pcwna::<f64, nalgebra::U1>(0.1, 1.0);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_discretize_infinite() {
+            let a = Matrix2::new(
+                0.0, 1.0,
+                0.0, f64::INFINITY,
+            );
+            discretize(&a, &Vector2::new(0.0, 1.0), &Matrix2::identity(), 0.1);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_discretize_nan() {
+            let a = Matrix2::new(
+                0.0, 1.0,
+                0.0, f64::NAN,
+            );
+            discretize(&a, &Vector2::new(0.0, 1.0), &Matrix2::identity(), 0.1);
+        }
+
+        #[test]
+        fn test_discretize_matches_exponential_decay() {
+            // Three decoupled first-order lag systems x' = -x / tau + u.
+            let taus = Vector3::new(0.5, 1.0, 20.0);
+            let a = Matrix3::from_diagonal(&taus.map(|tau| -1.0 / tau));
+            let b = Vector3::new(1.0, 1.0, 1.0);
+            let q = Matrix3::identity();
+            let dt: f64 = 2.0;
+
+            let (f, b_d, q_d) = discretize(&a, &b, &q, dt);
+
+            for i in 0..3 {
+                let tau = taus[i];
+                let decay = (-dt / tau).exp();
+                assert!((f[(i, i)] - decay).abs() < 1e-12);
+                assert!((b_d[i] - tau * (1.0 - decay)).abs() < 1e-12);
+                assert!((q_d[(i, i)] - tau / 2.0 * (1.0 - decay * decay)).abs() < 1e-12);
+            }
+        }
+    }
 }
 
-#[cfg(all(test, feature="std"))]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
+    use alloc::vec::Vec;
     use assert_approx_eq::assert_approx_eq;
     use num_traits::Float;
     use super::vec::*;
@@ -214,4 +785,123 @@ mod tests {
         debug_assert_eq!(100, c[8]);
         debug_assert_eq!(100, c[9]);
     }
+
+    #[test]
+    fn test_convolve_numpy_modes() {
+        let a = &[1.0, 2.0, 3.0];
+        let b = &[0.0, 1.0, 0.5];
+
+        assert_eq!(vec![0.0, 1.0, 2.5, 4.0, 1.5], convolve(a, b, ConvolutionMode::Full));
+        assert_eq!(vec![1.0, 2.5, 4.0], convolve(a, b, ConvolutionMode::Same));
+        assert_eq!(vec![2.5], convolve(a, b, ConvolutionMode::Valid));
+    }
+
+    #[test]
+    fn test_convolve_numpy_modes_even_window() {
+        let a = &[1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = &[1.0, 2.0, 3.0, 4.0];
+
+        let full = [1.0, 4.0, 10.0, 20.0, 30.0, 34.0, 31.0, 20.0];
+        assert_eq!(full.to_vec(), convolve(a, b, ConvolutionMode::Full));
+        assert_eq!(full[1..6].to_vec(), convolve(a, b, ConvolutionMode::Same));
+        assert_eq!(full[3..5].to_vec(), convolve(a, b, ConvolutionMode::Valid));
+        // The arguments are interchangeable.
+        assert_eq!(full[1..6].to_vec(), convolve(b, a, ConvolutionMode::Same));
+    }
+
+    #[test]
+    fn test_convolve2d_averaging() {
+        let signal: Vec<f64> = (1..=25).map(f64::from).collect();
+        let kernel = [1.0 / 9.0; 9];
+
+        let result = convolve2d(&signal, (5, 5), &kernel, (3, 3), ConvolutionMode::Extended(0.0));
+        let reference = [
+            16.0, 27.0, 33.0, 39.0, 28.0,
+            39.0, 63.0, 72.0, 81.0, 57.0,
+            69.0, 108.0, 117.0, 126.0, 87.0,
+            99.0, 153.0, 162.0, 171.0, 117.0,
+            76.0, 117.0, 123.0, 129.0, 88.0,
+        ];
+        dbg!(&result);
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i] / 9.0, result[i]);
+        }
+
+        let result = convolve2d(&signal, (5, 5), &kernel, (3, 3), ConvolutionMode::Wrap);
+        let reference = [
+            99.0, 93.0, 102.0, 111.0, 105.0,
+            69.0, 63.0, 72.0, 81.0, 75.0,
+            114.0, 108.0, 117.0, 126.0, 120.0,
+            159.0, 153.0, 162.0, 171.0, 165.0,
+            129.0, 123.0, 132.0, 141.0, 135.0,
+        ];
+        dbg!(&result);
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i] / 9.0, result[i]);
+        }
+
+        let result = convolve2d(&signal, (5, 5), &kernel, (3, 3), ConvolutionMode::Valid);
+        let reference = [
+            7.0, 8.0, 9.0,
+            12.0, 13.0, 14.0,
+            17.0, 18.0, 19.0,
+        ];
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i], result[i]);
+        }
+    }
+
+    #[test]
+    fn test_convolve2d_modes_shape() {
+        let signal = [1.0; 20];
+        let kernel = [1.0; 6];
+
+        assert_eq!(20, convolve2d(&signal, (4, 5), &kernel, (2, 3), ConvolutionMode::Same).len());
+        assert_eq!(5 * 7, convolve2d(&signal, (4, 5), &kernel, (2, 3), ConvolutionMode::Full).len());
+        assert_eq!(3 * 3, convolve2d(&signal, (4, 5), &kernel, (2, 3), ConvolutionMode::Valid).len());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_convolve_parallel_matches_serial() {
+        let a: Vec<f64> = (0..1000).map(|i| (f64::from(i) * 0.37).sin()).collect();
+        let b = &[0.1, 0.25, 0.3, 0.2, 0.15];
+
+        let serial = |wrap: bool| -> Vec<f64> {
+            (0..a.len() as i64)
+                .map(|i| {
+                    let mut x = 0.0;
+                    for (j, w) in b.iter().enumerate() {
+                        let ix = i - j as i64 + (b.len() as i64 / 2);
+                        let s = if wrap {
+                            a[ix.rem_euclid(a.len() as i64) as usize]
+                        } else {
+                            *a.get(ix as usize).unwrap_or(&0.5)
+                        };
+                        x += s * w;
+                    }
+                    x
+                })
+                .collect()
+        };
+
+        let pairs = [
+            (serial(true), par_convolve(&a, b, ConvolutionMode::Wrap)),
+            (serial(false), par_convolve(&a, b, ConvolutionMode::Extended(0.5))),
+            (convolve(&a, b, ConvolutionMode::Wrap), par_convolve(&a, b, ConvolutionMode::Wrap)),
+            (convolve(&a, b, ConvolutionMode::Full), par_convolve(&a, b, ConvolutionMode::Full)),
+        ];
+        for (serial, parallel) in pairs.iter() {
+            assert_eq!(serial.len(), parallel.len());
+            for (s, p) in serial.iter().zip(parallel.iter()) {
+                assert_eq!(s.to_bits(), p.to_bits());
+            }
+        }
+    }
 }
@@ -1,14 +1,31 @@
 /*!
 Provides implementations of and related to Discrete Bayes filtering.
 */
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "alloc")]
+use core::convert::TryFrom;
+#[cfg(feature = "alloc")]
+use core::ops::Deref;
 use num_traits::Float;
 
-use crate::common::vec::{convolve, ConvolutionMode};
-use crate::common::vec::{shift, ShiftMode};
+#[cfg(feature = "alloc")]
+use crate::common::vec::{convolve, convolve2d, ConvolutionMode};
+#[cfg(all(feature = "alloc", feature = "rayon"))]
+use crate::common::vec::par_convolve;
+#[cfg(feature = "alloc")]
+use crate::common::vec::{shift, shift2d, ShiftMode};
 
 /// Normalize distribution `pdf` in-place so it sums to 1.0.
 ///
+/// Returns the sum of `pdf` before normalization, i.e., the normalization constant.
+/// When `pdf` is the unnormalized posterior of an update, this is the marginal likelihood
+/// of the measurement. If the sum is zero or not finite, `pdf` cannot be normalized and
+/// is left untouched.
+///
 /// # Example
 ///
 /// ```
@@ -16,17 +33,53 @@ use crate::common::vec::{shift, ShiftMode};
 /// use assert_approx_eq::assert_approx_eq;
 ///
 /// let mut pdf = [1.0, 1.0, 1.0, 1.0];
-/// normalize(&mut pdf);
+/// let sum = normalize(&mut pdf);
 ///
+/// assert_approx_eq!(sum, 4.0_f64);
 /// assert_approx_eq!(pdf[0], 0.25_f64);
 /// assert_approx_eq!(pdf[1], 0.25_f64);
 /// assert_approx_eq!(pdf[2], 0.25_f64);
 /// assert_approx_eq!(pdf[3], 0.25_f64);
 /// ```
 ///
-pub fn normalize<F: Float>(pdf: &mut [F]) {
+pub fn normalize<F: Float>(pdf: &mut [F]) -> F {
     let sum = pdf.iter().fold(F::zero(), |p, q| p + *q);
-    pdf.iter_mut().for_each(|f| *f = *f / sum);
+    if sum != F::zero() && sum.is_finite() {
+        pdf.iter_mut().for_each(|f| *f = *f / sum);
+    }
+    sum
+}
+
+/// Errors that can occur while computing a discrete Bayes posterior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BayesError {
+    /// The likelihood and the prior do not have the same number of elements.
+    LengthMismatch {
+        /// Number of elements in the likelihood.
+        likelihood: usize,
+        /// Number of elements in the prior.
+        prior: usize,
+    },
+    /// The unnormalized posterior sums to zero or to a value that is not finite, so it cannot
+    /// be normalized.
+    ZeroSum,
+    /// The values are not a normalized distribution, i.e., they are negative, not finite or do
+    /// not sum to one.
+    NotNormalized,
+}
+
+impl fmt::Display for BayesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BayesError::LengthMismatch { likelihood, prior } => write!(
+                f,
+                "likelihood has {} elements but prior has {}",
+                likelihood, prior
+            ),
+            BayesError::ZeroSum => write!(f, "posterior sums to zero or is not finite"),
+            BayesError::NotNormalized => write!(f, "values are not a normalized distribution"),
+        }
+    }
 }
 
 /// Computes the posterior of a discrete random variable given a
@@ -34,30 +87,205 @@ pub fn normalize<F: Float>(pdf: &mut [F]) {
 /// will be the likelihood of a measurement matching your current environment,
 /// and the prior comes from discrete_bayes.predict().
 ///
-pub fn update<F: Float>(likelihood: &[F], prior: &[F]) -> Result<Vec<F>, ()> {
+/// Returns an error if the likelihood and prior differ in length, or if their
+/// product sums to zero (i.e., the measurement is impossible under the prior)
+/// or to a value that is not finite.
+///
+#[cfg(feature = "alloc")]
+pub fn update<F: Float>(likelihood: &[F], prior: &[F]) -> Result<Vec<F>, BayesError> {
+    let mut posterior = Vec::with_capacity(prior.len());
+    posterior.resize(prior.len(), F::zero());
+    update_into(likelihood, prior, &mut posterior)?;
+    Ok(posterior)
+}
+
+/// Computes the posterior of a discrete random variable like `update`, but keeps every cell of
+/// the posterior at a probability of at least `floor`.
+///
+/// Cells that would fall below `floor` are set to it, and the remaining probability is
+/// distributed over the other cells in proportion to their posterior. This keeps the belief
+/// from collapsing to zero anywhere, so the filter can recover when the tracked object turns
+/// out to be somewhere it considered impossible, e.g., the "kidnapped robot" in localization.
+///
+/// # Panics
+///
+/// Panics if `floor` is negative or larger than `1 / prior.len()`, since the posterior could
+/// not sum to one.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::update_with_floor;
+///
+/// let prior = [0.5, 0.5, 0.0, 0.0];
+/// let likelihood = [1.0, 0.1, 1.0, 1.0];
+///
+/// let posterior = update_with_floor(&likelihood, &prior, 0.01).unwrap();
+/// assert!(posterior.iter().all(|&p| p >= 0.01_f64));
+/// ```
+///
+#[cfg(feature = "alloc")]
+pub fn update_with_floor<F: Float>(likelihood: &[F], prior: &[F], floor: F) -> Result<Vec<F>, BayesError> {
+    assert!(
+        floor >= F::zero() && floor * F::from(prior.len()).unwrap() <= F::one(),
+        "floor must be in [0, 1 / prior.len()]"
+    );
+    let mut posterior = update(likelihood, prior)?;
+
+    let unfloored = posterior.clone();
+    let mut floored = vec![false; posterior.len()];
+    loop {
+        // Distribute the probability that is not held by floored cells over the other cells.
+        let n_floored = floored.iter().filter(|&&f| f).count();
+        let remaining = F::one() - F::from(n_floored).unwrap() * floor;
+        let free_sum = unfloored
+            .iter()
+            .zip(&floored)
+            .filter(|(_, &f)| !f)
+            .fold(F::zero(), |sum, (&p, _)| sum + p);
+
+        let mut changed = false;
+        for ((p, &u), f) in posterior.iter_mut().zip(&unfloored).zip(floored.iter_mut()) {
+            if *f {
+                *p = floor;
+                continue;
+            }
+            *p = if free_sum > F::zero() { u * remaining / free_sum } else { floor };
+            if *p < floor {
+                *f = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(posterior);
+        }
+    }
+}
+
+/// Computes the posterior of a discrete random variable given a discrete likelihood
+/// and prior, like `update`, but writes it into `out` instead of allocating.
+///
+/// # Panics
+///
+/// Panics if `out` does not have the same length as `prior`.
+///
+pub fn update_into<F: Float>(likelihood: &[F], prior: &[F], out: &mut [F]) -> Result<(), BayesError> {
     if likelihood.len() != prior.len() {
-        return Err(());
+        return Err(BayesError::LengthMismatch {
+            likelihood: likelihood.len(),
+            prior: prior.len(),
+        });
+    }
+    assert_eq!(prior.len(), out.len(), "output must have the length of the prior");
+    out.iter_mut()
+        .zip(likelihood.iter().zip(prior.iter()))
+        .for_each(|(o, (&l, &p))| *o = l * p);
+    let sum = normalize(out);
+    if sum == F::zero() || !sum.is_finite() {
+        return Err(BayesError::ZeroSum);
     }
-    let mut posterior: Vec<F> = likelihood
+    Ok(())
+}
+
+/// Returns the likelihood of `measurement` at each cell of the grid, for a sensor with Gaussian
+/// noise of standard deviation `sigma` that measures the position of the tracked object.
+///
+/// The likelihood of a cell is the density of the normal distribution `N(positions[i], sigma^2)`
+/// at `measurement`, where `positions` are the positions of the cell centers.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{gaussian_likelihood, update};
+///
+/// let positions = [0.0, 1.0, 2.0, 3.0];
+/// let likelihood = gaussian_likelihood(&positions, 2.2, 0.5);
+/// let posterior = update(&likelihood, &[0.25; 4]).unwrap();
+/// assert!(posterior[2] > posterior[3]);
+/// ```
+///
+#[cfg(feature = "alloc")]
+pub fn gaussian_likelihood<F: Float>(positions: &[F], measurement: F, sigma: F) -> Vec<F> {
+    let two = F::from(2.0).unwrap();
+    let norm = sigma * F::from(2.0 * core::f64::consts::PI).unwrap().sqrt();
+    positions
         .iter()
-        .zip(prior.iter())
-        .map(|(&l, &p)| l * p)
-        .collect();
-    normalize(&mut posterior);
-    Ok(posterior)
+        .map(|&x| {
+            let d = (measurement - x) / sigma;
+            (-d * d / two).exp() / norm
+        })
+        .collect()
+}
+
+/// Returns the likelihood of the reading `z` of a sensor that detects features of the map, at
+/// each cell of `map`, where the sensor reports the correct feature with probability `z_prob`.
+///
+/// This is the sensor model of the hallway example of "Kalman and Bayesian Filters in Python",
+/// where a robot senses whether it is in front of a door. Cells whose feature matches the
+/// reading are `z_prob / (1 - z_prob)` times as likely as the others, which have likelihood 1.
+///
+/// # Panics
+///
+/// Panics if `z_prob` is not in `(0, 1)`.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::lh_hall_sense;
+///
+/// let hallway = [1, 1, 0, 0, 0, 0, 0, 0, 1, 0];
+/// let likelihood = lh_hall_sense(&hallway, &1, 0.75);
+/// assert_eq!(likelihood[0], 3.0);
+/// assert_eq!(likelihood[2], 1.0);
+/// ```
+///
+/// # References
+/// * Labbe, "Kalman and Bayesian Filters in Python", chapter 2.
+///   http://rlabbe.github.io/Kalman-and-Bayesian-Filters-in-Python
+#[cfg(feature = "alloc")]
+pub fn lh_hall_sense<F: Float, T: PartialEq>(map: &[T], z: &T, z_prob: F) -> Vec<F> {
+    assert!(z_prob > F::zero() && z_prob < F::one(), "z_prob must be in (0, 1)");
+    let scale = z_prob / (F::one() - z_prob);
+    map.iter()
+        .map(|feature| if feature == z { scale } else { F::one() })
+        .collect()
 }
 
 /// Determines what happens at the boundaries of the probability distribution.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum EdgeHandling<F> {
     /// the  probability distribution is shifted and the given value is used to used to fill in missing elements.
     Constant(F),
     /// The probability distribution is wrapped around the array.
     Wrap,
+    /// Only the positions where the kernel fully overlaps the (shifted) probability distribution
+    /// are kept, and the result is re-normalized. Probability mass shifted out of the array is
+    /// lost. The output length is `pdf.len() - kernel.len() + 1`.
+    Valid,
 }
 
 /// Performs the discrete Bayes filter prediction step, generating the prior.
+#[cfg(feature = "alloc")]
 pub fn predict<F: Float>(pdf: &[F], offset: i64, kernel: &[F], mode: EdgeHandling<F>) -> Vec<F> {
+    predict_with(pdf, offset, kernel, mode, convolve)
+}
+
+/// Performs the discrete Bayes filter prediction step like `predict`, but computes the
+/// convolution with the kernel in parallel, see `common::par_convolve`.
+#[cfg(all(feature = "alloc", feature = "rayon"))]
+pub fn par_predict<F>(pdf: &[F], offset: i64, kernel: &[F], mode: EdgeHandling<F>) -> Vec<F>
+    where
+        F: Float + Send + Sync,
+{
+    predict_with(pdf, offset, kernel, mode, par_convolve)
+}
+
+#[cfg(feature = "alloc")]
+fn predict_with<F, C>(pdf: &[F], offset: i64, kernel: &[F], mode: EdgeHandling<F>, convolve: C) -> Vec<F>
+    where
+        F: Float,
+        C: Fn(&[F], &[F], ConvolutionMode<F>) -> Vec<F>,
+{
     match mode {
         EdgeHandling::Constant(c) => convolve(
             &shift(pdf, offset, ShiftMode::Extend(c)),
@@ -69,13 +297,610 @@ pub fn predict<F: Float>(pdf: &[F], offset: i64, kernel: &[F], mode: EdgeHandlin
             kernel,
             ConvolutionMode::Wrap,
         ),
+        EdgeHandling::Valid => {
+            let mut prior = convolve(
+                &shift(pdf, offset, ShiftMode::Extend(F::zero())),
+                kernel,
+                ConvolutionMode::Valid,
+            );
+            normalize(&mut prior);
+            prior
+        }
+    }
+}
+
+/// Performs the discrete Bayes filter prediction step, like `predict`, but writes the
+/// prior into `out` instead of allocating.
+///
+/// # Panics
+///
+/// Panics if `kernel` is longer than `pdf`, or if `out` does not have the same length as `pdf`
+/// (`pdf.len() - kernel.len() + 1` for `EdgeHandling::Valid`).
+///
+pub fn predict_into<F: Float>(
+    pdf: &[F],
+    offset: i64,
+    kernel: &[F],
+    mode: EdgeHandling<F>,
+    out: &mut [F],
+) {
+    let m = pdf.len() as i64;
+    let n = kernel.len() as i64;
+    assert!(m >= n, "kernel must not be longer than the pdf");
+    // `Valid` keeps the part of the zero extended convolution where the kernel fully overlaps.
+    let (len, start) = match mode {
+        EdgeHandling::Valid => (m - n + 1, (n - 1) - (n / 2)),
+        _ => (m, 0),
+    };
+    assert_eq!(len as usize, out.len(), "output has the wrong length");
+
+    // Sample the shifted pdf at index `ix`, applying the edge handling of both the shift and
+    // the convolution.
+    let shifted = |ix: i64| match mode {
+        EdgeHandling::Constant(c) => {
+            let src = ix - offset;
+            if ix < 0 || ix >= m || src < 0 || src >= m {
+                c
+            } else {
+                pdf[src as usize]
+            }
+        }
+        EdgeHandling::Wrap => pdf[(ix - offset).rem_euclid(m) as usize],
+        EdgeHandling::Valid => {
+            let src = ix - offset;
+            if src < 0 || src >= m {
+                F::zero()
+            } else {
+                pdf[src as usize]
+            }
+        }
+    };
+
+    for (i, o) in out.iter_mut().enumerate() {
+        let i = start + i as i64;
+        let mut x = F::zero();
+        for (j, &w_ij) in kernel.iter().enumerate() {
+            x = x + shifted(i - j as i64 + (n / 2)) * w_ij;
+        }
+        *o = x;
+    }
+
+    if let EdgeHandling::Valid = mode {
+        normalize(out);
+    }
+}
+
+/// Performs the discrete Bayes filter prediction step for a motion by a fractional number of
+/// cells, generating the prior.
+///
+/// The probability of each cell is split between the two cells closest to its destination by
+/// linear interpolation before it is convolved with `kernel`: the offset is rounded down to the
+/// cell `floor(offset)`, which receives `1 - (offset - floor(offset))` of the probability, and
+/// the next cell receives the remainder. For example, an offset of 1.25 moves 75% of the
+/// probability by one cell and 25% by two cells, and an offset of -0.25 moves 25% by minus one
+/// cell and keeps 75% in place. Integer offsets give the same result as `predict`.
+///
+/// Interpolation widens the kernel by one cell, so for `EdgeHandling::Valid` the prior of a
+/// fractional offset is one cell shorter than that of an integer offset.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{predict_fractional, EdgeHandling};
+///
+/// let prior = predict_fractional(&[1.0, 0.0, 0.0, 0.0], 1.5, &[1.0], EdgeHandling::Wrap);
+/// assert_eq!(vec![0.0, 0.5, 0.5, 0.0], prior);
+/// ```
+///
+#[cfg(feature = "alloc")]
+pub fn predict_fractional<F: Float>(
+    pdf: &[F],
+    offset: F,
+    kernel: &[F],
+    mode: EdgeHandling<F>,
+) -> Vec<F> {
+    let cells = offset.floor();
+    let fraction = offset - cells;
+    let cells = cells.to_i64().expect("offset must be finite");
+    if fraction == F::zero() {
+        return predict(pdf, cells, kernel, mode);
+    }
+
+    // Element `j` of a kernel of length `n` moves probability by `j - n / 2` cells, so the
+    // interpolated kernel is padded at the front for odd `n` to keep the kernel in place.
+    let n = kernel.len();
+    let pad = n % 2;
+    let mut interpolated = vec![F::zero(); n + 1 + pad];
+    for (j, &w) in kernel.iter().enumerate() {
+        interpolated[pad + j] = interpolated[pad + j] + w * (F::one() - fraction);
+        interpolated[pad + j + 1] = interpolated[pad + j + 1] + w * fraction;
+    }
+    predict(pdf, cells, &interpolated, mode)
+}
+
+/// Performs the discrete Bayes filter prediction step with a transition matrix, generating the
+/// prior.
+///
+/// `transition[i][j]` is the probability of moving from cell `i` to cell `j`, so each row sums
+/// to one, and the prior of cell `j` is the sum of `pdf[i] * transition[i][j]` over all cells
+/// `i`. Unlike `predict`, which moves the probability of every cell the same way, this can model
+/// motion that depends on the position, e.g., walls that block the movement.
+///
+/// # Panics
+///
+/// Panics if `transition` is not a `pdf.len()` x `pdf.len()` matrix.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::predict_matrix;
+///
+/// // Moves one cell to the right, except in the last cell, which is blocked by a wall.
+/// let transition: [&[f64]; 3] = [
+///     &[0.0, 1.0, 0.0],
+///     &[0.0, 0.0, 1.0],
+///     &[0.0, 0.0, 1.0],
+/// ];
+/// let prior = predict_matrix(&[0.5, 0.25, 0.25], &transition);
+/// assert_eq!(vec![0.0, 0.5, 0.5], prior);
+/// ```
+///
+#[cfg(feature = "alloc")]
+pub fn predict_matrix<F: Float>(pdf: &[F], transition: &[&[F]]) -> Vec<F> {
+    let n = pdf.len();
+    assert_eq!(n, transition.len(), "transition must have a row for each cell");
+    let mut prior = vec![F::zero(); n];
+    for (&p, row) in pdf.iter().zip(transition) {
+        assert_eq!(n, row.len(), "transition must have a column for each cell");
+        for (o, &t) in prior.iter_mut().zip(row.iter()) {
+            *o = *o + p * t;
+        }
+    }
+    prior
+}
+
+/// Performs the discrete Bayes filter prediction step in place, reusing its buffers across calls.
+///
+/// Unlike `predict`, which allocates the prior in each call, the predictor keeps a scratch copy
+/// of the distribution and overwrites the distribution with the prior, so a filter that runs at
+/// a high rate does not allocate after the first step. If the output should not replace the
+/// distribution, `Predictor::predict_into` writes it into a given buffer instead, which is only
+/// reallocated when it has to grow.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{EdgeHandling, Predictor};
+///
+/// let mut predictor = Predictor::new();
+/// let mut pdf = vec![1.0, 0.0, 0.0, 0.0];
+/// for _ in 0..3 {
+///     predictor.predict(&mut pdf, 1, &[1.0], EdgeHandling::Wrap);
+/// }
+/// assert_eq!(vec![0.0, 0.0, 0.0, 1.0], pdf);
+///
+/// let mut prior = Vec::new();
+/// predictor.predict_into(&pdf, 1, &[1.0], EdgeHandling::Wrap, &mut prior);
+/// assert_eq!(vec![1.0, 0.0, 0.0, 0.0], prior);
+/// ```
+///
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+pub struct Predictor<F> {
+    scratch: Vec<F>,
+}
+
+#[cfg(feature = "alloc")]
+impl<F: Float> Predictor<F> {
+    /// Returns a predictor without allocated buffers.
+    pub fn new() -> Self {
+        Predictor { scratch: Vec::new() }
+    }
+
+    /// Replaces `pdf` with the prior of the prediction step, see `predict`.
+    ///
+    /// For `EdgeHandling::Valid`, `pdf` is shortened to the length of the prior.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kernel` is longer than `pdf`.
+    ///
+    pub fn predict(&mut self, pdf: &mut Vec<F>, offset: i64, kernel: &[F], mode: EdgeHandling<F>) {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(pdf);
+        pdf.truncate(prior_len(pdf.len(), kernel.len(), mode));
+        predict_into(&self.scratch, offset, kernel, mode, pdf);
+    }
+
+    /// Writes the prior of the prediction step of `pdf` into `out`, see `predict`.
+    ///
+    /// `out` is resized to the length of the prior, reusing its allocation, so it is only
+    /// reallocated when it has to grow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kernel` is longer than `pdf`.
+    ///
+    pub fn predict_into(
+        &mut self,
+        pdf: &[F],
+        offset: i64,
+        kernel: &[F],
+        mode: EdgeHandling<F>,
+        out: &mut Vec<F>,
+    ) {
+        out.clear();
+        out.resize(prior_len(pdf.len(), kernel.len(), mode), F::zero());
+        predict_into(pdf, offset, kernel, mode, out);
+    }
+}
+
+/// Returns the length of the prior of the prediction step of a pdf of length `len`.
+#[cfg(feature = "alloc")]
+fn prior_len<F>(len: usize, kernel_len: usize, mode: EdgeHandling<F>) -> usize {
+    match mode {
+        EdgeHandling::Valid => (len + 1).saturating_sub(kernel_len),
+        _ => len,
+    }
+}
+
+/// Performs the discrete Bayes filter prediction step on a 2D grid, generating the prior.
+///
+/// `pdf` and `kernel` are given as flat slices in row-major order together with their
+/// `(rows, cols)` shape, and `offset` is the `(row, col)` displacement. The edge handling
+/// `mode` applies to both axes. The result has the same shape as `pdf`, except for
+/// `EdgeHandling::Valid`, where it has shape `(rows - kernel_rows + 1, cols - kernel_cols + 1)`.
+///
+/// Since the measurement update is an elementwise product, `update` can be applied to 2D
+/// grids in the same representation directly.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{predict_2d, EdgeHandling};
+///
+/// let pdf = [
+///     1.0, 0.0, 0.0,
+///     0.0, 0.0, 0.0,
+///     0.0, 0.0, 0.0,
+/// ];
+/// let kernel = [1.0];
+///
+/// let prior = predict_2d(&pdf, (3, 3), (1, 2), &kernel, (1, 1), EdgeHandling::Wrap);
+/// assert_eq!(1.0, prior[1 * 3 + 2]);
+/// ```
+///
+#[cfg(feature = "alloc")]
+pub fn predict_2d<F: Float>(
+    pdf: &[F],
+    shape: (usize, usize),
+    offset: (i64, i64),
+    kernel: &[F],
+    kernel_shape: (usize, usize),
+    mode: EdgeHandling<F>,
+) -> Vec<F> {
+    match mode {
+        EdgeHandling::Constant(c) => convolve2d(
+            &shift2d(pdf, shape, offset, ShiftMode::Extend(c)),
+            shape,
+            kernel,
+            kernel_shape,
+            ConvolutionMode::Extended(c),
+        ),
+        EdgeHandling::Wrap => convolve2d(
+            &shift2d(pdf, shape, offset, ShiftMode::Wrap),
+            shape,
+            kernel,
+            kernel_shape,
+            ConvolutionMode::Wrap,
+        ),
+        EdgeHandling::Valid => {
+            let mut prior = convolve2d(
+                &shift2d(pdf, shape, offset, ShiftMode::Extend(F::zero())),
+                shape,
+                kernel,
+                kernel_shape,
+                ConvolutionMode::Valid,
+            );
+            normalize(&mut prior);
+            prior
+        }
     }
 }
 
-#[cfg(all(test, feature="std"))]
+/// Determines how the cells of a discrete distribution are arranged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// The cells lie on a line; the first and last cell are the furthest apart.
+    Linear,
+    /// The cells lie on a circle; the last cell is adjacent to the first.
+    Circular,
+}
+
+/// Returns the maximum a posteriori (MAP) estimate of the distribution `pdf`, i.e., the
+/// index of the most likely cell together with its probability. Ties resolve to the lowest index.
+///
+/// # Panics
+///
+/// Panics if `pdf` is empty.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::map_estimate;
+///
+/// let pdf = [0.1, 0.2, 0.6, 0.1];
+/// assert_eq!((2, 0.6), map_estimate(&pdf));
+/// ```
+///
+pub fn map_estimate<F: Float>(pdf: &[F]) -> (usize, F) {
+    assert!(!pdf.is_empty(), "cannot estimate from an empty distribution");
+    pdf.iter()
+        .enumerate()
+        .fold((0, pdf[0]), |(ix, max), (i, &p)| if p > max { (i, p) } else { (ix, max) })
+}
+
+/// Returns the probability-weighted expected index of the distribution `pdf`.
+///
+/// For a `Topology::Linear` distribution this is the plain weighted average of the indices.
+/// For a `Topology::Circular` distribution the indices are mapped onto the unit circle before
+/// averaging, so the result lies in `[0, pdf.len())` and probability mass on both sides of
+/// the wrap-around boundary is averaged across it rather than through the middle.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{mean_index, Topology};
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let pdf = [0.0, 0.25, 0.5, 0.25, 0.0];
+/// assert_approx_eq!(2.0_f64, mean_index(&pdf, Topology::Linear));
+/// assert_approx_eq!(2.0_f64, mean_index(&pdf, Topology::Circular));
+/// ```
+///
+pub fn mean_index<F: Float>(pdf: &[F], topology: Topology) -> F {
+    match topology {
+        Topology::Linear => {
+            let (weighted, sum) = pdf.iter().enumerate().fold(
+                (F::zero(), F::zero()),
+                |(weighted, sum), (i, &p)| (weighted + F::from(i).unwrap() * p, sum + p),
+            );
+            weighted / sum
+        }
+        Topology::Circular => circular_mean_index(pdf),
+    }
+}
+
+/// Returns the probability-weighted expected index of the circular distribution `pdf`.
+///
+/// The indices are mapped onto the unit circle, averaged, and the angle of the average is
+/// mapped back to an index in `[0, pdf.len())`. This is the mean for beliefs over a looped
+/// track, where the last cell is adjacent to the first.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::circular_mean_index;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// // Belief split between the last and the first cell.
+/// let pdf = [0.5, 0.0, 0.0, 0.0, 0.5];
+/// assert_approx_eq!(4.5_f64, circular_mean_index(&pdf));
+/// ```
+///
+pub fn circular_mean_index<F: Float>(pdf: &[F]) -> F {
+    let n = F::from(pdf.len()).unwrap();
+    let two_pi = F::from(2.0 * core::f64::consts::PI).unwrap();
+    let (sin, cos) = pdf.iter().enumerate().fold(
+        (F::zero(), F::zero()),
+        |(sin, cos), (i, &p)| {
+            let angle = two_pi * F::from(i).unwrap() / n;
+            (sin + p * angle.sin(), cos + p * angle.cos())
+        },
+    );
+    let angle = sin.atan2(cos);
+    let angle = if angle < F::zero() { angle + two_pi } else { angle };
+    angle * n / two_pi
+}
+
+/// Returns the Shannon entropy `-sum(p * ln(p))` of the distribution `pdf` in nats,
+/// using the convention `0 * ln(0) = 0`. `pdf` is assumed to be normalized.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::entropy;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let pdf = [0.5, 0.5, 0.0];
+/// assert_approx_eq!(entropy(&pdf), 2.0_f64.ln());
+/// ```
+///
+pub fn entropy<F: Float>(pdf: &[F]) -> F {
+    pdf.iter()
+        .filter(|&&p| p > F::zero())
+        .fold(F::zero(), |h, &p| h - p * p.ln())
+}
+
+/// A normalized discrete distribution, i.e., non-negative probabilities that sum to one.
+///
+/// Enable the `serde` feature to serialize and deserialize it as a sequence of probabilities.
+/// Deserialization checks that the probabilities are normalized.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{BayesError, Distribution};
+///
+/// let prior = Distribution::new(vec![0.25, 0.25, 0.25, 0.25]).unwrap();
+/// let posterior = prior.update(&[0.1, 0.1, 0.1, 0.7]).unwrap();
+/// assert_eq!(3, filter::discrete_bayes::map_estimate(&posterior).0);
+///
+/// assert_eq!(Err(BayesError::NotNormalized), Distribution::new(vec![1.0, 1.0]));
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Vec<F>", into = "Vec<F>"))]
+pub struct Distribution<F: Float>(Vec<F>);
+
+#[cfg(feature = "alloc")]
+impl<F: Float> Distribution<F> {
+    /// Returns the distribution with the probabilities `pdf`, or `BayesError::NotNormalized` if
+    /// they are negative, not finite or do not sum to one.
+    pub fn new(pdf: Vec<F>) -> Result<Self, BayesError> {
+        let valid = pdf.iter().all(|p| p.is_finite() && *p >= F::zero());
+        let sum = pdf.iter().fold(F::zero(), |sum, p| sum + *p);
+        if valid && (sum - F::one()).abs() <= F::epsilon().sqrt() {
+            Ok(Distribution(pdf))
+        } else {
+            Err(BayesError::NotNormalized)
+        }
+    }
+
+    /// Returns the distribution obtained by normalizing `values`, or `BayesError::ZeroSum`
+    /// if they cannot be normalized.
+    pub fn from_unnormalized(mut values: Vec<F>) -> Result<Self, BayesError> {
+        let sum = normalize(&mut values);
+        if sum == F::zero() || !sum.is_finite() {
+            return Err(BayesError::ZeroSum);
+        }
+        Distribution::new(values)
+    }
+
+    /// Returns the probabilities of the distribution.
+    pub fn into_inner(self) -> Vec<F> {
+        self.0
+    }
+
+    /// Returns the posterior of this prior given the `likelihood`, see `update`.
+    ///
+    /// Returns `BayesError::NotNormalized` if the posterior is not a valid distribution, e.g.,
+    /// because of a negative likelihood.
+    pub fn update(&self, likelihood: &[F]) -> Result<Self, BayesError> {
+        update(likelihood, &self.0).and_then(Distribution::new)
+    }
+
+    /// Returns the prediction of this distribution, see `predict`, normalized to sum to one.
+    ///
+    /// The prediction loses or gains probability mass if the kernel does not sum to one or
+    /// `mode` shifts in a constant other than zero, so it is renormalized. Returns
+    /// `BayesError::ZeroSum` if it cannot be normalized, and `BayesError::NotNormalized` if it
+    /// has negative probabilities, e.g., from a negative kernel.
+    pub fn predict(
+        &self,
+        offset: i64,
+        kernel: &[F],
+        mode: EdgeHandling<F>,
+    ) -> Result<Self, BayesError> {
+        Distribution::from_unnormalized(predict(&self.0, offset, kernel, mode))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: Float> Deref for Distribution<F> {
+    type Target = [F];
+
+    fn deref(&self) -> &[F] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: Float> TryFrom<Vec<F>> for Distribution<F> {
+    type Error = BayesError;
+
+    fn try_from(pdf: Vec<F>) -> Result<Self, BayesError> {
+        Distribution::new(pdf)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: Float> From<Distribution<F>> for Vec<F> {
+    fn from(distribution: Distribution<F>) -> Vec<F> {
+        distribution.0
+    }
+}
+
+/// A discrete Bayes filter that owns its belief, i.e., the probability distribution over the
+/// cells, together with the motion model, so that predictions and updates only take the
+/// displacement and the likelihood of each step.
+///
+/// The prediction shifts the belief by the displacement and convolves it with `kernel`, the
+/// distribution of the motion error, applying the edge handling `mode`, see `predict`. The
+/// update multiplies the belief with the likelihood of the measurement, see `update`.
+///
+/// # Example
+///
+/// ```
+/// use filter::discrete_bayes::{lh_hall_sense, map_estimate, DiscreteBayesFilter, EdgeHandling};
+///
+/// let hallway = [1, 1, 0, 0, 0, 0, 0, 0, 1, 0];
+/// let mut filter = DiscreteBayesFilter::new(vec![0.1; 10], vec![0.1, 0.8, 0.1], EdgeHandling::Wrap);
+///
+/// // The robot starts at cell 0 and moves one cell per step, sensing whether there is a door.
+/// for position in 0..4 {
+///     if position > 0 {
+///         filter.predict(1);
+///     }
+///     let likelihood = lh_hall_sense(&hallway, &hallway[position], 0.75);
+///     filter.update(&likelihood).unwrap();
+/// }
+/// assert_eq!(3, map_estimate(&filter.belief).0);
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct DiscreteBayesFilter<F> {
+    /// Current probability distribution over the cells.
+    pub belief: Vec<F>,
+    /// Distribution of the motion error, centered on the displacement.
+    pub kernel: Vec<F>,
+    /// Handling of the boundaries in the prediction.
+    pub mode: EdgeHandling<F>,
+    predictor: Predictor<F>,
+}
+
+#[cfg(feature = "alloc")]
+impl<F: Float> DiscreteBayesFilter<F> {
+    /// Returns a filter with the initial belief `belief`, the motion error `kernel` and the edge
+    /// handling `mode`.
+    pub fn new(belief: Vec<F>, kernel: Vec<F>, mode: EdgeHandling<F>) -> Self {
+        DiscreteBayesFilter {
+            belief,
+            kernel,
+            mode,
+            predictor: Predictor::new(),
+        }
+    }
+
+    /// Predicts the belief after a motion by `offset` cells, see `predict`.
+    ///
+    /// For `EdgeHandling::Valid`, the belief is shortened to the length of the prior.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the kernel is longer than the belief.
+    ///
+    pub fn predict(&mut self, offset: i64) {
+        self.predictor.predict(&mut self.belief, offset, &self.kernel, self.mode);
+    }
+
+    /// Updates the belief with the `likelihood` of a measurement, see `update`.
+    ///
+    /// Returns an error and leaves the belief unchanged if the likelihood does not have the
+    /// length of the belief or the measurement is impossible under the belief.
+    pub fn update(&mut self, likelihood: &[F]) -> Result<(), BayesError> {
+        self.belief = update(likelihood, &self.belief)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use assert_approx_eq::assert_approx_eq;
 
+    use crate::test_util;
+
     use super::*;
 
     #[test]
@@ -196,4 +1021,496 @@ mod tests {
             assert_approx_eq!(reference[i], result[i]);
         }
     }
+
+    #[test]
+    fn test_normalize_zero_sum() {
+        let mut pdf = [0.0, 0.0, 0.0, 0.0];
+
+        let sum = normalize(&mut pdf);
+
+        assert_eq!(0.0, sum);
+        assert_eq!([0.0, 0.0, 0.0, 0.0], pdf);
+    }
+
+    #[test]
+    fn test_normalize_non_finite() {
+        let mut pdf = [1.0, f64::INFINITY, 1.0];
+
+        let sum = normalize(&mut pdf);
+
+        assert!(sum.is_infinite());
+        assert_eq!([1.0, f64::INFINITY, 1.0], pdf);
+    }
+
+    #[test]
+    fn test_update() {
+        let prior = [0.25, 0.25, 0.25, 0.25];
+        let likelihood = [1.0, 3.0, 0.0, 0.0];
+
+        let result = update(&likelihood, &prior).unwrap();
+        let reference = [0.25, 0.75, 0.0, 0.0];
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i], result[i]);
+        }
+    }
+
+    #[test]
+    fn test_update_length_mismatch() {
+        let prior = [0.25, 0.25, 0.25, 0.25];
+        let likelihood = [1.0, 3.0, 0.0];
+
+        let result = update(&likelihood, &prior);
+        assert_eq!(
+            Err(BayesError::LengthMismatch {
+                likelihood: 3,
+                prior: 4
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn test_update_zero_sum() {
+        let prior = [0.5, 0.5, 0.0, 0.0];
+        let likelihood = [0.0, 0.0, 1.0, 1.0];
+
+        let result = update(&likelihood, &prior);
+        assert_eq!(Err(BayesError::ZeroSum), result);
+    }
+
+    #[test]
+    fn test_update_into_non_finite_sum() {
+        let prior = [0.5, 0.5];
+        let mut out = [0.0; 2];
+
+        assert_eq!(Err(BayesError::ZeroSum), update_into(&[f64::INFINITY, 1.0], &prior, &mut out));
+        assert_eq!(Err(BayesError::ZeroSum), update_into(&[f64::NAN, 1.0], &prior, &mut out));
+        assert_eq!(Err(BayesError::ZeroSum), update_into(&[f64::MAX, f64::MAX], &[1.0, 1.0], &mut out));
+        assert_eq!(Err(BayesError::ZeroSum), update(&[f64::INFINITY, 1.0], &prior));
+    }
+
+    #[test]
+    fn test_map_estimate_peaked() {
+        let pdf = [0.01, 0.02, 0.05, 0.8, 0.07, 0.03, 0.02];
+
+        let (ix, p) = map_estimate(&pdf);
+
+        assert_eq!(3, ix);
+        assert_approx_eq!(0.8, p);
+    }
+
+    #[test]
+    fn test_mean_index_peaked() {
+        let pdf = [0.0, 0.0, 0.1, 0.8, 0.1, 0.0, 0.0];
+
+        assert_approx_eq!(3.0, mean_index(&pdf, Topology::Linear));
+        assert_approx_eq!(3.0, mean_index(&pdf, Topology::Circular));
+
+        let pdf = [0.0, 0.0, 0.0, 0.8, 0.2, 0.0, 0.0];
+
+        assert_approx_eq!(3.2, mean_index(&pdf, Topology::Linear));
+    }
+
+    #[test]
+    fn test_entropy_uniform() {
+        let mut pdf = [1.0; 8];
+        normalize(&mut pdf);
+
+        assert_approx_eq!(8.0.ln(), entropy(&pdf));
+    }
+
+    #[test]
+    fn test_entropy_delta() {
+        let pdf = [0.0, 0.0, 1.0, 0.0];
+
+        assert_approx_eq!(0.0, entropy(&pdf));
+    }
+
+    #[test]
+    fn test_prediction_2d_diagonal() {
+        let pdf = [
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+        ];
+        let kernel_1d = [0.25, 0.5, 0.25];
+        let kernel: Vec<f64> = kernel_1d
+            .iter()
+            .flat_map(|a| kernel_1d.iter().map(move |b| a * b))
+            .collect();
+
+        let result = predict_2d(&pdf, (5, 5), (1, 1), &kernel, (3, 3), EdgeHandling::Wrap);
+        let reference = [
+            0.0, 0.0,    0.0,   0.0,    0.0,
+            0.0, 0.0625, 0.125, 0.0625, 0.0,
+            0.0, 0.125,  0.25,  0.125,  0.0,
+            0.0, 0.0625, 0.125, 0.0625, 0.0,
+            0.0, 0.0,    0.0,   0.0,    0.0,
+        ];
+        dbg!(&result);
+        dbg!(&reference);
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i], result[i]);
+        }
+
+        let result = predict_2d(&result, (5, 5), (1, 1), &[1.0], (1, 1), EdgeHandling::Constant(0.0));
+        assert_eq!((3 * 5 + 3, 0.25), map_estimate(&result));
+    }
+
+    #[test]
+    fn test_update_into_matches_update() {
+        let prior = [0.1, 0.2, 0.3, 0.4];
+        let likelihood = [0.9, 0.1, 0.5, 0.2];
+
+        let reference = update(&likelihood, &prior).unwrap();
+        let mut result = [0.0; 4];
+        update_into(&likelihood, &prior, &mut result).unwrap();
+
+        assert_eq!(reference.as_slice(), &result);
+    }
+
+    #[test]
+    fn test_predict_into_matches_predict() {
+        let pdf = {
+            let mut pdf = [0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 8.0];
+            normalize(&mut pdf);
+            pdf
+        };
+        let kernel = [0.25, 0.5, 0.125, 0.125];
+
+        for &offset in &[-8, -3, 0, 1, 3, 9] {
+            let reference = predict(&pdf, offset, &kernel, EdgeHandling::Wrap);
+            let mut result = [0.0; 7];
+            predict_into(&pdf, offset, &kernel, EdgeHandling::Wrap, &mut result);
+            assert_eq!(reference.as_slice(), &result);
+
+            let reference = predict(&pdf, offset, &kernel, EdgeHandling::Constant(10.0));
+            let mut result = [0.0; 7];
+            predict_into(&pdf, offset, &kernel, EdgeHandling::Constant(10.0), &mut result);
+            assert_eq!(reference.as_slice(), &result);
+        }
+    }
+
+    #[test]
+    fn test_prediction_valid_kernel_3() {
+        let pdf = [0.1, 0.2, 0.4, 0.2, 0.1];
+        let kernel = [0.25, 0.5, 0.25];
+
+        let result = predict(&pdf, 0, &kernel, EdgeHandling::Valid);
+        let reference = [0.3, 0.4, 0.3];
+        dbg!(&result);
+        dbg!(&reference);
+
+        debug_assert_eq!(pdf.len() - kernel.len() + 1, result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i], result[i]);
+        }
+
+        let result = predict(&pdf, 1, &kernel, EdgeHandling::Valid);
+        let reference = [0.16, 0.36, 0.48];
+        dbg!(&result);
+        dbg!(&reference);
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i], result[i]);
+        }
+    }
+
+    #[test]
+    fn test_predict_into_valid_matches_predict() {
+        let pdf = [0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 8.0];
+        let kernel = [0.25, 0.5, 0.125, 0.125];
+
+        for &offset in &[-2, 0, 1, 3] {
+            let reference = predict(&pdf, offset, &kernel, EdgeHandling::Valid);
+            let mut result = [0.0; 4];
+            predict_into(&pdf, offset, &kernel, EdgeHandling::Valid, &mut result);
+
+            debug_assert_eq!(reference.len(), result.len());
+            for i in 0..reference.len() {
+                assert_approx_eq!(reference[i], result[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_prediction_2d_valid() {
+        let pdf = [
+            0.0, 0.0, 0.0, 0.0,
+            0.0, 0.5, 0.0, 0.0,
+            0.0, 0.0, 0.5, 0.0,
+            0.0, 0.0, 0.0, 0.0,
+        ];
+        let kernel = [
+            0.0, 0.0, 0.0,
+            0.0, 0.5, 0.5,
+            0.0, 0.0, 0.0,
+        ];
+
+        let result = predict_2d(&pdf, (4, 4), (0, 0), &kernel, (3, 3), EdgeHandling::Valid);
+        let third = 1.0 / 3.0;
+        let reference = [
+            third, third,
+            0.0, third,
+        ];
+        dbg!(&result);
+
+        debug_assert_eq!(reference.len(), result.len());
+        for i in 0..reference.len() {
+            assert_approx_eq!(reference[i], result[i]);
+        }
+    }
+
+    #[test]
+    fn test_distribution_validation() {
+        assert_eq!(Err(BayesError::NotNormalized), Distribution::new(vec![0.5, 0.6]));
+        assert_eq!(Err(BayesError::NotNormalized), Distribution::new(vec![1.5, -0.5]));
+        assert_eq!(Err(BayesError::ZeroSum), Distribution::from_unnormalized(vec![0.0, 0.0]));
+
+        let pdf = Distribution::from_unnormalized(vec![1.0, 3.0]).unwrap();
+        assert_eq!(&[0.25, 0.75], &*pdf);
+        assert_eq!(Ok(pdf), Distribution::new(vec![0.25, 0.75]));
+    }
+
+    #[test]
+    fn test_distribution_update_validates() {
+        let pdf = Distribution::new(vec![0.5, 0.5]).unwrap();
+
+        assert_eq!(Ok(pdf.clone()), pdf.update(&[2.0, 2.0]));
+        assert_eq!(Err(BayesError::NotNormalized), pdf.update(&[-1.0, 2.0]));
+        assert!(pdf.update(&[f64::INFINITY, 1.0]).is_err());
+        assert!(pdf.update(&[f64::NAN, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_distribution_predict_renormalizes() {
+        let pdf = Distribution::new(vec![0.0, 1.0, 0.0, 0.0]).unwrap();
+
+        let spread = pdf.predict(1, &[1.0, 1.0, 2.0], EdgeHandling::Wrap).unwrap();
+        assert_eq!(&[0.0, 0.25, 0.25, 0.5], &*spread);
+
+        let constant = pdf.predict(0, &[1.0], EdgeHandling::Constant(0.5)).unwrap();
+        assert_approx_eq!(constant.iter().sum::<f64>(), 1.0);
+        assert!(Distribution::new(constant.into_inner()).is_ok());
+
+        let edge = Distribution::new(vec![0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(Err(BayesError::ZeroSum), edge.predict(1, &[1.0], EdgeHandling::Constant(0.0)));
+        assert_eq!(Err(BayesError::NotNormalized), pdf.predict(0, &[-1.0, 2.0, 0.0], EdgeHandling::Wrap));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_distribution_serde() {
+        let pdf = Distribution::new(vec![0.25, 0.5, 0.25]).unwrap();
+
+        let json = serde_json::to_string(&pdf).unwrap();
+        assert_eq!("[0.25,0.5,0.25]", json);
+        assert_eq!(pdf, serde_json::from_str(&json).unwrap());
+
+        assert!(serde_json::from_str::<Distribution<f64>>("[0.5,0.6]").is_err());
+    }
+
+    #[test]
+    fn test_circular_mean_index_wraps() {
+        let mut pdf = vec![0.0; 10];
+        pdf[0] = 0.5;
+        pdf[9] = 0.5;
+
+        assert_approx_eq!(circular_mean_index(&pdf), 9.5);
+        assert_approx_eq!(mean_index(&pdf, Topology::Circular), 9.5);
+        assert_approx_eq!(mean_index(&pdf, Topology::Linear), 4.5);
+
+        pdf[0] = 0.6;
+        pdf[9] = 0.4;
+        let mean = circular_mean_index(&pdf);
+        assert!(!(0.5..=9.5).contains(&mean));
+    }
+
+    #[test]
+    fn test_update_with_floor() {
+        let prior = [0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0, 0.0];
+        let likelihood = [1.0, 1.0, 0.001, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let floor = 0.02;
+
+        let posterior = update_with_floor(&likelihood, &prior, floor).unwrap();
+        assert!(posterior.iter().all(|&p| p >= floor - 1e-12));
+        assert_approx_eq!(posterior.iter().sum::<f64>(), 1.0);
+        assert_approx_eq!(posterior[2], floor);
+        assert_approx_eq!(posterior[3], 1.0 - 7.0 * floor);
+
+        // The belief recovers when the object is found where it was considered impossible.
+        let mut belief = posterior;
+        for _ in 0..10 {
+            belief = update_with_floor(&[0.1, 0.1, 0.1, 0.1, 0.1, 0.1, 0.9, 0.1], &belief, floor)
+                .unwrap();
+        }
+        assert_eq!(map_estimate(&belief).0, 6);
+
+        let plain = update(&likelihood, &prior).unwrap();
+        let unfloored = update_with_floor(&likelihood, &prior, 0.0).unwrap();
+        for (p, q) in unfloored.iter().zip(&plain) {
+            assert_approx_eq!(p, q);
+        }
+        assert_eq!(
+            update_with_floor(&[1.0, 1.0], &[1.0, 0.0, 0.0], 0.1),
+            Err(BayesError::LengthMismatch { likelihood: 2, prior: 3 })
+        );
+    }
+
+    #[test]
+    fn test_gaussian_likelihood() {
+        let likelihood = gaussian_likelihood(&[0.0, 1.0, 2.0], 1.0, 1.0);
+
+        // 1 / sqrt(2 pi) and exp(-1 / 2) / sqrt(2 pi).
+        assert_approx_eq!(0.24197072451914337, likelihood[0]);
+        assert_approx_eq!(0.3989422804014327, likelihood[1]);
+        assert_approx_eq!(0.24197072451914337, likelihood[2]);
+    }
+
+    #[test]
+    fn test_lh_hall_sense() {
+        let likelihood = lh_hall_sense(&[true, false, true], &true, 0.75);
+        assert_eq!(vec![3.0, 1.0, 3.0], likelihood);
+
+        let posterior = update(&likelihood, &[1.0 / 3.0; 3]).unwrap();
+        assert_approx_eq!(3.0 / 7.0, posterior[0]);
+        assert_approx_eq!(1.0 / 7.0, posterior[1]);
+    }
+
+    #[test]
+    fn test_predictor_matches_predict_without_allocating() {
+        let kernel = [0.25, 0.5, 0.125, 0.125];
+        let mut predictor = Predictor::new();
+        let mut pdf = vec![0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 8.0];
+        normalize(&mut pdf);
+        let mut reference = pdf.clone();
+        let mut out = Vec::new();
+
+        predictor.predict(&mut pdf, 1, &kernel, EdgeHandling::Wrap);
+        predictor.predict_into(&reference, 1, &kernel, EdgeHandling::Wrap, &mut out);
+        reference = predict(&reference, 1, &kernel, EdgeHandling::Wrap);
+        assert_eq!(reference, pdf);
+        assert_eq!(reference, out);
+
+        // After the first step, neither method allocates.
+        for &offset in &[-3, 0, 2, 5] {
+            let mode = EdgeHandling::Constant(0.0);
+            let allocations = test_util::allocations();
+            predictor.predict(&mut pdf, offset, &kernel, mode);
+            predictor.predict_into(&reference, offset, &kernel, mode, &mut out);
+            assert_eq!(allocations, test_util::allocations());
+
+            reference = predict(&reference, offset, &kernel, mode);
+            assert_eq!(reference, pdf);
+            assert_eq!(reference, out);
+        }
+
+        let allocations = test_util::allocations();
+        predictor.predict_into(&pdf, 1, &kernel, EdgeHandling::Valid, &mut out);
+        predictor.predict(&mut pdf, 1, &kernel, EdgeHandling::Valid);
+        assert_eq!(allocations, test_util::allocations());
+
+        reference = predict(&reference, 1, &kernel, EdgeHandling::Valid);
+        assert_eq!(reference, pdf);
+        assert_eq!(reference, out);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_predict_matches_predict() {
+        let pdf: Vec<f64> = (0..50).map(|i| f64::from(i % 7)).collect();
+        let kernel = [0.1, 0.2, 0.4, 0.2, 0.1];
+        for &offset in &[-3, 0, 4] {
+            assert_eq!(
+                predict(&pdf, offset, &kernel, EdgeHandling::Wrap),
+                par_predict(&pdf, offset, &kernel, EdgeHandling::Wrap)
+            );
+            assert_eq!(
+                predict(&pdf, offset, &kernel, EdgeHandling::Constant(0.5)),
+                par_predict(&pdf, offset, &kernel, EdgeHandling::Constant(0.5))
+            );
+            assert_eq!(
+                predict(&pdf, offset, &kernel, EdgeHandling::Valid),
+                par_predict(&pdf, offset, &kernel, EdgeHandling::Valid)
+            );
+        }
+    }
+
+    #[test]
+    fn test_predict_fractional() {
+        let mut pdf = [0.0; 8];
+        pdf[2] = 1.0;
+
+        let prior = predict_fractional(&pdf, 1.5, &[1.0], EdgeHandling::Wrap);
+        assert_eq!(vec![0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0], prior);
+
+        let prior = predict_fractional(&pdf, -0.25, &[1.0], EdgeHandling::Wrap);
+        assert_eq!(vec![0.0, 0.25, 0.75, 0.0, 0.0, 0.0, 0.0, 0.0], prior);
+
+        assert_eq!(
+            predict(&pdf, 2, &[0.25, 0.5, 0.25], EdgeHandling::Constant(0.0)),
+            predict_fractional(&pdf, 2.0, &[0.25, 0.5, 0.25], EdgeHandling::Constant(0.0))
+        );
+
+        // The kernel spreads both parts of the interpolated probability.
+        let pdf = [0.0, 0.1, 0.6, 0.3, 0.0, 0.0, 0.0, 0.0];
+        for kernel in &[vec![0.1, 0.8, 0.1], vec![0.2, 0.3, 0.3, 0.2]] {
+            let prior = predict_fractional(&pdf, 1.5, kernel, EdgeHandling::Wrap);
+            let one = predict(&pdf, 1, kernel, EdgeHandling::Wrap);
+            let two = predict(&pdf, 2, kernel, EdgeHandling::Wrap);
+            for i in 0..pdf.len() {
+                assert_approx_eq!(0.5 * one[i] + 0.5 * two[i], prior[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_predict_matrix() {
+        let pdf = [0.1, 0.2, 0.4, 0.3, 0.0];
+
+        let identity: Vec<Vec<f64>> = (0..5)
+            .map(|i| (0..5).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+            .collect();
+        let rows: Vec<&[f64]> = identity.iter().map(|r| r.as_slice()).collect();
+        assert_eq!(pdf.to_vec(), predict_matrix(&pdf, &rows));
+
+        for &offset in &[-2, 1, 3] {
+            let shifted: Vec<Vec<f64>> = (0..5_i64)
+                .map(|i| {
+                    (0..5)
+                        .map(|j| if j == (i + offset).rem_euclid(5) { 1.0 } else { 0.0 })
+                        .collect()
+                })
+                .collect();
+            let rows: Vec<&[f64]> = shifted.iter().map(|r| r.as_slice()).collect();
+            assert_eq!(shift(&pdf, offset, ShiftMode::Wrap), predict_matrix(&pdf, &rows));
+        }
+    }
+
+    #[test]
+    fn test_discrete_bayes_filter_matches_free_functions() {
+        let kernel = vec![0.1, 0.8, 0.1];
+        let likelihood = [0.2, 0.6, 0.6, 0.2, 0.2, 0.2];
+        let mut filter = DiscreteBayesFilter::new(vec![1.0 / 6.0; 6], kernel.clone(), EdgeHandling::Wrap);
+        let mut reference = vec![1.0 / 6.0; 6];
+
+        for &offset in &[1, 2, -1] {
+            filter.predict(offset);
+            reference = predict(&reference, offset, &kernel, EdgeHandling::Wrap);
+            filter.update(&likelihood).unwrap();
+            reference = update(&likelihood, &reference).unwrap();
+            assert_eq!(reference, filter.belief);
+        }
+
+        // A failed update leaves the belief unchanged.
+        assert_eq!(Err(BayesError::ZeroSum), filter.update(&[0.0; 6]));
+        assert_eq!(reference, filter.belief);
+    }
 }
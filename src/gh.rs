@@ -1,10 +1,21 @@
 /*!
 Provides implementations of and related to the g-h and g-h-k filter.
+
+The filters are generic over `FloatCore`, so they also run in half precision with `half::f16`
+if the `half` feature is enabled, e.g., on embedded targets without a floating point unit.
+The other filters of this crate require `RealField` and are limited to `f32` and `f64`.
 */
 
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 use num_traits::float::FloatCore;
 use num_traits::Float;
 
+use crate::Filter;
+
 /// A g-h filter.
 ///
 /// # Example
@@ -27,6 +38,7 @@ use num_traits::Float;
 /// *  Brookner, "Tracking and Kalman Filters Made Easy". John Wiley and Sons, 1998.
 ///
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct GHFilter<T> {
     /// Filter g gain parameter.
     pub g: T,
@@ -42,6 +54,8 @@ pub struct GHFilter<T> {
     pub x_p: T,
     /// Predicted derivative of the filter state.
     pub dx_p: T,
+    #[cfg(feature = "alloc")]
+    history: Option<Vec<(T, T)>>,
 }
 
 impl<T: FloatCore> GHFilter<T> {
@@ -71,6 +85,30 @@ impl<T: FloatCore> GHFilter<T> {
             dxt: dx0,
             x_p: x0,
             dx_p: dx0,
+            #[cfg(feature = "alloc")]
+            history: None,
+        }
+    }
+
+    /// Enables recording `(xt, dxt)` after each update, e.g., for plotting, see `history`.
+    /// Discards any previously recorded history.
+    #[cfg(feature = "alloc")]
+    pub fn enable_history(&mut self) {
+        self.history = Some(Vec::new());
+    }
+
+    /// Returns `(xt, dxt)` after each update since `enable_history` or `clear_history` was
+    /// called, or `None` if the history is disabled.
+    #[cfg(feature = "alloc")]
+    pub fn history(&self) -> Option<&[(T, T)]> {
+        self.history.as_deref()
+    }
+
+    /// Discards the recorded history, but keeps recording if it is enabled.
+    #[cfg(feature = "alloc")]
+    pub fn clear_history(&mut self) {
+        if let Some(history) = self.history.as_mut() {
+            history.clear();
         }
     }
 
@@ -84,6 +122,14 @@ impl<T: FloatCore> GHFilter<T> {
         let y = z - self.x_p;
         self.dxt = self.dx_p + self.h * (y / self.dt);
         self.xt = self.x_p + self.g * y;
+
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(history) = self.history.as_mut() {
+                history.push((self.xt, self.dxt));
+            }
+        }
+
         self.xt
     }
 
@@ -116,6 +162,23 @@ impl<T: FloatCore> GHFilter<T> {
     }
 }
 
+/// The g-h filter predicts and updates in a single step, so `predict` does nothing and `update`
+/// performs both steps.
+impl<T: FloatCore> Filter for GHFilter<T> {
+    type Measurement = T;
+    type State = T;
+
+    fn predict(&mut self) {}
+
+    fn update(&mut self, z: &T) {
+        GHFilter::update(self, *z);
+    }
+
+    fn state(&self) -> &T {
+        &self.xt
+    }
+}
+
 /// A g-h-k filter.
 ///
 /// # Example
@@ -138,6 +201,7 @@ impl<T: FloatCore> GHFilter<T> {
 /// * Brookner, "Tracking and Kalman Filters Made Easy". John Wiley and Sons, 1998.
 ///
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct GHKFilter<T> {
     /// Filter g gain parameter.
     pub g: T,
@@ -159,6 +223,8 @@ pub struct GHKFilter<T> {
     pub dx_p: T,
     /// Predicted second derivative of the filter state.
     pub ddx_p: T,
+    #[cfg(feature = "alloc")]
+    history: Option<Vec<(T, T, T)>>,
 }
 
 impl<T: FloatCore> GHKFilter<T> {
@@ -193,6 +259,30 @@ impl<T: FloatCore> GHKFilter<T> {
             x_p: x0,
             dx_p: dx0,
             ddx_p: ddx0,
+            #[cfg(feature = "alloc")]
+            history: None,
+        }
+    }
+
+    /// Enables recording `(xt, dxt, ddxt)` after each update, e.g., for plotting, see `history`.
+    /// Discards any previously recorded history.
+    #[cfg(feature = "alloc")]
+    pub fn enable_history(&mut self) {
+        self.history = Some(Vec::new());
+    }
+
+    /// Returns `(xt, dxt, ddxt)` after each update since `enable_history` or `clear_history` was
+    /// called, or `None` if the history is disabled.
+    #[cfg(feature = "alloc")]
+    pub fn history(&self) -> Option<&[(T, T, T)]> {
+        self.history.as_deref()
+    }
+
+    /// Discards the recorded history, but keeps recording if it is enabled.
+    #[cfg(feature = "alloc")]
+    pub fn clear_history(&mut self) {
+        if let Some(history) = self.history.as_mut() {
+            history.clear();
         }
     }
 
@@ -210,6 +300,14 @@ impl<T: FloatCore> GHKFilter<T> {
         self.ddxt = self.ddx_p + two * self.k * y / (self.dt * self.dt);
         self.dxt = self.dx_p + self.h * (y / self.dt);
         self.xt = self.x_p + self.g * y;
+
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(history) = self.history.as_mut() {
+                history.push((self.xt, self.dxt, self.ddxt));
+            }
+        }
+
         self.xt
     }
 
@@ -227,8 +325,8 @@ impl<T: FloatCore> GHKFilter<T> {
         let k = self.k;
         let gh2 = two * g + h;
 
-        ((g * k * (gh2 - four) + h * (g * gh2 + two * h))
-            / (two * k - (g * (h + k) * (gh2 - four))))
+        (g * k * (gh2 - four) + h * (g * gh2 + two * h))
+            / (two * k - (g * (h + k) * (gh2 - four)))
     }
 
     /// Returns the Variance Reduction Factor (VRF) of the state variable
@@ -264,6 +362,23 @@ impl<T: FloatCore> GHKFilter<T> {
     }
 }
 
+/// The g-h-k filter predicts and updates in a single step, so `predict` does nothing and `update`
+/// performs both steps.
+impl<T: FloatCore> Filter for GHKFilter<T> {
+    type Measurement = T;
+    type State = T;
+
+    fn predict(&mut self) {}
+
+    fn update(&mut self, z: &T) {
+        GHKFilter::update(self, *z);
+    }
+
+    fn state(&self) -> &T {
+        &self.xt
+    }
+}
+
 /// Returns g, h, k parameters for optimal smoothing of noise for a given value of g.
 /// This is due to Polge and Bhagavan.
 ///
@@ -338,24 +453,404 @@ pub fn critical_damping_parameters_order_three<T: FloatCore>(theta: T) -> (T, T,
     )
 }
 
-/// Computes the g,h constants for a Benedict-Bornder filter, which minimizes transient errors
+/// Damping of the Benedict-Bordner g-h filter, see `benedict_bordner_constants`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenedictBordner {
+    /// The original Benedict-Bordner relation `h = g^2 / (2 - g)`, which allows ringing.
+    Standard,
+    /// Nearly critically damped, which reduces ringing at the cost of reduced performance.
+    NearCritical,
+}
+
+/// Computes the g,h constants for a Benedict-Bordner filter, which minimizes transient errors
 /// for a g-h filter. Returns the values g,h for a specified g. Strictly speaking, only h
 /// is computed, g is returned unchanged. The default formula for the Benedict-Bordner allows ringing.
 /// We can "nearly" critically damp it; ringing will be reduced, but not entirely eliminated at
 /// the cost of reduced performance.
 ///
+/// # Example
+///
+/// ```
+/// use filter::gh::{benedict_bordner_constants, BenedictBordner};
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let (g, h) = benedict_bordner_constants(0.8_f64, BenedictBordner::NearCritical);
+/// assert_approx_eq!(g, 0.8);
+/// assert_approx_eq!(h, 0.2);
+/// ```
+///
 /// # References
 /// * Brookner, "Tracking and Kalman Filters Made Easy". John Wiley and Sons, 1998.
-pub fn benedict_bornder_constants<T: Float>(g: T, critical: bool) -> (T, T) {
+pub fn benedict_bordner_constants<T: Float>(g: T, damping: BenedictBordner) -> (T, T) {
     let g_sqr = g.powi(2);
-    if critical {
-        (
+    match damping {
+        BenedictBordner::NearCritical => (
             g,
             T::from(0.8).unwrap()
                 * (T::from(2).unwrap() - g_sqr - T::from(2).unwrap() * (T::one() - g_sqr).sqrt())
                 / g_sqr,
-        )
+        ),
+        BenedictBordner::Standard => (g, g_sqr / (T::from(2).unwrap() - g)),
+    }
+}
+
+/// Returns g, h parameters for a g-h filter tracking a target with white noise acceleration of
+/// the variance `process_variance`, measured with noise of the variance `measurement_variance`
+/// every `dt`.
+///
+/// The ratio of process to measurement noise is expressed by the tracking index
+/// `lambda = sqrt(process_variance) * dt^2 / sqrt(measurement_variance)`. g is the steady-state
+/// position gain of the Kalman filter for this tracking index, and h follows from the
+/// Benedict-Bordner relation, see `benedict_bordner_constants`. Noisier measurements yield
+/// smaller gains and a smoother, but more lagging, estimate.
+///
+/// # Example
+///
+/// ```
+/// use filter::gh::{design_gh, GHFilter};
+///
+/// let (g, h) = design_gh(0.1_f64, 4.0, 1.0);
+/// let mut filter = GHFilter::new(0.0, 0.0, g, h, 1.0);
+/// filter.update(1.0);
+/// ```
+///
+/// # References
+/// * Kalata, "The Tracking Index: A Generalized Parameter for α-β and α-β-γ Target Trackers".
+///   IEEE Transactions on Aerospace and Electronic Systems, AES-20(2), 1984.
+/// * Brookner, "Tracking and Kalman Filters Made Easy". John Wiley and Sons, 1998.
+pub fn design_gh<T: Float>(process_variance: T, measurement_variance: T, dt: T) -> (T, T) {
+    let four = T::from(4).unwrap();
+    let eight = T::from(8).unwrap();
+
+    let lambda = process_variance.sqrt() * dt * dt / measurement_variance.sqrt();
+    let root = (lambda * lambda + eight * lambda).sqrt();
+    let g = ((lambda + four) * root - lambda * lambda - eight * lambda) / eight;
+
+    benedict_bordner_constants(g, BenedictBordner::Standard)
+}
+
+/// Returns the g, h, k parameters of the steady-state Kalman filter for a target with piecewise
+/// constant white noise acceleration, given the tracking index `lambda`, i.e., the ratio of the
+/// standard deviation of the motion during one step to the standard deviation of the
+/// measurement noise, see `design_ghk`.
+///
+/// With `s = sqrt(1 - g)`, Kalata's relations for the alpha-beta-gamma filter are
+///
+/// * `g = 1 - s^2`,
+/// * `h = 2 (1 - s)^2`,
+/// * `k = (1 - s)^3 / (1 + s)`,
+///
+/// where `s` is the root in (0, 1) of `lambda s (1 + s) = 2 (1 - s)^3`. k is a quarter of
+/// Kalata's gamma, as the g-h-k filter corrects the acceleration by `2 k / dt^2` instead of
+/// `gamma / (2 dt^2)`.
+///
+/// # Example
+///
+/// ```
+/// use filter::gh::kalata_parameters;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let (g, h, k) = kalata_parameters(1.0_f64 / 3.0);
+/// assert_approx_eq!(g, 0.75);
+/// assert_approx_eq!(h, 0.5);
+/// assert_approx_eq!(k, 1.0 / 12.0);
+/// ```
+///
+/// # References
+/// * Kalata, "The Tracking Index: A Generalized Parameter for α-β and α-β-γ Target Trackers".
+///   IEEE Transactions on Aerospace and Electronic Systems, AES-20(2), 1984.
+/// * Gray, Murray, "A Derivation of an Analytic Expression for the Tracking Index for the
+///   Alpha-Beta-Gamma Filter". IEEE Transactions on Aerospace and Electronic Systems, 29(3), 1993.
+pub fn kalata_parameters<T: Float>(lambda: T) -> (T, T, T) {
+    let one = T::one();
+    let two = T::from(2).unwrap();
+
+    // The left side of lambda s (1 + s) = 2 (1 - s)^3 rises and the right side falls in (0, 1),
+    // so bisect.
+    let (mut low, mut high) = (T::zero(), one);
+    loop {
+        let s = (low + high) / two;
+        if s <= low || s >= high {
+            break;
+        }
+        if lambda * s * (one + s) < two * (one - s).powi(3) {
+            low = s;
+        } else {
+            high = s;
+        }
+    }
+    let s = (low + high) / two;
+
+    (
+        one - s * s,
+        two * (one - s).powi(2),
+        (one - s).powi(3) / (one + s),
+    )
+}
+
+/// Returns g, h, k parameters for a g-h-k filter tracking a target with piecewise constant white
+/// noise acceleration, i.e., acceleration increments of the variance `process_variance` per
+/// step, measured with noise of the variance `measurement_variance` every `dt`.
+///
+/// The gains are the steady-state gains of the Kalman filter for the tracking index
+/// `lambda = sqrt(process_variance) * dt^2 / sqrt(measurement_variance)`, see
+/// `kalata_parameters`. Noisier measurements yield smaller gains and a smoother, but more
+/// lagging, estimate.
+///
+/// # Example
+///
+/// ```
+/// use filter::gh::{design_ghk, GHKFilter};
+///
+/// let (g, h, k) = design_ghk(0.1_f64, 4.0, 1.0);
+/// let mut filter = GHKFilter::new(0.0, 0.0, 0.0, g, h, k, 1.0);
+/// filter.update(1.0);
+/// ```
+///
+/// # References
+/// * Kalata, "The Tracking Index: A Generalized Parameter for α-β and α-β-γ Target Trackers".
+///   IEEE Transactions on Aerospace and Electronic Systems, AES-20(2), 1984.
+pub fn design_ghk<T: Float>(process_variance: T, measurement_variance: T, dt: T) -> (T, T, T) {
+    let lambda = process_variance.sqrt() * dt * dt / measurement_variance.sqrt();
+    kalata_parameters(lambda)
+}
+
+/// Computes the g,h constants for a Benedict-Bordner filter, nearly critically damped if
+/// `critical` is `true`.
+#[deprecated(note = "use `benedict_bordner_constants` with `BenedictBordner` instead")]
+pub fn benedict_bornder_constants<T: Float>(g: T, critical: bool) -> (T, T) {
+    let damping = if critical {
+        BenedictBordner::NearCritical
     } else {
-        (g, g_sqr / (T::from(2).unwrap() - g))
+        BenedictBordner::Standard
+    };
+    benedict_bordner_constants(g, damping)
+}
+
+/// Smooths `data` offline by running a g-h filter forwards and backwards over it and averaging
+/// the two estimates of each sample.
+///
+/// A g-h filter tracking a ramp lags behind it, and the lag of the backward pass has the
+/// opposite sign of the lag of the forward pass, so the average cancels most of the lag and the
+/// noise is reduced further, similar to the RTS smoother for the Kalman filter. The forward pass
+/// is initialised with the first sample and the slope of the first two samples, the backward pass
+/// continues from the final state of the forward pass.
+///
+/// # Example
+///
+/// ```
+/// use filter::gh::gh_smooth;
+///
+/// let data = [0.0, 1.2, 1.9, 3.1, 3.9, 5.2, 5.9, 7.1];
+/// let smoothed = gh_smooth(&data, 0.4, 0.1, 1.0);
+/// assert_eq!(smoothed.len(), data.len());
+/// ```
+#[cfg(feature = "alloc")]
+pub fn gh_smooth<T: Float + FloatCore>(data: &[T], g: T, h: T, dt: T) -> Vec<T> {
+    if data.len() < 2 {
+        return data.to_vec();
+    }
+
+    // The forward pass starts from the first two samples, the backward pass from the final state
+    // of the forward pass, with the direction of time reversed.
+    let mut filter = GHFilter::new(data[0], (data[1] - data[0]) / dt, g, h, dt);
+    let mut forward = vec![data[0]];
+    forward.extend(data[1..].iter().map(|&z| filter.update(z)));
+
+    filter.dxt = -filter.dxt;
+    let mut backward = vec![filter.xt];
+    backward.extend(data[..data.len() - 1].iter().rev().map(|&z| filter.update(z)));
+
+    let two = T::one() + T::one();
+    forward
+        .iter()
+        .zip(backward.iter().rev())
+        .map(|(&f, &b)| (f + b) / two)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_gh_smooth_reduces_error_on_noisy_ramp() {
+        // Uniform noise in (-2, 2).
+        let mut uniform = crate::test_util::uniform(7);
+        let mut noise = || 4.0 * uniform() - 2.0;
+
+        let truth: Vec<f64> = (0..100).map(|t| 0.5 * t as f64).collect();
+        let data: Vec<f64> = truth.iter().map(|x| x + noise()).collect();
+        let (g, h, dt) = (0.3, 0.05, 1.0);
+
+        let mut filter = GHFilter::new(data[0], (data[1] - data[0]) / dt, g, h, dt);
+        let mut forward = vec![data[0]];
+        forward.extend(data[1..].iter().map(|&z| filter.update(z)));
+        let smoothed = gh_smooth(&data, g, h, dt);
+
+        let error = |estimates: &[f64]| {
+            estimates
+                .iter()
+                .zip(&truth)
+                .map(|(x, t)| (x - t).powi(2))
+                .sum::<f64>()
+        };
+        assert_eq!(smoothed.len(), data.len());
+        assert!(error(&smoothed) < 0.5 * error(&forward));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_gh_smooth_short_input() {
+        assert!(gh_smooth::<f64>(&[], 0.5, 0.1, 1.0).is_empty());
+        assert_eq!(gh_smooth(&[3.0], 0.5, 0.1, 1.0), vec![3.0]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_history() {
+        let mut fgh = GHFilter::new(0.0, 0.0, 0.8, 0.2, 1.0);
+        fgh.update(1.0);
+        assert!(fgh.history().is_none());
+
+        fgh.enable_history();
+        for t in 0..10 {
+            fgh.update(t as f64);
+        }
+        let history = fgh.history().unwrap();
+        assert_eq!(history.len(), 10);
+        assert_eq!(*history.last().unwrap(), (fgh.xt, fgh.dxt));
+
+        fgh.clear_history();
+        assert_eq!(Some(&[][..]), fgh.history());
+        fgh.update(10.0);
+        assert_eq!(Some(&[(fgh.xt, fgh.dxt)][..]), fgh.history());
+
+        let mut fghk = GHKFilter::new(0.0, 0.0, 0.0, 0.5, 0.2, 0.05, 1.0);
+        fghk.enable_history();
+        for t in 0..10 {
+            fghk.update(t as f64);
+        }
+        let history = fghk.history().unwrap();
+        assert_eq!(history.len(), 10);
+        assert_eq!(*history.last().unwrap(), (fghk.xt, fghk.dxt, fghk.ddxt));
+    }
+
+    #[test]
+    fn test_benedict_bordner_constants() {
+        let (g, h) = benedict_bordner_constants(0.5_f64, BenedictBordner::Standard);
+        assert_approx_eq!(g, 0.5);
+        assert_approx_eq!(h, 0.25 / 1.5);
+
+        let (g, h) = benedict_bordner_constants(0.8_f64, BenedictBordner::Standard);
+        assert_approx_eq!(g, 0.8);
+        assert_approx_eq!(h, 0.64 / 1.2);
+
+        let (g, h) = benedict_bordner_constants(0.5_f64, BenedictBordner::NearCritical);
+        assert_approx_eq!(g, 0.5);
+        assert_approx_eq!(h, 0.8 * (2.0 - 0.25 - 2.0 * 0.75_f64.sqrt()) / 0.25);
+
+        let (g, h) = benedict_bordner_constants(0.8_f32, BenedictBordner::NearCritical);
+        assert_approx_eq!(g, 0.8);
+        assert_approx_eq!(h, 0.2, 1e-6);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_benedict_bornder_constants_shim() {
+        for &g in &[0.3_f64, 0.5, 0.8] {
+            assert_eq!(
+                benedict_bornder_constants(g, false),
+                benedict_bordner_constants(g, BenedictBordner::Standard)
+            );
+            assert_eq!(
+                benedict_bornder_constants(g, true),
+                benedict_bordner_constants(g, BenedictBordner::NearCritical)
+            );
+        }
+    }
+
+    #[test]
+    fn test_design_gh() {
+        let (g_quiet, h_quiet) = design_gh(1.0_f64, 0.1, 1.0);
+        let (g_noisy, h_noisy) = design_gh(1.0_f64, 10.0, 1.0);
+        assert!(g_noisy < g_quiet);
+        assert!(h_noisy < h_quiet);
+
+        for &(g, h) in &[(g_quiet, h_quiet), (g_noisy, h_noisy)] {
+            assert!(g > 0.0 && g < 1.0);
+            assert_approx_eq!(h, g * g / (2.0 - g));
+        }
+
+        // A tracking index of 1 yields g = 0.75.
+        let (g, _) = design_gh(4.0_f64, 4.0, 1.0);
+        assert_approx_eq!(g, 0.75);
+    }
+
+    #[test]
+    fn test_design_ghk() {
+        let quiet = design_ghk(1.0_f64, 0.1, 1.0);
+        let noisy = design_ghk(1.0_f64, 10.0, 1.0);
+        assert!(noisy.0 < quiet.0);
+        assert!(noisy.1 < quiet.1);
+        assert!(noisy.2 < quiet.2);
+
+        for &(g, h, k) in &[quiet, noisy] {
+            assert!(g > 0.0 && g < 1.0);
+            assert!(h > 0.0 && h < 2.0);
+            assert!(k > 0.0 && k < 0.5);
+        }
+
+        // A tracking index of 1/3 yields sqrt(1 - g) = 0.5.
+        let (g, h, k) = design_ghk(1.0_f64, 9.0, 1.0);
+        assert_approx_eq!(g, 0.75);
+        assert_approx_eq!(h, 0.5);
+        assert_approx_eq!(k, 1.0 / 12.0);
+    }
+
+    #[test]
+    fn test_kalata_parameters() {
+        // sqrt(1 - g) = 0.5.
+        let (g, h, k) = kalata_parameters(1.0_f64 / 3.0);
+        assert_approx_eq!(g, 0.75);
+        assert_approx_eq!(h, 0.5);
+        assert_approx_eq!(k, 1.0 / 12.0);
+
+        // sqrt(1 - g) = 0.2.
+        let (g, h, k) = kalata_parameters(64.0_f64 / 15.0);
+        assert_approx_eq!(g, 0.96);
+        assert_approx_eq!(h, 1.28);
+        assert_approx_eq!(k, 32.0 / 75.0);
+
+        let (g, h, k) = kalata_parameters(0.0_f64);
+        assert_approx_eq!(g, 0.0);
+        assert_approx_eq!(h, 0.0);
+        assert_approx_eq!(k, 0.0);
+    }
+
+    #[test]
+    #[cfg(feature = "half")]
+    fn test_gh_filter_half_precision() {
+        use half::f16;
+
+        let mut fgh: GHFilter<f16> = GHFilter::new(
+            f16::from_f32(0.0),
+            f16::from_f32(1.0),
+            f16::from_f32(0.5),
+            f16::from_f32(0.1),
+            f16::from_f32(1.0),
+        );
+        let mut reference: GHFilter<f32> = GHFilter::new(0.0, 1.0, 0.5, 0.1, 1.0);
+
+        for t in 1..20 {
+            let z = t as f32 + if t % 2 == 0 { 0.25 } else { -0.25 };
+            let estimate = fgh.update(f16::from_f32(z));
+            let expected = reference.update(z);
+            assert_approx_eq!(estimate.to_f32(), expected, 0.05);
+        }
+        assert_approx_eq!(fgh.dxt.to_f32(), reference.dxt, 0.01);
     }
 }
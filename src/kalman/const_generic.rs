@@ -0,0 +1,97 @@
+/*!
+This module provides type aliases that give the dimensions of the Kalman filter as numbers
+instead of `nalgebra` dimension types.
+
+`KalmanFilterC<f64, 2, 1>` is the same type as `KalmanFilter<f64, U2, U1, U1>`, so both forms can
+be mixed freely. Dimensions from 1 to 16 are supported.
+
+# Example
+
+```
+use filter::kalman::const_generic::{KalmanFilterC, MatrixC, VectorC};
+
+let mut kf = KalmanFilterC::<f64, 2, 1>::default();
+kf.x = VectorC::<f64, 2>::new(2.0, 0.0);
+kf.F = MatrixC::<f64, 2, 2>::new(
+    1.0, 1.0,
+    0.0, 1.0,
+);
+kf.H = MatrixC::<f64, 1, 2>::new(1.0, 0.0);
+kf.P *= 1000.0;
+kf.R = MatrixC::<f64, 1, 1>::new(5.0);
+kf.Q = MatrixC::<f64, 2, 2>::repeat(0.0001);
+
+for t in 0..100 {
+    kf.update(&VectorC::<f64, 1>::new(t as f64), None, None);
+    kf.predict(None, None, None, None);
+}
+assert!((kf.x[0] - 100.0).abs() < 0.05);
+```
+*/
+
+use nalgebra::base::dimension::DimName;
+use nalgebra::{
+    MatrixMN, VectorN, U1, U10, U11, U12, U13, U14, U15, U16, U2, U3, U4, U5, U6, U7, U8, U9,
+};
+
+use crate::kalman::kalman_filter::KalmanFilter;
+
+/// Dimension given as a number, which is mapped to the `nalgebra` dimension type by `ToDim`.
+#[derive(Debug, Clone, Copy)]
+pub struct Const<const N: usize>;
+
+/// Maps a dimension given as a number to the corresponding `nalgebra` dimension type.
+pub trait ToDim {
+    /// The `nalgebra` dimension type.
+    type Dim: DimName;
+}
+
+macro_rules! impl_to_dim {
+    ($($n:literal => $dim:ty),* $(,)?) => {
+        $(
+            impl ToDim for Const<$n> {
+                type Dim = $dim;
+            }
+        )*
+    };
+}
+
+impl_to_dim!(
+    1 => U1, 2 => U2, 3 => U3, 4 => U4, 5 => U5, 6 => U6, 7 => U7, 8 => U8,
+    9 => U9, 10 => U10, 11 => U11, 12 => U12, 13 => U13, 14 => U14, 15 => U15, 16 => U16,
+);
+
+/// Vector with `N` elements.
+pub type VectorC<F, const N: usize> = VectorN<F, <Const<N> as ToDim>::Dim>;
+
+/// Matrix with `R` rows and `C` columns.
+pub type MatrixC<F, const R: usize, const C: usize> =
+    MatrixMN<F, <Const<R> as ToDim>::Dim, <Const<C> as ToDim>::Dim>;
+
+/// Kalman filter with a state of `X` variables, measurements of `Z` variables and control inputs
+/// of `U` variables, which defaults to 1 for filters without control input.
+pub type KalmanFilterC<F, const X: usize, const Z: usize, const U: usize = 1> = KalmanFilter<
+    F,
+    <Const<X> as ToDim>::Dim,
+    <Const<Z> as ToDim>::Dim,
+    <Const<U> as ToDim>::Dim,
+>;
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Matrix2, Vector2};
+
+    use super::*;
+
+    #[test]
+    fn test_aliases_match_dimension_types() {
+        let kf: KalmanFilterC<f64, 2, 1> = KalmanFilter::default();
+        let typed: KalmanFilter<f64, U2, U1, U1> = kf;
+        assert_eq!(Matrix2::identity(), typed.P);
+
+        let x: VectorC<f32, 2> = Vector2::new(1.0, 2.0);
+        assert_eq!(2, x.len());
+        let m = MatrixC::<f32, 3, 4>::zeros();
+        assert_eq!((3, 4), m.shape());
+    }
+}
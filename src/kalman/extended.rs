@@ -0,0 +1,299 @@
+/*!
+This module implements the extended Kalman filter
+*/
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+/// Implements an extended Kalman filter (EKF).
+///
+/// The EKF handles a nonlinear measurement function `h(x)` by linearizing it around the current
+/// estimate: `update` is given `h` together with its Jacobian `H(x)`. The state transition is
+/// linear in `x` with the matrix `F`. The control input either enters linearly through `B * u`
+/// in `predict`, or through a nonlinear, state dependent function `g(x, u)` in `predict_control`.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::extended::ExtendedKalmanFilter;
+/// use nalgebra::{U1, U2, Vector1, Vector2, Matrix1, Matrix2, RowVector2};
+///
+/// // Range measurements to a point 10 units above the track.
+/// let hx = |x: &Vector2<f64>| Vector1::new((x[0] * x[0] + 100.0).sqrt());
+/// let h_jacobian = |x: &Vector2<f64>| RowVector2::new(x[0] / (x[0] * x[0] + 100.0).sqrt(), 0.0);
+///
+/// let mut ekf: ExtendedKalmanFilter<f64, U2, U1, U1> = ExtendedKalmanFilter::new();
+/// ekf.x = Vector2::new(1.0, 1.0);
+/// ekf.F = Matrix2::new(
+///     1.0, 1.0,
+///     0.0, 1.0,
+/// );
+/// ekf.R = Matrix1::new(0.1);
+/// ekf.Q *= 0.001;
+///
+/// for t in 1..20 {
+///     let position = t as f64;
+///     ekf.predict(None);
+///     ekf.update(&hx(&Vector2::new(position, 1.0)), h_jacobian, hx);
+/// }
+/// assert!((ekf.x[0] - 19.0).abs() < 0.5);
+/// ```
+///
+/// # References
+/// * Labbe, "Kalman and Bayesian Filters in Python", chapter 11.
+///   http://rlabbe.github.io/Kalman-and-Bayesian-Filters-in-Python
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct ExtendedKalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Current state estimate.
+    pub x: VectorN<F, DimX>,
+    /// Current state covariance matrix.
+    pub P: MatrixMN<F, DimX, DimX>,
+    /// Prior (predicted) state estimate.
+    pub x_prior: VectorN<F, DimX>,
+    /// Prior (predicted) state covariance matrix.
+    pub P_prior: MatrixMN<F, DimX, DimX>,
+    /// Posterior (updated) state estimate.
+    pub x_post: VectorN<F, DimX>,
+    /// Posterior (updated) state covariance matrix.
+    pub P_post: MatrixMN<F, DimX, DimX>,
+    /// State Transition matrix.
+    pub F: MatrixMN<F, DimX, DimX>,
+    /// Control transition matrix
+    pub B: Option<MatrixMN<F, DimX, DimU>>,
+    /// Process noise matrix.
+    pub Q: MatrixMN<F, DimX, DimX>,
+    /// Measurement noise matrix.
+    pub R: MatrixMN<F, DimZ, DimZ>,
+    /// Residual of the update step.
+    pub y: VectorN<F, DimZ>,
+    /// Kalman gain of the update step.
+    pub K: MatrixMN<F, DimX, DimZ>,
+    /// System uncertainty of the update step.
+    pub S: MatrixMN<F, DimZ, DimZ>,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> ExtendedKalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns an extended Kalman filter initialised with identity matrices for `P`, `F`, `Q`
+    /// and `R`.
+    pub fn new() -> Self {
+        ExtendedKalmanFilter {
+            x: VectorN::<F, DimX>::zeros(),
+            P: MatrixMN::<F, DimX, DimX>::identity(),
+            x_prior: VectorN::<F, DimX>::zeros(),
+            P_prior: MatrixMN::<F, DimX, DimX>::identity(),
+            x_post: VectorN::<F, DimX>::zeros(),
+            P_post: MatrixMN::<F, DimX, DimX>::identity(),
+            F: MatrixMN::<F, DimX, DimX>::identity(),
+            B: None,
+            Q: MatrixMN::<F, DimX, DimX>::identity(),
+            R: MatrixMN::<F, DimZ, DimZ>::identity(),
+            y: VectorN::<F, DimZ>::zeros(),
+            K: MatrixMN::<F, DimX, DimZ>::zeros(),
+            S: MatrixMN::<F, DimZ, DimZ>::zeros(),
+        }
+    }
+
+    /// Predict next state (prior) using the state propagation equations, where the control
+    /// input `u` enters linearly through `B * u`.
+    pub fn predict(&mut self, u: Option<&VectorN<F, DimU>>) {
+        if let (Some(B), Some(u)) = (self.B.as_ref(), u) {
+            self.x = &self.F * &self.x + B * u;
+        } else {
+            self.x = &self.F * &self.x;
+        }
+        self.P = &self.F * &self.P * self.F.transpose() + &self.Q;
+
+        self.x_prior = self.x.clone();
+        self.P_prior = self.P.clone();
+    }
+
+    /// Predict next state (prior), where the control input `u` enters through the nonlinear
+    /// function `g(x, u)`, i.e., `x = F x + g(x, u)`.
+    ///
+    /// In contrast to `B * u` in `predict`, the effect of the control may depend on the state,
+    /// e.g., a forward velocity command moves a vehicle along its current heading. The
+    /// covariance is propagated with the Jacobian of the whole transition, `F + G(x, u)`,
+    /// where `g_jacobian` returns the Jacobian `G` of `g` with respect to the state, evaluated
+    /// at the estimate before the prediction. With `g(x, u) = B u`, `G` is zero and this is
+    /// equivalent to `predict`.
+    pub fn predict_control<G, GJ>(&mut self, u: &VectorN<F, DimU>, g: G, g_jacobian: GJ)
+        where
+            G: Fn(&VectorN<F, DimX>, &VectorN<F, DimU>) -> VectorN<F, DimX>,
+            GJ: Fn(&VectorN<F, DimX>, &VectorN<F, DimU>) -> MatrixMN<F, DimX, DimX>,
+    {
+        let J = &self.F + g_jacobian(&self.x, u);
+        self.x = &self.F * &self.x + g(&self.x, u);
+        self.P = &J * &self.P * J.transpose() + &self.Q;
+
+        self.x_prior = self.x.clone();
+        self.P_prior = self.P.clone();
+    }
+
+    /// Add a new measurement (z) to the filter, where `hx` returns the measurement expected for
+    /// a state and `h_jacobian` its Jacobian with respect to the state. Both are evaluated at the
+    /// prior estimate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system uncertainty `S` is singular.
+    pub fn update<HJ, HX>(&mut self, z: &VectorN<F, DimZ>, h_jacobian: HJ, hx: HX)
+        where
+            HJ: Fn(&VectorN<F, DimX>) -> MatrixMN<F, DimZ, DimX>,
+            HX: Fn(&VectorN<F, DimX>) -> VectorN<F, DimZ>,
+    {
+        let H = h_jacobian(&self.x);
+
+        let PHT = &self.P * H.transpose();
+        self.S = &H * &PHT + &self.R;
+        let SI = self.S.clone().try_inverse().expect("S must be invertible");
+        self.K = PHT * SI;
+
+        self.y = z - hx(&self.x);
+        self.x = &self.x + &self.K * &self.y;
+
+        let I_KH = MatrixMN::<F, DimX, DimX>::identity() - &self.K * H;
+        self.P = &I_KH * &self.P * I_KH.transpose() + &self.K * &self.R * self.K.transpose();
+
+        self.x_post = self.x.clone();
+        self.P_post = self.P.clone();
+    }
+}
+
+impl<F, DimX, DimZ, DimU> Default for ExtendedKalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+    use nalgebra::{Matrix2, Matrix2x3, Matrix3, Vector2, Vector3, U2, U3};
+
+    use super::*;
+
+    const DT: f64 = 0.1;
+
+    /// Motion of a unicycle with the control `[forward velocity, turn rate]`.
+    fn unicycle(x: &Vector3<f64>, u: &Vector2<f64>) -> Vector3<f64> {
+        Vector3::new(u[0] * x[2].cos(), u[0] * x[2].sin(), u[1]) * DT
+    }
+
+    fn unicycle_jacobian(x: &Vector3<f64>, u: &Vector2<f64>) -> Matrix3<f64> {
+        let mut G = Matrix3::zeros();
+        G[(0, 2)] = -u[0] * x[2].sin() * DT;
+        G[(1, 2)] = u[0] * x[2].cos() * DT;
+        G
+    }
+
+    fn position(x: &Vector3<f64>) -> Vector2<f64> {
+        Vector2::new(x[0], x[1])
+    }
+
+    fn position_jacobian(_: &Vector3<f64>) -> Matrix2x3<f64> {
+        Matrix2x3::new(
+            1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+        )
+    }
+
+    #[test]
+    fn test_unicycle_tracks_heading() {
+        let mut ekf: ExtendedKalmanFilter<f64, U3, U2, U2> = ExtendedKalmanFilter::new();
+        // The initial heading is off by half a radian.
+        ekf.x = Vector3::new(0.0, 0.0, 0.5);
+        ekf.P = Matrix3::from_diagonal(&Vector3::new(0.01, 0.01, 1.0));
+        ekf.Q = Matrix3::identity() * 1e-5;
+        ekf.R = Matrix2::identity() * 0.01;
+
+        let mut truth = Vector3::new(0.0, 0.0, 0.0);
+        for t in 0..200 {
+            let u = Vector2::new(1.0, if t < 100 { 0.2 } else { -0.3 });
+            truth += unicycle(&truth, &u);
+
+            let noise = 0.05 * (1.7 * t as f64).sin();
+            let z = position(&truth) + Vector2::new(noise, -noise);
+
+            ekf.predict_control(&u, unicycle, unicycle_jacobian);
+            ekf.update(&z, position_jacobian, position);
+        }
+
+        assert!((ekf.x[0] - truth[0]).abs() < 0.1);
+        assert!((ekf.x[1] - truth[1]).abs() < 0.1);
+        assert!((ekf.x[2] - truth[2]).abs() < 0.05);
+        // Heading is only observed through the motion, so it remains the most uncertain.
+        assert!(ekf.P[(2, 2)] < 0.01);
+    }
+
+    #[test]
+    fn test_linear_control_matches_predict() {
+        let B = Matrix3::new(
+            1.0, 0.0, 0.0,
+            0.0, 2.0, 0.0,
+            0.0, 0.0, 0.5,
+        );
+
+        let mut linear: ExtendedKalmanFilter<f64, U3, U2, U3> = ExtendedKalmanFilter::new();
+        linear.x = Vector3::new(1.0, 2.0, 3.0);
+        linear.F[(0, 1)] = 0.3;
+        linear.B = Some(B);
+        let mut nonlinear: ExtendedKalmanFilter<f64, U3, U2, U3> = ExtendedKalmanFilter::new();
+        nonlinear.x = linear.x;
+        nonlinear.F = linear.F;
+
+        let u = Vector3::new(0.5, -1.0, 2.0);
+        linear.predict(Some(&u));
+        nonlinear.predict_control(&u, |_, u| B * u, |_, _| Matrix3::zeros());
+
+        for i in 0..3 {
+            assert_approx_eq!(linear.x[i], nonlinear.x[i]);
+        }
+        assert_eq!(linear.P, nonlinear.P);
+    }
+}
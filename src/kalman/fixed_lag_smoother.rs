@@ -0,0 +1,199 @@
+/*!
+This module implements a fixed-lag smoother for the linear Kalman filter.
+*/
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+use crate::kalman::kalman_filter::KalmanFilter;
+
+/// Implements a fixed-lag smoother.
+///
+/// The smoother runs a Kalman filter and, with every new measurement, corrects the estimates of
+/// the last `N` states. Each entry of `x_smooth` is therefore smoothed with up to `N` future
+/// measurements, without having to wait for the whole data set like the RTS smoother.
+/// The estimate of the state `N` steps back is final once a measurement has been added.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::fixed_lag_smoother::FixedLagSmoother;
+/// use filter::kalman::kalman_filter::KalmanFilter;
+/// use nalgebra::{U1, U2, Vector1, Vector2, Matrix2};
+///
+/// let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+/// kf.x = Vector2::new(0.0, 0.0);
+/// kf.F = Matrix2::new(
+///     1.0, 1.0,
+///     0.0, 1.0,
+/// );
+/// kf.H = Vector2::new(1.0, 0.0).transpose();
+/// kf.Q *= 0.001;
+///
+/// let mut fls = FixedLagSmoother::new(kf, 4);
+/// for t in 0..10 {
+///     fls.smooth(&Vector1::new(t as f64), None);
+/// }
+///
+/// assert_eq!(fls.x_smooth.len(), 10);
+/// ```
+///
+/// # References
+/// * Simon, "Optimal State Estimation: Kalman, H Infinity, and Nonlinear Approaches",
+///   section 9.3. John Wiley and Sons, 2006.
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct FixedLagSmoother<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Underlying Kalman filter, which holds the filtered (unsmoothed) estimate.
+    pub kf: KalmanFilter<F, DimX, DimZ, DimU>,
+    /// Number of past states that are corrected with each new measurement.
+    pub N: usize,
+    /// Smoothed state estimates, one per measurement.
+    pub x_smooth: Vec<VectorN<F, DimX>>,
+    /// Cross covariances between the errors of the retained past estimates and the error of the
+    /// current prior.
+    cross: VecDeque<MatrixMN<F, DimX, DimX>>,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> FixedLagSmoother<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns a smoother with lag `N` for the given Kalman filter.
+    pub fn new(kf: KalmanFilter<F, DimX, DimZ, DimU>, N: usize) -> Self {
+        FixedLagSmoother {
+            kf,
+            N,
+            x_smooth: Vec::new(),
+            cross: VecDeque::with_capacity(N + 1),
+        }
+    }
+
+    /// Predicts the next state, adds the measurement (z) and corrects the last `N` smoothed
+    /// estimates.
+    pub fn smooth(&mut self, z: &VectorN<F, DimZ>, u: Option<&VectorN<F, DimU>>) {
+        self.kf.predict(u, None, None, None);
+
+        self.x_smooth.push(self.kf.x.clone());
+        self.cross.push_back(self.kf.P.clone());
+        if self.cross.len() > self.N + 1 {
+            self.cross.pop_front();
+        }
+
+        self.kf.update(z, None, None);
+
+        let correction = self.kf.H.transpose() * &self.kf.SI * &self.kf.y;
+        let I = MatrixMN::<F, DimX, DimX>::identity();
+        let F_LH = (&self.kf.F * (I - &self.kf.K * &self.kf.H)).transpose();
+
+        let offset = self.x_smooth.len() - self.cross.len();
+        for (x, C) in self.x_smooth[offset..].iter_mut().zip(self.cross.iter_mut()) {
+            *x += &*C * &correction;
+            *C = &*C * &F_LH;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use nalgebra::{Matrix1, Matrix2, Vector1, Vector2, U1, U2};
+
+    use super::*;
+
+    fn setup_filter() -> KalmanFilter<f64, U2, U1, U1> {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(0.0, 0.0);
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.R = Matrix1::new(4.0);
+        kf.Q = Matrix2::new(
+            0.25, 0.5,
+            0.5, 1.0,
+        ) * 0.1;
+        kf.P *= 10.0;
+        kf
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_matches_rts_smoother() {
+        let zs: Vec<_> = (0..40)
+            .map(|t| {
+                let t = t as f64;
+                Vector1::new(0.1 * t * t + 2.0 * (t * 2.7).sin())
+            })
+            .collect();
+
+        let mut kf = setup_filter();
+        let mut xs = Vec::new();
+        let mut Ps = Vec::new();
+        for z in &zs {
+            kf.predict(None, None, None, None);
+            kf.update(z, None, None);
+            xs.push(kf.x);
+            Ps.push(kf.P);
+        }
+        let filtered = xs.clone();
+        kf.rts_smoother(&mut xs, &mut Ps);
+
+        let N = 8;
+        let mut fls = FixedLagSmoother::new(setup_filter(), N);
+        for z in &zs {
+            fls.smooth(z, None);
+        }
+
+        assert_eq!(zs.len(), fls.x_smooth.len());
+
+        // The last N + 1 estimates have seen all measurements and match the RTS smoother.
+        let final_estimates = zs.len() - N - 1;
+        for (x, x_rts) in fls.x_smooth.iter().zip(&xs).skip(final_estimates) {
+            assert!((x - x_rts).norm() < 1e-9);
+        }
+
+        // Earlier estimates have seen N future measurements and are a lot closer to it than the
+        // filtered estimates.
+        let error = |estimates: &[Vector2<f64>]| {
+            estimates
+                .iter()
+                .zip(&xs)
+                .take(final_estimates)
+                .map(|(x, x_rts)| (x - x_rts).norm_squared())
+                .sum::<f64>()
+        };
+        assert!(error(&fls.x_smooth) < 0.05 * error(&filtered));
+    }
+}
@@ -0,0 +1,276 @@
+/*!
+This module implements the H-infinity filter
+*/
+
+use core::fmt;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+/// Errors that can occur in the update step of the H-infinity filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HInfinityError {
+    /// A matrix that has to be inverted is singular.
+    SingularMatrix,
+    /// The updated state covariance is not positive definite, i.e., `gamma` is too small to be
+    /// achievable for the filter.
+    NotPositiveDefinite,
+}
+
+impl fmt::Display for HInfinityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HInfinityError::SingularMatrix => write!(f, "matrix is singular"),
+            HInfinityError::NotPositiveDefinite => {
+                write!(f, "covariance is not positive definite, gamma is infeasible")
+            }
+        }
+    }
+}
+
+/// Implements an H-infinity filter.
+///
+/// Instead of assuming Gaussian noise with known statistics, the H-infinity filter minimizes the
+/// worst-case estimation error. It guarantees that the ratio of the (weighted by `S`) estimation
+/// error to the energy of the process and measurement noise stays below the performance bound
+/// `gamma`. The noise covariances `Q` and `R` act as weights of the respective noise.
+///
+/// The bound `gamma` is only feasible if `P^-1 - S / gamma + H^T R^-1 H` stays positive definite
+/// in every update. Smaller values of `gamma` make the filter more robust against unmodelled noise,
+/// at the cost of a larger covariance; if `gamma` is too small, the update fails with
+/// `HInfinityError::NotPositiveDefinite`. As `gamma` goes to infinity, the filter becomes
+/// the Kalman filter.
+///
+/// Note that filterpy's `HInfinityFilter` is parameterized by `1 / gamma` instead,
+/// and names the process noise `W`, the measurement noise `V` and the error weight `Q`.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::h_infinity::HInfinityFilter;
+/// use nalgebra::{U1, U2, Vector1, Vector2, Matrix1, Matrix2};
+///
+/// let mut hf: HInfinityFilter<f64, U2, U1, U1> = HInfinityFilter::new(1000.0);
+/// hf.x = Vector2::new(0.0, 0.0);
+/// hf.F = Matrix2::new(
+///     1.0, 1.0,
+///     0.0, 1.0,
+/// );
+/// hf.H = Vector2::new(1.0, 0.0).transpose();
+/// hf.P *= 100.0;
+/// hf.R = Matrix1::new(1.0);
+/// hf.Q *= 0.01;
+///
+/// for t in 0..10 {
+///     hf.predict(None);
+///     hf.update(&Vector1::new(t as f64)).unwrap();
+/// }
+/// ```
+///
+/// # References
+/// * Simon, "Optimal State Estimation: Kalman, H Infinity, and Nonlinear Approaches".
+///   John Wiley and Sons, 2006.
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct HInfinityFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Current state estimate.
+    pub x: VectorN<F, DimX>,
+    /// Current state covariance matrix.
+    pub P: MatrixMN<F, DimX, DimX>,
+    /// State Transition matrix.
+    pub F: MatrixMN<F, DimX, DimX>,
+    /// Measurement function.
+    pub H: MatrixMN<F, DimZ, DimX>,
+    /// Control transition matrix
+    pub G: Option<MatrixMN<F, DimX, DimU>>,
+    /// Process noise matrix.
+    pub Q: MatrixMN<F, DimX, DimX>,
+    /// Measurement noise matrix.
+    pub R: MatrixMN<F, DimZ, DimZ>,
+    /// Weight of the state estimation error.
+    pub S: MatrixMN<F, DimX, DimX>,
+    /// Performance bound.
+    pub gamma: F,
+    /// Residual of the update step.
+    pub y: VectorN<F, DimZ>,
+    /// Gain of the update step.
+    pub K: MatrixMN<F, DimX, DimZ>,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> HInfinityFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns an H-infinity filter with the performance bound `gamma`, initialised with
+    /// identity matrices for `P`, `F`, `Q`, `R` and `S`.
+    pub fn new(gamma: F) -> Self {
+        HInfinityFilter {
+            x: VectorN::<F, DimX>::zeros(),
+            P: MatrixMN::<F, DimX, DimX>::identity(),
+            F: MatrixMN::<F, DimX, DimX>::identity(),
+            H: MatrixMN::<F, DimZ, DimX>::zeros(),
+            G: None,
+            Q: MatrixMN::<F, DimX, DimX>::identity(),
+            R: MatrixMN::<F, DimZ, DimZ>::identity(),
+            S: MatrixMN::<F, DimX, DimX>::identity(),
+            gamma,
+            y: VectorN::<F, DimZ>::zeros(),
+            K: MatrixMN::<F, DimX, DimZ>::zeros(),
+        }
+    }
+
+    /// Predict next state using the state propagation equations.
+    pub fn predict(&mut self, u: Option<&VectorN<F, DimU>>) {
+        if let (Some(G), Some(u)) = (self.G.as_ref(), u) {
+            self.x = &self.F * &self.x + G * u;
+        } else {
+            self.x = &self.F * &self.x;
+        }
+        self.P = &self.F * &self.P * self.F.transpose() + &self.Q;
+    }
+
+    /// Add a new measurement (z) to the filter.
+    ///
+    /// Returns an error and leaves the filter unchanged if `R` or the design matrix is singular,
+    /// or if the updated covariance is not positive definite, i.e., `gamma` is infeasible.
+    pub fn update(&mut self, z: &VectorN<F, DimZ>) -> Result<(), HInfinityError> {
+        let RI = self.R.clone().try_inverse().ok_or(HInfinityError::SingularMatrix)?;
+        let HTRI = self.H.transpose() * RI;
+
+        let I = MatrixMN::<F, DimX, DimX>::identity();
+        let L = (I - (&self.S * &self.P) / self.gamma + &HTRI * &self.H * &self.P)
+            .try_inverse()
+            .ok_or(HInfinityError::SingularMatrix)?;
+
+        // P L = (P^-1 - S / gamma + H^T R^-1 H)^-1, which must be positive definite.
+        let PL = &self.P * L;
+        let P = (&PL + PL.transpose()) * nalgebra::convert::<f64, F>(0.5);
+        if P.clone().cholesky().is_none() {
+            return Err(HInfinityError::NotPositiveDefinite);
+        }
+
+        self.K = &P * HTRI;
+        self.y = z - &self.H * &self.x;
+        self.x = &self.x + &self.K * &self.y;
+        self.P = P;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+    use nalgebra::{Matrix1, Matrix2, Vector1, Vector2, U1, U2};
+
+    use crate::kalman::kalman_filter::KalmanFilter;
+
+    use super::*;
+
+    type Filters = (HInfinityFilter<f64, U2, U1, U1>, KalmanFilter<f64, U2, U1, U1>);
+
+    fn setup_filters(gamma: f64) -> Filters {
+        let mut hf: HInfinityFilter<f64, U2, U1, U1> = HInfinityFilter::new(gamma);
+        hf.x = Vector2::new(0.0, 0.0);
+        hf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        hf.H = Vector2::new(1.0, 0.0).transpose();
+        hf.P *= 100.0;
+        hf.R = Matrix1::new(5.0);
+        hf.Q = Matrix2::new(
+            0.25, 0.5,
+            0.5, 1.0,
+        ) * 0.01;
+
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = hf.x;
+        kf.F = hf.F;
+        kf.H = hf.H;
+        kf.P = hf.P;
+        kf.R = hf.R;
+        kf.Q = hf.Q;
+
+        (hf, kf)
+    }
+
+    fn measurement(t: usize) -> Vector1<f64> {
+        let t = t as f64;
+        Vector1::new(2.0 * t + 3.0 * (t * 1.3).sin())
+    }
+
+    #[test]
+    fn test_large_gamma_matches_kalman() {
+        let (mut hf, mut kf) = setup_filters(1e12);
+
+        for t in 0..50 {
+            hf.predict(None);
+            kf.predict(None, None, None, None);
+            hf.update(&measurement(t)).unwrap();
+            kf.update(&measurement(t), None, None);
+
+            assert_approx_eq!(kf.x[0], hf.x[0], 1e-6);
+            assert_approx_eq!(kf.x[1], hf.x[1], 1e-6);
+            assert_approx_eq!(kf.P[(0, 0)], hf.P[(0, 0)], 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_small_gamma_is_more_conservative() {
+        let (mut hf, mut kf) = setup_filters(500.0);
+
+        for t in 0..50 {
+            hf.predict(None);
+            kf.predict(None, None, None, None);
+            hf.update(&measurement(t)).unwrap();
+            kf.update(&measurement(t), None, None);
+        }
+
+        assert!(hf.P[(0, 0)] > kf.P[(0, 0)]);
+        assert!(hf.K[0] > kf.K[0]);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_infeasible_gamma() {
+        let (mut hf, _) = setup_filters(0.001);
+
+        hf.predict(None);
+        let x_prior = hf.x;
+        let P_prior = hf.P;
+        let result = hf.update(&measurement(0));
+
+        assert_eq!(Err(HInfinityError::NotPositiveDefinite), result);
+        assert_eq!(x_prior, hf.x);
+        assert_eq!(P_prior, hf.P);
+    }
+}
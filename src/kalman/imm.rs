@@ -0,0 +1,355 @@
+/*!
+This module implements the Interacting Multiple Model (IMM) estimator.
+*/
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+use crate::kalman::kalman_filter::KalmanFilter;
+use crate::kalman::outer;
+use crate::stats::bayes_update_log;
+
+/// Implements an Interacting Multiple Model (IMM) estimator.
+///
+/// The estimator runs a bank of Kalman filters, one per motion model, and switches between
+/// them according to a Markov chain with the transition matrix `M`. Before each prediction
+/// the filter states are mixed according to the probability of switching models, and after
+/// each update the mode probabilities `mu` are updated with the likelihood of the measurement
+/// under each model. All filters have to share the same state space, e.g., a constant velocity
+/// model can be expressed in a constant acceleration state space with the acceleration zeroed.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::imm::IMMEstimator;
+/// use filter::kalman::kalman_filter::KalmanFilter;
+/// use nalgebra::{U1, U2, Vector1, Vector2, Matrix2};
+///
+/// let mut slow: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+/// slow.F = Matrix2::new(
+///     1.0, 1.0,
+///     0.0, 1.0,
+/// );
+/// slow.H = Vector2::new(1.0, 0.0).transpose();
+/// slow.Q *= 0.001;
+///
+/// let mut agile: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+/// agile.F = slow.F;
+/// agile.H = slow.H;
+/// agile.Q *= 1.0;
+///
+/// let mut imm = IMMEstimator::new(
+///     vec![slow, agile],
+///     vec![0.5, 0.5],
+///     vec![vec![0.95, 0.05], vec![0.05, 0.95]],
+/// );
+///
+/// for t in 0..10 {
+///     imm.predict(None);
+///     imm.update(&Vector1::new(t as f64));
+/// }
+///
+/// assert!(imm.mu[0] > imm.mu[1]);
+/// ```
+///
+/// # References
+/// * Roger Labbe. "Kalman and Bayesian Filters in Python", chapter 14.
+///   https://github.com/rlabbe/Kalman-and-Bayesian-Filters-in-Python
+/// * Bar-Shalom, Li, Kirubarajan, "Estimation with Applications to Tracking and Navigation".
+///   John Wiley and Sons, 2001.
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct IMMEstimator<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Bank of filters, one per model.
+    pub filters: Vec<KalmanFilter<F, DimX, DimZ, DimU>>,
+    /// Mode probabilities, i.e., the probability of each model being the correct one.
+    pub mu: Vec<F>,
+    /// Markov chain transition matrix. `M[i][j]` is the probability of switching from model `i`
+    /// to model `j`.
+    pub M: Vec<Vec<F>>,
+    /// Combined state estimate.
+    pub x: VectorN<F, DimX>,
+    /// Combined state covariance matrix.
+    pub P: MatrixMN<F, DimX, DimX>,
+    /// Likelihood of the last measurement under each model.
+    pub likelihood: Vec<F>,
+    /// Mixing probabilities. `omega[i][j]` is the probability that the system was in model `i`
+    /// given that it is now in model `j`.
+    pub omega: Vec<Vec<F>>,
+    /// Total probability of each model after the transition.
+    pub cbar: Vec<F>,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> IMMEstimator<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns an IMM estimator for the given bank of `filters`, initial mode probabilities `mu`
+    /// and Markov chain transition matrix `M`.
+    ///
+    /// # Panics
+    /// Panics if there are fewer than two filters, or if `mu` or `M` do not match the number
+    /// of filters.
+    pub fn new(
+        filters: Vec<KalmanFilter<F, DimX, DimZ, DimU>>,
+        mu: Vec<F>,
+        M: Vec<Vec<F>>,
+    ) -> Self {
+        let n = filters.len();
+        assert!(n >= 2, "IMM requires at least two filters");
+        assert_eq!(n, mu.len(), "mu must have one entry per filter");
+        assert_eq!(n, M.len(), "M must have one row per filter");
+        assert!(M.iter().all(|row| row.len() == n), "M must be square");
+
+        let mut imm = IMMEstimator {
+            filters,
+            mu,
+            M,
+            x: VectorN::<F, DimX>::zeros(),
+            P: MatrixMN::<F, DimX, DimX>::zeros(),
+            likelihood: vec![F::zero(); n],
+            omega: vec![vec![F::zero(); n]; n],
+            cbar: vec![F::zero(); n],
+        };
+        imm.compute_mixing_probabilities();
+        imm.compute_state_estimate();
+        imm
+    }
+
+    /// Predict next state (prior) by mixing the states of the filters according to the mixing
+    /// probabilities and running the prediction step of each filter.
+    pub fn predict(&mut self, u: Option<&VectorN<F, DimU>>) {
+        let mixed: Vec<_> = (0..self.filters.len())
+            .map(|j| {
+                let mut x = VectorN::<F, DimX>::zeros();
+                for (i, kf) in self.filters.iter().enumerate() {
+                    x += &kf.x * self.omega[i][j];
+                }
+
+                let mut P = MatrixMN::<F, DimX, DimX>::zeros();
+                for (i, kf) in self.filters.iter().enumerate() {
+                    let y = &kf.x - &x;
+                    P += (outer(&y) + &kf.P) * self.omega[i][j];
+                }
+                (x, P)
+            })
+            .collect();
+
+        for (kf, (x, P)) in self.filters.iter_mut().zip(mixed) {
+            kf.x = x;
+            kf.P = P;
+            kf.predict(u, None, None, None);
+        }
+
+        self.compute_state_estimate();
+    }
+
+    /// Add a new measurement (z) to all filters and update the mode probabilities.
+    ///
+    /// The mode probabilities are updated with the log-likelihoods of the measurement, so that
+    /// an outlier that is practically impossible under every model does not zero them out. If
+    /// the measurement has zero probability under every model, the mode probabilities are set
+    /// to the predicted ones, `cbar`.
+    pub fn update(&mut self, z: &VectorN<F, DimZ>) {
+        let mut log_likelihoods = Vec::with_capacity(self.filters.len());
+        for (kf, likelihood) in self.filters.iter_mut().zip(self.likelihood.iter_mut()) {
+            kf.update(z, None, None);
+            *likelihood = kf.likelihood();
+            log_likelihoods.push(kf.log_likelihood());
+        }
+
+        self.mu.clone_from(&self.cbar);
+        bayes_update_log(&mut self.mu, &log_likelihoods);
+
+        self.compute_mixing_probabilities();
+        self.compute_state_estimate();
+    }
+
+    fn compute_mixing_probabilities(&mut self) {
+        let n = self.filters.len();
+        for j in 0..n {
+            self.cbar[j] = (0..n).fold(F::zero(), |acc, i| acc + self.mu[i] * self.M[i][j]);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                self.omega[i][j] = if self.cbar[j] > F::zero() {
+                    self.M[i][j] * self.mu[i] / self.cbar[j]
+                } else if i == j {
+                    // No model can switch to model j, so its filter is not mixed with the others.
+                    F::one()
+                } else {
+                    F::zero()
+                };
+            }
+        }
+    }
+
+    fn compute_state_estimate(&mut self) {
+        self.x = VectorN::<F, DimX>::zeros();
+        for (kf, mu) in self.filters.iter().zip(&self.mu) {
+            self.x += &kf.x * *mu;
+        }
+
+        self.P = MatrixMN::<F, DimX, DimX>::zeros();
+        for (kf, mu) in self.filters.iter().zip(&self.mu) {
+            let y = &kf.x - &self.x;
+            self.P += (outer(&y) + &kf.P) * *mu;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use nalgebra::{Matrix1, Matrix3, Vector1, Vector3, U1, U3};
+
+    use super::*;
+
+    #[test]
+    fn test_maneuver_shifts_probability() {
+        let mut ca: KalmanFilter<f64, U3, U1, U1> = KalmanFilter::default();
+        ca.x = Vector3::new(0.0, 1.0, 0.0);
+        ca.F = Matrix3::new(
+            1.0, 1.0, 0.5,
+            0.0, 1.0, 1.0,
+            0.0, 0.0, 1.0,
+        );
+        ca.H = Vector3::new(1.0, 0.0, 0.0).transpose();
+        ca.R = Matrix1::new(1.0);
+        ca.Q = Matrix3::from_diagonal(&Vector3::new(0.0, 0.0, 0.01));
+        ca.P *= 10.0;
+
+        let mut cv: KalmanFilter<f64, U3, U1, U1> = KalmanFilter::default();
+        cv.x = ca.x;
+        cv.F = Matrix3::new(
+            1.0, 1.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0,
+        );
+        cv.H = ca.H;
+        cv.R = ca.R;
+        cv.Q = Matrix3::from_diagonal(&Vector3::new(0.0, 0.001, 0.0));
+        cv.P *= 10.0;
+
+        let mut imm = IMMEstimator::new(
+            vec![cv, ca],
+            vec![0.5, 0.5],
+            vec![vec![0.97, 0.03], vec![0.03, 0.97]],
+        );
+
+        let noise = |t: usize| 0.5 * (t as f64 * 2.7).sin();
+
+        let mut position = 0.0;
+        let mut velocity = 1.0;
+        for t in 0..40 {
+            position += velocity;
+            imm.predict(None);
+            imm.update(&Vector1::new(position + noise(t)));
+        }
+        let mu_before = imm.mu[1];
+        assert!(imm.mu[0] > imm.mu[1]);
+
+        for t in 40..60 {
+            position += velocity + 0.5;
+            velocity += 1.0;
+            imm.predict(None);
+            imm.update(&Vector1::new(position + noise(t)));
+        }
+
+        assert!(imm.mu[1] > mu_before);
+        assert!(imm.mu[1] > imm.mu[0]);
+        assert!((imm.mu[0] + imm.mu[1] - 1.0).abs() < 1e-9);
+        assert!((imm.x[0] - position).abs() < 2.0);
+    }
+
+    fn filter(q: f64) -> KalmanFilter<f64, U3, U1, U1> {
+        let mut kf: KalmanFilter<f64, U3, U1, U1> = KalmanFilter::default();
+        kf.F = Matrix3::new(
+            1.0, 1.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 1.0,
+        );
+        kf.H = Vector3::new(1.0, 0.0, 0.0).transpose();
+        kf.R = Matrix1::new(1.0);
+        kf.Q *= q;
+        kf
+    }
+
+    #[test]
+    fn test_outlier_keeps_probabilities() {
+        let mut imm = IMMEstimator::new(
+            vec![filter(0.001), filter(1.0)],
+            vec![0.5, 0.5],
+            vec![vec![0.95, 0.05], vec![0.05, 0.95]],
+        );
+
+        for t in 0..20 {
+            imm.predict(None);
+            imm.update(&Vector1::new(t as f64));
+        }
+
+        imm.predict(None);
+        imm.update(&Vector1::new(1e6));
+
+        assert!(imm.mu.iter().all(|mu| mu.is_finite()));
+        assert!((imm.mu.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(imm.omega.iter().flatten().all(|omega| omega.is_finite()));
+
+        for t in 21..40 {
+            imm.predict(None);
+            imm.update(&Vector1::new(t as f64));
+        }
+        assert!(imm.x.iter().all(|x| x.is_finite()));
+        assert!(imm.x[0] > 20.0);
+    }
+
+    #[test]
+    fn test_unreachable_model() {
+        let mut imm = IMMEstimator::new(
+            vec![filter(0.001), filter(1.0)],
+            vec![0.5, 0.5],
+            vec![vec![1.0, 0.0], vec![1.0, 0.0]],
+        );
+
+        for t in 0..10 {
+            imm.predict(None);
+            imm.update(&Vector1::new(t as f64));
+        }
+
+        assert_eq!(imm.cbar[1], 0.0);
+        assert_eq!(imm.omega[1][1], 1.0);
+        assert_eq!(imm.mu, vec![1.0, 0.0]);
+        assert!(imm.filters[1].x.iter().all(|x| x.is_finite()));
+        assert!((imm.x[0] - 9.0).abs() < 1.0);
+    }
+}
@@ -2,9 +2,24 @@
 This module implements the linear Kalman filter
 */
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
 use nalgebra::allocator::Allocator;
-use nalgebra::base::dimension::DimName;
-use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+use nalgebra::base::dimension::{DimDiff, DimName, DimNameAdd, DimNameSum, DimSub};
+use nalgebra::{DefaultAllocator, Matrix2, MatrixMN, RealField, Vector1, Vector2, VectorN, U1};
+#[cfg(all(feature = "rand", feature = "alloc"))]
+use rand::Rng;
+
+#[cfg(feature = "alloc")]
+use crate::kalman::outer;
+use crate::stats::{GaussianDistribution, MultivariateGaussian};
+use crate::Filter;
 
 /// Implements a Kalman filter.
 /// For a detailed explanation, see the excellent book Kalman and Bayesian
@@ -19,6 +34,7 @@ use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
 ///
 #[allow(non_snake_case)]
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct KalmanFilter<F, DimX, DimZ, DimU>
     where
         F: RealField,
@@ -58,16 +74,33 @@ pub struct KalmanFilter<F, DimX, DimZ, DimU>
     pub F: MatrixMN<F, DimX, DimX>,
     /// Measurement function.
     pub H: MatrixMN<F, DimZ, DimX>,
-    /// Residual of the update step.
+    /// Residual of the update step. Only valid after an update, see `innovation`.
     pub y: VectorN<F, DimZ>,
-    /// Kalman gain of the update step.
+    /// Kalman gain of the update step. Only valid after an update, see `kalman_gain`.
     pub K: MatrixMN<F, DimX, DimZ>,
-    /// System uncertainty (P projected to measurement space).
+    /// System uncertainty (P projected to measurement space). Only valid after an update, see
+    /// `innovation_covariance`.
     pub S: MatrixMN<F, DimZ, DimZ>,
     /// Inverse system uncertainty.
     pub SI: MatrixMN<F, DimZ, DimZ>,
-    /// Fading memory setting.
+    /// Fading memory setting, the square of the factor by which the state covariance is inflated
+    /// in each prediction. See `fading_memory`.
     pub alpha_sq: F,
+    /// Adaptive scaling of the process noise, disabled if `None`.
+    pub adaptive_q: Option<AdaptiveQ<F>>,
+    /// Detection of the convergence of the Kalman gain, disabled if `None`. See
+    /// `auto_steadystate`.
+    pub convergence: Option<Convergence<F>>,
+    /// Method used to invert the system uncertainty `S`.
+    pub inv_strategy: InverseStrategy,
+    #[cfg(feature = "alloc")]
+    history: Option<History<F, DimX>>,
+    #[cfg(feature = "alloc")]
+    nis_window: Option<NisWindow<F>>,
+    #[cfg(feature = "alloc")]
+    trajectory: Option<Trajectory<F, DimX>>,
+    #[cfg(feature = "alloc")]
+    on_update: Option<OnUpdate<F, DimZ>>,
 }
 
 #[allow(non_snake_case)]
@@ -86,6 +119,55 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         + Allocator<F, DimU>
         + Allocator<F, DimX, DimU>,
 {
+    /// Returns a fading memory Kalman filter, initialised with default parameters and `alpha_sq`
+    /// set to `alpha * alpha`.
+    ///
+    /// The filter inflates the predicted covariance by `alpha^2` in each step, so older
+    /// measurements are weighted exponentially less. With `alpha` slightly above 1 (e.g. 1.01 to
+    /// 1.05), the filter reacts faster to changes that the process model does not describe, at the
+    /// cost of a noisier estimate. With `alpha = 1` it is the regular Kalman filter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::U1;
+    ///
+    /// let kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::fading_memory(1.02);
+    /// assert_eq!(kf.alpha_sq, 1.02 * 1.02);
+    /// ```
+    pub fn fading_memory(alpha: F) -> Self {
+        KalmanFilter {
+            alpha_sq: alpha * alpha,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the measurement noise to `R = sigma_sq * I`, i.e., independent measurements with the
+    /// same variance `sigma_sq`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{Matrix2, Matrix3, U1, U2, U3};
+    ///
+    /// let mut kf: KalmanFilter<f64, U3, U2, U1> = KalmanFilter::default();
+    /// kf.set_isotropic_measurement_noise(0.25);
+    /// kf.set_isotropic_process_noise(0.01);
+    /// assert_eq!(kf.R, Matrix2::identity() * 0.25);
+    /// assert_eq!(kf.Q, Matrix3::identity() * 0.01);
+    /// ```
+    pub fn set_isotropic_measurement_noise(&mut self, sigma_sq: F) {
+        self.R = MatrixMN::<F, DimZ, DimZ>::identity() * sigma_sq;
+    }
+
+    /// Sets the process noise to `Q = q * I`, i.e., independent noise with the same variance `q`
+    /// on each state variable.
+    pub fn set_isotropic_process_noise(&mut self, q: F) {
+        self.Q = MatrixMN::<F, DimX, DimX>::identity() * q;
+    }
+
     /// Predict next state (prior) using the Kalman filter state propagation equations.
     pub fn predict(
         &mut self,
@@ -94,22 +176,41 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         F: Option<&MatrixMN<F, DimX, DimX>>,
         Q: Option<&MatrixMN<F, DimX, DimX>>,
     ) {
+        self.record();
+
         let B = if B.is_some() { B } else { self.B.as_ref() };
         let F = F.unwrap_or(&self.F);
         let Q = Q.unwrap_or(&self.Q);
 
-        if B.is_some() && u.is_some() {
-            self.x = F * self.x.clone() + B.unwrap() * u.unwrap();
+        if let (Some(B), Some(u)) = (B, u) {
+            self.x = F * self.x.clone() + B * u;
         } else {
             self.x = F * self.x.clone();
         }
 
-        self.P = ((F * self.P.clone()) * F.transpose()) * self.alpha_sq + Q;
+        if !self.is_steady_state() {
+            self.P = ((F * self.P.clone()) * F.transpose()) * self.alpha_sq + Q;
+        }
 
         self.x_prior = self.x.clone();
         self.P_prior = self.P.clone();
     }
 
+    /// Predict the state (prior) after the time step `dt`, rebuilding the state transition `F` and
+    /// the process noise `Q` from `state_transition` and `process_noise` for that time step.
+    ///
+    /// The rebuilt matrices are stored in `F` and `Q`, so they are also used by later calls that
+    /// depend on them, e.g., `get_prediction`.
+    pub fn predict_dt<FF, QF>(&mut self, dt: F, state_transition: FF, process_noise: QF)
+        where
+            FF: Fn(F) -> MatrixMN<F, DimX, DimX>,
+            QF: Fn(F) -> MatrixMN<F, DimX, DimX>,
+    {
+        self.F = state_transition(dt);
+        self.Q = process_noise(dt);
+        self.predict(None, None, None, None);
+    }
+
     /// Add a new measurement (z) to the Kalman filter.
     pub fn update(
         &mut self,
@@ -117,27 +218,267 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         R: Option<&MatrixMN<F, DimZ, DimZ>>,
         H: Option<&MatrixMN<F, DimZ, DimX>>,
     ) {
-        let R = R.unwrap_or(&self.R);
-        let H = H.unwrap_or(&self.H);
+        self.update_with_residual(z, R, H, |z, hx| z - hx);
+    }
 
-        self.y = z - H * &self.x;
+    /// Add a new measurement (z) to the Kalman filter, using `residual` to compute the difference
+    /// between the measurement and the predicted measurement instead of plain subtraction.
+    ///
+    /// This is needed when the measurement space is not Euclidean, e.g., for angles that wrap
+    /// around at `±pi`, see `stats::angle_residual`.
+    pub fn update_with_residual<Residual>(
+        &mut self,
+        z: &VectorN<F, DimZ>,
+        R: Option<&MatrixMN<F, DimZ, DimZ>>,
+        H: Option<&MatrixMN<F, DimZ, DimX>>,
+        residual: Residual,
+    )
+        where
+            Residual: Fn(&VectorN<F, DimZ>, &VectorN<F, DimZ>) -> VectorN<F, DimZ>,
+    {
+        self.record();
+        self.correct(z, R, H, residual);
+        self.record_posterior();
+    }
 
-        let PHT = self.P.clone() * H.transpose();
-        self.S = H * &PHT + R;
+    /// Applies the measurement (z) to `x` and `P`, without recording the state for `rewind` or
+    /// `smooth`.
+    fn correct<Residual>(
+        &mut self,
+        z: &VectorN<F, DimZ>,
+        R: Option<&MatrixMN<F, DimZ, DimZ>>,
+        H: Option<&MatrixMN<F, DimZ, DimX>>,
+        residual: Residual,
+    )
+        where
+            Residual: Fn(&VectorN<F, DimZ>, &VectorN<F, DimZ>) -> VectorN<F, DimZ>,
+    {
+        let R = R.unwrap_or(&self.R).clone();
+        let H = H.unwrap_or(&self.H).clone();
+
+        let y = residual(z, &(&H * &self.x));
+
+        if self.is_steady_state() {
+            self.y = y;
+            self.x = &self.x + &self.K * &self.y;
+        } else {
+            let K = self.K.clone();
+            let P = self.P.clone();
+            self.P = self.apply_residual(y, &R, &H, &P);
+
+            if let Some(convergence) = self.convergence.as_mut() {
+                convergence.observe((&self.K - K).amax());
+            }
+        }
+
+        self.finish_update(z);
+    }
 
-        self.SI = self.S.clone().try_inverse().unwrap();
+    /// Applies the residual `y` of a measurement to `x`, computing the system uncertainty and
+    /// the Kalman gain from the prior covariance `P`, and returns the posterior covariance.
+    fn apply_residual(
+        &mut self,
+        y: VectorN<F, DimZ>,
+        R: &MatrixMN<F, DimZ, DimZ>,
+        H: &MatrixMN<F, DimZ, DimX>,
+        P: &MatrixMN<F, DimX, DimX>,
+    ) -> MatrixMN<F, DimX, DimX> {
+        self.y = y;
+
+        let PHT = P * H.transpose();
+        self.S = H * &PHT + R;
 
+        self.SI = self.inv_strategy.invert(&self.S);
         self.K = PHT * &self.SI;
 
         self.x = &self.x + &self.K * &self.y;
 
         let I_KH = MatrixMN::<F, DimX, DimX>::identity() - &self.K * H;
-        self.P =
-            ((I_KH.clone() * &self.P) * I_KH.transpose()) + ((&self.K * R) * &self.K.transpose());
+        ((I_KH.clone() * P) * I_KH.transpose()) + ((&self.K * R) * self.K.transpose())
+    }
 
+    /// Stores the measurement (z) and the posterior of an update, and runs the bookkeeping that
+    /// follows each update.
+    fn finish_update(&mut self, z: &VectorN<F, DimZ>) {
         self.z = Some(z.clone());
         self.x_post = self.x.clone();
         self.P_post = self.P.clone();
+
+        self.record_nis();
+        self.notify_update();
+        self.adapt_process_noise();
+    }
+
+    /// Starts detecting the convergence of the Kalman gain `K`, see `converged`.
+    ///
+    /// If `auto_steadystate` is set, the filter switches to the steady state equations of
+    /// `predict_steadystate` and `update_steadystate` once `K` has converged: `predict` and
+    /// `update` then only propagate `x` and keep `K` and `P` fixed, which saves the matrix
+    /// inversion of each update. Overriding `R` or `H` in `update`, or `Q` in `predict`, has
+    /// no effect on a filter that has switched.
+    pub fn detect_convergence(&mut self, tol: F, window: usize, auto_steadystate: bool) {
+        let mut convergence = Convergence::new(tol, window);
+        convergence.auto_steadystate = auto_steadystate;
+        self.convergence = Some(convergence);
+    }
+
+    /// Switches the filter to the steady state equations once the Kalman gain `K` has converged.
+    ///
+    /// Shorthand for `detect_convergence(tol, window, true)`.
+    pub fn auto_steadystate(&mut self, tol: F, window: usize) {
+        self.detect_convergence(tol, window, true);
+    }
+
+    /// Returns whether the Kalman gain `K` has converged, i.e., whether no element of `K` changed
+    /// by more than the tolerance in each of the last updates of the window given to
+    /// `detect_convergence`. Always `false` if convergence is not detected.
+    pub fn converged(&self) -> bool {
+        self.convergence.as_ref().map_or(false, Convergence::converged)
+    }
+
+    /// Returns whether the filter has switched to the steady state equations, see
+    /// `auto_steadystate`.
+    pub fn is_steady_state(&self) -> bool {
+        self.convergence
+            .as_ref()
+            .map_or(false, |convergence| convergence.auto_steadystate && convergence.converged())
+    }
+
+    /// Add a new measurement (z) to the Kalman filter, assuming Student's t distributed
+    /// measurement noise with `dof` degrees of freedom and scale `R`, so outliers are down-weighted.
+    ///
+    /// The update is approximated by iteratively reweighting the measurement noise: starting
+    /// from the prior, the measurement is fused with `R * scale`, where
+    /// `scale = (dof + d^2) / (dof + DimZ)` and `d^2 = r^T R^-1 r + tr(R^-1 H P H^T)` is the
+    /// expected squared Mahalanobis distance of the residual `r` of the previous iteration. After
+    /// a few iterations, the regular update is applied with the resulting noise. The noise is
+    /// never scaled below `R`, so measurements consistent with the prediction are fused as in
+    /// `update`.
+    ///
+    /// The degrees of freedom `dof` control how heavy the tails of the noise distribution are.
+    /// Small values, e.g. 1 to 5, strongly down-weight measurements with large residuals, while
+    /// the update approaches `update` as `dof` grows towards infinity.
+    ///
+    /// # Panics
+    /// Panics if `R` is singular.
+    ///
+    /// # References
+    /// * Roth, Özkan, Gustafsson, "A Student's t filter for heavy tailed process and measurement
+    ///   noise". IEEE International Conference on Acoustics, Speech and Signal Processing, 2013.
+    pub fn update_robust(&mut self, z: &VectorN<F, DimZ>, dof: F) {
+        const ITERATIONS: usize = 5;
+
+        let RI = self.R.clone().try_inverse().expect("R must be invertible");
+        let HT = self.H.transpose();
+        let I = MatrixMN::<F, DimX, DimX>::identity();
+        let dim_z: F = nalgebra::convert(DimZ::dim() as f64);
+
+        let mut x = self.x.clone();
+        let mut P = self.P.clone();
+        let mut scale = F::one();
+        for _ in 0..ITERATIONS {
+            let r = z - &self.H * &x;
+            let d_sq = r.dot(&(&RI * &r)) + (&RI * &self.H * &P * &HT).trace();
+            scale = ((dof + d_sq) / (dof + dim_z)).max(F::one());
+
+            let PHT = &self.P * &HT;
+            let S = &self.H * &PHT + &self.R * scale;
+            let K = PHT * self.inv_strategy.invert(&S);
+            x = &self.x + &K * (z - &self.H * &self.x);
+            P = (&I - K * &self.H) * &self.P;
+        }
+
+        let R = &self.R * scale;
+        self.update(z, Some(&R), None);
+    }
+
+    /// Add several measurements taken at the same time, each given as the tuple `(z, R, H)`, to
+    /// the Kalman filter in a single update.
+    ///
+    /// The measurements are applied one after the other, each with its own Kalman gain, which is
+    /// equivalent to the update with all measurements stacked into one and a block diagonal
+    /// measurement noise. Afterwards, the residual `y`, gain `K` and system uncertainty `S` are
+    /// those of the last measurement. The call counts as a single update step: the state is
+    /// recorded once for `rewind` and `smooth`, and the convergence detection, the NIS window,
+    /// the adaptive process noise and the update callback see the last measurement once.
+    ///
+    /// Once the filter has switched to the steady state, see `auto_steadystate`, the gains are
+    /// computed from the prior covariance that the fixed `P` leads to, `F P F^T * alpha_sq + Q`,
+    /// and `P` is left unchanged.
+    #[allow(clippy::type_complexity)]
+    pub fn update_multiple(
+        &mut self,
+        measurements: &[(VectorN<F, DimZ>, MatrixMN<F, DimZ, DimZ>, MatrixMN<F, DimZ, DimX>)],
+    ) {
+        self.record();
+
+        let steady_state = self.is_steady_state();
+        let K = self.K.clone();
+        let mut P = if steady_state {
+            (&self.F * &self.P * self.F.transpose()) * self.alpha_sq + &self.Q
+        } else {
+            self.P.clone()
+        };
+        for (z, R, H) in measurements {
+            P = self.apply_residual(z - H * &self.x, R, H, &P);
+        }
+
+        if let Some((z, _, _)) = measurements.last() {
+            if !steady_state {
+                self.P = P;
+                if let Some(convergence) = self.convergence.as_mut() {
+                    convergence.observe((&self.K - K).amax());
+                }
+            }
+            self.finish_update(z);
+        }
+        self.record_posterior();
+    }
+
+    /// Add a new measurement (z) to the Kalman filter, where the measurement noise is correlated
+    /// with the process noise of the preceding prediction.
+    ///
+    /// This is needed when the same disturbance drives both the system and the sensor, e.g.,
+    /// when the measurement is taken from a sensor mounted on the vibrating system itself.
+    /// `M` is the cross-covariance between process and measurement noise. With `M = 0`, this is
+    /// equivalent to `update`, including the convergence detection of `auto_steadystate` and the
+    /// adaptive process noise.
+    pub fn update_correlated(
+        &mut self,
+        z: &VectorN<F, DimZ>,
+        R: Option<&MatrixMN<F, DimZ, DimZ>>,
+        H: Option<&MatrixMN<F, DimZ, DimX>>,
+        M: &MatrixMN<F, DimX, DimZ>,
+    ) {
+        self.record();
+
+        let R = R.unwrap_or(&self.R);
+        let H = H.unwrap_or(&self.H);
+
+        self.y = z - H * &self.x;
+
+        if self.is_steady_state() {
+            self.x = &self.x + &self.K * &self.y;
+        } else {
+            let PHT = self.P.clone() * H.transpose();
+            self.S = H * &PHT + H * M + M.transpose() * H.transpose() + R;
+
+            self.SI = self.inv_strategy.invert(&self.S);
+
+            let K = (PHT + M) * &self.SI;
+            let gain_change = (&K - &self.K).amax();
+            self.K = K;
+
+            self.x = &self.x + &self.K * &self.y;
+            self.P = &self.P - &self.K * (H * &self.P + M.transpose());
+
+            if let Some(convergence) = self.convergence.as_mut() {
+                convergence.observe(gain_change);
+            }
+        }
+
+        self.finish_update(z);
+        self.record_posterior();
     }
 
     /// Predict state (prior) using the Kalman filter state propagation equations.
@@ -147,10 +488,12 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         u: Option<&VectorN<F, DimU>>,
         B: Option<&MatrixMN<F, DimX, DimU>>,
     ) {
+        self.record();
+
         let B = if B.is_some() { B } else { self.B.as_ref() };
 
-        if B.is_some() && u.is_some() {
-            self.x = &self.F * &self.x + B.unwrap() * u.unwrap();
+        if let (Some(B), Some(u)) = (B, u) {
+            self.x = &self.F * &self.x + B * u;
         } else {
             self.x = &self.F * &self.x;
         }
@@ -162,12 +505,15 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
     /// Add a new measurement (z) to the Kalman filter without recomputing the Kalman gain K,
     /// the state covariance P, or the system uncertainty S.
     pub fn update_steadystate(&mut self, z: &VectorN<F, DimZ>) {
+        self.record();
+
         self.y = z - &self.H * &self.x;
         self.x = &self.x + &self.K * &self.y;
 
         self.z = Some(z.clone());
         self.x_post = self.x.clone();
         self.P_post = self.P.clone();
+        self.record_posterior();
     }
 
     /// Predicts the next state of the filter and returns it without altering the state of the filter.
@@ -182,8 +528,8 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
 
         let B = self.B.as_ref();
         let x = {
-            if B.is_some() && u.is_some() {
-                F * &self.x + B.unwrap() * u.unwrap()
+            if let (Some(B), Some(u)) = (B, u) {
+                F * &self.x + B * u
             } else {
                 F * &self.x
             }
@@ -194,6 +540,53 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         (x, P)
     }
 
+    /// Predicts the state of the filter for the next `n` steps without control input and returns
+    /// the state estimate and covariance after each step. Does not alter the state of the filter.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    pub fn forecast(&self, n: usize) -> Vec<(VectorN<F, DimX>, MatrixMN<F, DimX, DimX>)> {
+        let FT = self.F.transpose();
+
+        let mut x = self.x.clone();
+        let mut P = self.P.clone();
+        (0..n)
+            .map(|_| {
+                x = &self.F * &x;
+                P = ((&self.F * &P) * &FT) * self.alpha_sq + &self.Q;
+                (x.clone(), P.clone())
+            })
+            .collect()
+    }
+
+    /// Predicts the next state of the filter and returns it together with the system uncertainty
+    /// `S` and the Kalman gain `K` that an update with the measurement function `H` and the
+    /// measurement noise `R` would use. Does not alter the state of the filter.
+    ///
+    /// This allows evaluating candidate measurements before taking them, e.g., for active sensing.
+    #[allow(clippy::type_complexity)]
+    pub fn get_prediction_full(
+        &self,
+        u: Option<&VectorN<F, DimU>>,
+        H: Option<&MatrixMN<F, DimZ, DimX>>,
+        R: Option<&MatrixMN<F, DimZ, DimZ>>,
+    ) -> (
+        VectorN<F, DimX>,
+        MatrixMN<F, DimX, DimX>,
+        MatrixMN<F, DimZ, DimZ>,
+        MatrixMN<F, DimX, DimZ>,
+    ) {
+        let H = H.unwrap_or(&self.H);
+        let R = R.unwrap_or(&self.R);
+
+        let (x, P) = self.get_prediction(u);
+
+        let PHT = &P * H.transpose();
+        let S = H * &PHT + R;
+        let K = PHT * self.inv_strategy.invert(&S);
+
+        (x, P, S, K)
+    }
+
     ///  Computes the new estimate based on measurement `z` and returns it without altering the state of the filter.
     pub fn get_update(&self, z: &VectorN<F, DimZ>) -> (VectorN<F, DimX>, MatrixMN<F, DimX, DimX>) {
         let R = &self.R;
@@ -206,7 +599,7 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         let PHT = &(P * H.transpose());
 
         let S = H * PHT + R;
-        let SI = S.try_inverse().unwrap();
+        let SI = self.inv_strategy.invert(&S);
 
         let K = &(PHT * SI);
 
@@ -219,121 +612,3232 @@ impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
         (x, P)
     }
 
+    /// Returns the expected information gain of an update with the measurement function `H` and
+    /// the measurement noise `R`, i.e., the mutual information `0.5 ln(det(P) / det(P_post))`
+    /// between the state and the measurement, where `P_post` is the state covariance after the
+    /// update. Does not alter the state of the filter.
+    ///
+    /// The gain does not depend on the value of the measurement, so candidate measurements can be
+    /// compared before taking them, e.g., for active sensing. It is computed with the equivalent
+    /// `0.5 ln(det(S) / det(R))`, which is also defined for a singular `P`.
+    ///
+    /// # Panics
+    /// Panics if `R` or the system uncertainty `S` is not positive definite.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{Matrix1, U1};
+    ///
+    /// let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+    /// kf.P = Matrix1::new(3.0);
+    /// let gain = kf.expected_information_gain(&Matrix1::new(1.0), &Matrix1::new(1.0));
+    /// assert!((gain - 0.5 * 4.0_f64.ln()).abs() < 1e-12);
+    /// ```
+    pub fn expected_information_gain(
+        &self,
+        H: &MatrixMN<F, DimZ, DimX>,
+        R: &MatrixMN<F, DimZ, DimZ>,
+    ) -> F {
+        // 0.5 ln det(M) is the sum of the logarithms of the diagonal of the Cholesky factor.
+        let half_ln_det = |M: MatrixMN<F, DimZ, DimZ>, name: &str| {
+            let chol = M.cholesky().unwrap_or_else(|| panic!("{} must be positive definite", name));
+            chol.l_dirty().diagonal().iter().fold(F::zero(), |acc, l| acc + l.ln())
+        };
+        let S = H * &self.P * H.transpose() + R;
+        half_ln_det(S, "S") - half_ln_det(R.clone(), "R")
+    }
+
     /// Returns the residual for the given measurement (z). Does not alter the state of the filter.
     pub fn residual_of(&self, z: &VectorN<F, DimZ>) -> VectorN<F, DimZ> {
         z - (&self.H * &self.x_prior)
     }
 
+    /// Returns the predicted measurement `H x_prior` and its covariance `H P_prior H^T + R`.
+    /// Does not alter the state of the filter.
+    pub fn predicted_measurement(&self) -> (VectorN<F, DimZ>, MatrixMN<F, DimZ, DimZ>) {
+        let z = &self.H * &self.x_prior;
+        let S = &self.H * &self.P_prior * self.H.transpose() + &self.R;
+        (z, S)
+    }
+
+    /// Transforms the state of the filter with the invertible linear map `t`, e.g., to change its
+    /// units, so that the transformed filter behaves exactly like the original one.
+    ///
+    /// States and their covariances become `t x` and `t P t^T`, the state transition becomes
+    /// `t F t^-1`, the measurement function `H t^-1`, the control transition `t B` and the
+    /// process noise `t Q t^T`. Measurements, `R`, `S` and the residual are unchanged. The
+    /// states recorded for `rewind` and `smooth` are transformed as well.
+    ///
+    /// # Panics
+    /// Panics if `t` is singular.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{U1, U2, Matrix2, Vector2};
+    ///
+    /// let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+    /// kf.x = Vector2::new(1.5, 0.1);
+    /// kf.H = Vector2::new(1.0, 0.0).transpose();
+    ///
+    /// // Metres to millimetres.
+    /// kf.transform(&Matrix2::from_diagonal_element(1000.0));
+    /// assert_eq!(kf.x, Vector2::new(1500.0, 100.0));
+    /// assert_eq!(kf.H[0], 0.001);
+    /// ```
+    pub fn transform(&mut self, t: &MatrixMN<F, DimX, DimX>) {
+        let TI = t.clone().try_inverse().expect("transformation must be invertible");
+        let TT = t.transpose();
+
+        self.x = t * &self.x;
+        self.P = t * &self.P * &TT;
+        self.x_prior = t * &self.x_prior;
+        self.P_prior = t * &self.P_prior * &TT;
+        self.x_post = t * &self.x_post;
+        self.P_post = t * &self.P_post * &TT;
+        self.Q = t * &self.Q * &TT;
+        self.F = t * &self.F * &TI;
+        self.H = &self.H * &TI;
+        self.K = t * &self.K;
+        if let Some(B) = self.B.as_mut() {
+            *B = t * &*B;
+        }
+        self.transform_records(t);
+    }
+
+    #[cfg(feature = "alloc")]
+    fn transform_records(&mut self, t: &MatrixMN<F, DimX, DimX>) {
+        let TT = t.transpose();
+        let transform = |(x, P): (&mut VectorN<F, DimX>, &mut MatrixMN<F, DimX, DimX>)| {
+            *x = t * &*x;
+            *P = t * &*P * &TT;
+        };
+
+        if let Some(history) = self.history.as_mut() {
+            history.xs.iter_mut().zip(history.Ps.iter_mut()).for_each(transform);
+        }
+        if let Some(trajectory) = self.trajectory.as_mut() {
+            trajectory.xs.iter_mut().zip(trajectory.Ps.iter_mut()).for_each(transform);
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn transform_records(&mut self, _t: &MatrixMN<F, DimX, DimX>) {}
+
+    /// Runs predict and update over the measurements `data` and returns the residual `y` of each
+    /// update, i.e., the innovation sequence.
+    ///
+    /// For a well-tuned filter the innovations are white, i.e., uncorrelated over time with zero
+    /// mean and the covariance `S`.
+    #[cfg(feature = "alloc")]
+    pub fn innovations(&mut self, data: &[VectorN<F, DimZ>]) -> Vec<VectorN<F, DimZ>> {
+        data.iter()
+            .map(|z| {
+                self.predict(None, None, None, None);
+                self.update(z, None, None);
+                self.y.clone()
+            })
+            .collect()
+    }
+
+    /// Runs predict and update over the measurements `data` and returns the sum of the
+    /// log-likelihoods of all measurements, i.e., the log-likelihood of the whole data set under
+    /// the filter configuration.
+    ///
+    /// This is the objective for tuning parameters like `Q` and `R` by maximum likelihood: run
+    /// the filter with each candidate configuration from the same initial state and choose the
+    /// one with the largest log-likelihood.
+    pub fn batch_log_likelihood(&mut self, data: &[VectorN<F, DimZ>]) -> F {
+        data.iter().fold(F::zero(), |sum, z| {
+            self.predict(None, None, None, None);
+            self.update(z, None, None);
+            sum + self.log_likelihood()
+        })
+    }
+
+    /// Estimates the process noise `Q` and the measurement noise `R` from the measurements `data`
+    /// with `iters` iterations of the expectation-maximization (EM) algorithm.
+    ///
+    /// Each iteration runs the filter over `data` from the current state `x` and `P` with the
+    /// current `Q` and `R`, smooths the result with the Rauch-Tung-Striebel smoother, and sets `Q`
+    /// and `R` to the covariances of the process and measurement noise implied by the smoothed
+    /// states. The state transition `F` and measurement function `H` are assumed to be correct
+    /// and are not changed, control inputs and fading memory are not used. The filter itself is
+    /// not run, so `x` and `P` are unchanged and the filter can be run over `data` afterwards.
+    ///
+    /// Each iteration does not decrease the log-likelihood of the data, see
+    /// `batch_log_likelihood`, but the convergence can be slow, so tens to hundreds of
+    /// iterations may be needed. EM converges to a local maximum of the likelihood, which
+    /// depends on the initial `Q` and `R`; start with positive definite guesses of the right
+    /// order of magnitude. Noise components that cannot be distinguished in the data, e.g.,
+    /// process noise on states that are not observed, are not estimated reliably, and short
+    /// data sets give noisy estimates.
+    ///
+    /// # Panics
+    /// Panics if a predicted state covariance or the system uncertainty becomes singular.
+    ///
+    /// # References
+    /// * Shumway, Stoffer, "An approach to time series smoothing and forecasting using the EM
+    ///   algorithm". Journal of Time Series Analysis 3, 1982.
+    #[cfg(feature = "alloc")]
+    pub fn em_estimate_noise(&mut self, data: &[VectorN<F, DimZ>], iters: usize) {
+        let n = data.len();
+        if n == 0 {
+            return;
+        }
+        let n_f: F = nalgebra::convert(n as f64);
+        let FT = self.F.transpose();
+        let HT = self.H.transpose();
+        let I = MatrixMN::<F, DimX, DimX>::identity();
+
+        for _ in 0..iters {
+            // Forward pass, keeping the predicted and updated estimates of each step.
+            let mut x = self.x.clone();
+            let mut P = self.P.clone();
+            let mut P_priors = Vec::with_capacity(n);
+            let mut xs = Vec::with_capacity(n);
+            let mut Ps = Vec::with_capacity(n);
+            for z in data {
+                x = &self.F * x;
+                P = &self.F * P * &FT + &self.Q;
+                P_priors.push(P.clone());
+
+                let PHT = &P * &HT;
+                let S = &self.H * &PHT + &self.R;
+                let K = PHT * S.try_inverse().expect("S must be invertible");
+                x = &x + &K * (z - &self.H * &x);
+                let I_KH = &I - &K * &self.H;
+                P = &I_KH * P * I_KH.transpose() + &K * &self.R * K.transpose();
+                xs.push(x.clone());
+                Ps.push(P.clone());
+            }
+
+            // Backward pass, including the initial state, so that the first transition is
+            // smoothed as well. `gains[k]` smooths the estimate before step `k` with the one
+            // after it.
+            let gain = |P: &MatrixMN<F, DimX, DimX>, P_prior: &MatrixMN<F, DimX, DimX>| {
+                P * &FT * P_prior.clone().try_inverse().expect("P must be invertible")
+            };
+            let mut gains = Vec::with_capacity(n);
+            gains.push(gain(&self.P, &P_priors[0]));
+            for k in (0..n - 1).rev() {
+                let J = gain(&Ps[k], &P_priors[k + 1]);
+                xs[k] = &xs[k] + &J * (&xs[k + 1] - &self.F * &xs[k]);
+                Ps[k] = &Ps[k] + &J * (&Ps[k + 1] - &P_priors[k + 1]) * J.transpose();
+                gains.push(J);
+            }
+            gains[1..].reverse();
+            let x0 = &self.x + &gains[0] * (&xs[0] - &self.F * &self.x);
+            let P0 = &self.P + &gains[0] * (&Ps[0] - &P_priors[0]) * gains[0].transpose();
+
+            // Maximization, from the expected squared residuals of the transitions and
+            // measurements under the smoothed distribution.
+            let mut Q = MatrixMN::<F, DimX, DimX>::zeros();
+            let mut R = MatrixMN::<F, DimZ, DimZ>::zeros();
+            for k in 0..n {
+                let (x_prev, P_prev) = if k == 0 { (&x0, &P0) } else { (&xs[k - 1], &Ps[k - 1]) };
+                // Covariance of the state after and before the transition.
+                let P_cross = &Ps[k] * gains[k].transpose();
+                let w = &xs[k] - &self.F * x_prev;
+                Q += outer(&w) + &Ps[k]
+                    - &P_cross * &FT
+                    - &self.F * P_cross.transpose()
+                    + &self.F * P_prev * &FT;
+
+                let v = &data[k] - &self.H * &xs[k];
+                R += outer(&v) + &self.H * &Ps[k] * &HT;
+            }
+
+            Q /= n_f;
+            R /= n_f;
+            // Symmetrize against the accumulation of rounding errors.
+            self.Q = (&Q + Q.transpose()) * nalgebra::convert::<f64, F>(0.5);
+            self.R = (&R + R.transpose()) * nalgebra::convert::<f64, F>(0.5);
+        }
+    }
+
+    /// Returns the marginal distribution of the state components `indices`, i.e., the entries of
+    /// `x` and the principal submatrix of `P` for these components, in the order of `indices`.
+    ///
+    /// # Panics
+    /// Panics if the length of `indices` does not match `DimM` or an index is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{U1, U3, Vector3, Matrix3};
+    ///
+    /// let mut kf: KalmanFilter<f64, U3, U1, U1> = KalmanFilter::default();
+    /// kf.x = Vector3::new(1.0, 2.0, 3.0);
+    /// kf.P = Matrix3::new(
+    ///     1.0, 0.1, 0.2,
+    ///     0.1, 2.0, 0.3,
+    ///     0.2, 0.3, 3.0,
+    /// );
+    ///
+    /// let (x, P) = kf.marginal::<U1>(&[2]);
+    /// assert_eq!(x[0], 3.0);
+    /// assert_eq!(P[(0, 0)], 3.0);
+    /// ```
+    pub fn marginal<DimM>(&self, indices: &[usize]) -> (VectorN<F, DimM>, MatrixMN<F, DimM, DimM>)
+        where
+            DimM: DimName,
+            DefaultAllocator: Allocator<F, DimM> + Allocator<F, DimM, DimM>,
+    {
+        assert_eq!(indices.len(), DimM::dim(), "indices must have one entry per marginal dimension");
+
+        let x = VectorN::<F, DimM>::from_fn(|r, _| self.x[indices[r]]);
+        let P = MatrixMN::<F, DimM, DimM>::from_fn(|r, c| self.P[(indices[r], indices[c])]);
+        (x, P)
+    }
+
+    /// Returns `n` samples drawn from the current state distribution `N(x, P)`.
+    ///
+    /// Each sample is `x + L w`, where `L` is the Cholesky factor of `P` and `w` a vector of
+    /// independent standard normal variables generated from `rng` with the Box-Muller transform.
+    ///
+    /// # Panics
+    /// Panics if `P` is not positive definite, since it has no Cholesky factor. This includes
+    /// positive semidefinite `P` of states that are known exactly; see `check_consistency`.
+    #[cfg(all(feature = "rand", feature = "alloc"))]
+    pub fn sample(&self, rng: &mut impl Rng, n: usize) -> Vec<VectorN<F, DimX>> {
+        let L = self
+            .P
+            .clone()
+            .cholesky()
+            .expect("P must be positive definite")
+            .unpack();
+
+        let two_pi: F = nalgebra::convert(2.0 * core::f64::consts::PI);
+        let mut standard_normal = || {
+            // `1 - u` is in (0, 1], so the logarithm is finite.
+            let u1: F = nalgebra::convert(1.0 - rng.gen::<f64>());
+            let u2: F = nalgebra::convert(rng.gen::<f64>());
+            (-u1.ln() * nalgebra::convert(2.0)).sqrt() * (two_pi * u2).cos()
+        };
+
+        (0..n)
+            .map(|_| {
+                let w = VectorN::<F, DimX>::from_fn(|_, _| standard_normal());
+                &self.x + &L * w
+            })
+            .collect()
+    }
+
     /// Helper function that converts a state into a measurement.
     pub fn measurement_of_state(&self, x: &VectorN<F, DimX>) -> VectorN<F, DimZ> {
         &self.H * x
     }
-}
 
-#[allow(non_snake_case)]
-impl<F, DimX, DimZ, DimU> Default for KalmanFilter<F, DimX, DimZ, DimU>
-    where
-        F: RealField,
-        DimX: DimName,
-        DimZ: DimName,
-        DimU: DimName,
-        DefaultAllocator: Allocator<F, DimX>
-        + Allocator<F, DimZ>
-        + Allocator<F, DimX, DimZ>
-        + Allocator<F, DimZ, DimX>
-        + Allocator<F, DimZ, DimZ>
-        + Allocator<F, DimX, DimX>
-        + Allocator<F, DimU>
-        + Allocator<F, DimX, DimU>,
-{
-    /// Returns a Kalman filter initialised with default parameters.
-    fn default() -> Self {
-        let x = VectorN::<F, DimX>::from_element(F::one());
-        let P = MatrixMN::<F, DimX, DimX>::identity();
-        let Q = MatrixMN::<F, DimX, DimX>::identity();
-        let F = MatrixMN::<F, DimX, DimX>::identity();
-        let H = MatrixMN::<F, DimZ, DimX>::from_element(F::zero());
-        let R = MatrixMN::<F, DimZ, DimZ>::identity();
-        let alpha_sq = F::one();
+    /// Runs the Rauch-Tung-Striebel smoother in-place on the posterior state estimates `xs` and
+    /// covariances `Ps` computed by this filter, using its state transition `F` and process
+    /// noise `Q`.
+    ///
+    /// # Panics
+    /// Panics if `xs` and `Ps` differ in length.
+    pub fn rts_smoother(&self, xs: &mut [VectorN<F, DimX>], Ps: &mut [MatrixMN<F, DimX, DimX>]) {
+        assert_eq!(xs.len(), Ps.len(), "xs and Ps must have the same length");
 
-        let z = None;
+        for k in (0..xs.len().saturating_sub(1)).rev() {
+            let Pp = &self.F * &Ps[k] * self.F.transpose() + &self.Q;
+            let K = &Ps[k] * self.F.transpose() * Pp.clone().try_inverse().unwrap();
 
-        let K = MatrixMN::<F, DimX, DimZ>::from_element(F::zero());
-        let y = VectorN::<F, DimZ>::from_element(F::one());
-        let S = MatrixMN::<F, DimZ, DimZ>::from_element(F::zero());
-        let SI = MatrixMN::<F, DimZ, DimZ>::from_element(F::zero());
+            xs[k] = &xs[k] + &K * (&xs[k + 1] - &self.F * &xs[k]);
+            Ps[k] = &Ps[k] + &K * (&Ps[k + 1] - Pp) * K.transpose();
+        }
+    }
 
-        let x_prior = x.clone();
-        let P_prior = P.clone();
+    /// Runs the two-filter smoother in-place on the posterior state estimates `xs` and
+    /// covariances `Ps` computed by this filter from the measurements `zs`, using its state
+    /// transition `F`, process noise `Q`, measurement function `H` and measurement noise `R`.
+    ///
+    /// A backward information filter is run over `zs`, and its estimate from the measurements
+    /// after each step is fused with the forward estimate of that step. The result is the same
+    /// as that of `rts_smoother`, but the backward pass does not depend on the forward pass and
+    /// does not need the inverse of the predicted covariance. Control inputs are not supported.
+    ///
+    /// # Panics
+    /// Panics if `zs`, `xs` and `Ps` differ in length, or if `R` or a forward covariance is
+    /// singular.
+    pub fn two_filter_smoother(
+        &self,
+        zs: &[VectorN<F, DimZ>],
+        xs: &mut [VectorN<F, DimX>],
+        Ps: &mut [MatrixMN<F, DimX, DimX>],
+    ) {
+        assert_eq!(xs.len(), Ps.len(), "xs and Ps must have the same length");
+        assert_eq!(zs.len(), xs.len(), "zs and xs must have the same length");
 
-        let x_post = x.clone();
-        let P_post = P.clone();
+        let I = MatrixMN::<F, DimX, DimX>::identity();
+        let HTRI = self.H.transpose() * self.R.clone().try_inverse().unwrap();
+        let HTRIH = &HTRI * &self.H;
 
-        KalmanFilter {
-            x,
-            P,
-            x_prior,
-            P_prior,
-            x_post,
-            P_post,
-            z,
-            R,
-            Q,
-            B: None,
-            F,
-            H,
-            y,
-            K,
-            S,
-            SI,
-            alpha_sq,
+        // Information matrix and vector of the backward estimate from the later measurements.
+        let mut info = MatrixMN::<F, DimX, DimX>::zeros();
+        let mut info_x = VectorN::<F, DimX>::zeros();
+
+        for k in (0..xs.len()).rev() {
+            let PI = Ps[k].clone().try_inverse().unwrap();
+            let P = (&PI + &info).try_inverse().unwrap();
+            xs[k] = &P * (&PI * &xs[k] + &info_x);
+            Ps[k] = P;
+
+            // Add the measurement and predict backwards to the previous step.
+            let info_z = &info + &HTRIH;
+            let info_x_z = &info_x + &HTRI * &zs[k];
+            let G = self.F.transpose() * (&I + &info_z * &self.Q).try_inverse().unwrap();
+            info = &G * info_z * &self.F;
+            info_x = G * info_x_z;
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use assert_approx_eq::assert_approx_eq;
-    use nalgebra::base::Vector1;
-    use nalgebra::{U1, U2, Vector2, Matrix2, Matrix1};
+    /// Enables recording the state before each predict and update step, keeping the last
+    /// `capacity` states so that they can be restored with `rewind`.
+    ///
+    /// Each recorded step stores a copy of `x` and `P`, i.e., `DimX * (DimX + 1)` scalars, so the
+    /// history uses at most `capacity` times that much memory. Discards any previously recorded
+    /// states.
+    #[cfg(feature = "alloc")]
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history = Some(History::new(capacity));
+    }
 
-    use super::*;
+    /// Stops recording states for `rewind` and discards the recorded ones.
+    #[cfg(feature = "alloc")]
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
 
-    #[test]
-    fn test_univariate_kf_setup() {
-        let mut kf: KalmanFilter<f32, U1, U1, U1> = KalmanFilter::<f32, U1, U1, U1>::default();
+    /// Returns the record of past states for `rewind`, or `None` if it is disabled, see
+    /// `enable_history`.
+    #[cfg(feature = "alloc")]
+    pub fn history(&self) -> Option<&History<F, DimX>> {
+        self.history.as_ref()
+    }
 
-        for i in 0..1000 {
-            let zf = i as f32;
-            let z = Vector1::new(zf);
-            kf.predict(None, None, None, None);
-            kf.update(&z, None, None);
-            assert_approx_eq!(zf, kf.z.clone().unwrap()[0]);
+    /// Restores `x` and `P` to the values they had before the last `n` predict and update steps.
+    ///
+    /// Returns an error and leaves the filter unchanged if the history is disabled or contains
+    /// fewer than `n` steps.
+    #[cfg(feature = "alloc")]
+    pub fn rewind(&mut self, n: usize) -> Result<(), HistoryError> {
+        let history = self.history.as_mut().ok_or(HistoryError::Disabled)?;
+        history.rewind(n, &mut self.x, &mut self.P)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn record(&mut self) {
+        if let Some(history) = self.history.as_mut() {
+            history.push(self.x.clone(), self.P.clone());
         }
     }
 
-    #[test]
-    fn test_1d_reference() {
-        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+    #[cfg(not(feature = "alloc"))]
+    fn record(&mut self) {}
 
-        kf.x = Vector2::new(2.0, 0.0);
-        kf.F = Matrix2::new(
-            1.0, 1.0,
-            0.0, 1.0,
-        );
-        kf.H = Vector2::new(1.0, 0.0).transpose();
-        kf.P *= 1000.0;
-        kf.R = Matrix1::new(5.0);
-        kf.Q = Matrix2::repeat(0.0001);
+    /// Enables recording the posterior state after each update, so the whole run of the filter
+    /// can be smoothed with `smooth`.
+    ///
+    /// Unlike the history of `enable_history`, the record is unbounded.
+    #[cfg(feature = "alloc")]
+    pub fn enable_smoothing(&mut self) {
+        self.trajectory = Some(Trajectory::default());
+    }
 
-        for t in 0..100 {
-            let z = Vector1::new(t as f64);
-            kf.update(&z, None, None);
-            kf.predict(None, None, None, None);
-            // This matches the results from an equivalent filterpy filter.
-            assert_approx_eq!(kf.x[0],
-                              if t == 0 { 0.0099502487 } else { t as f64 + 1.0 },
-                              0.05);
+    /// Stops recording the posterior states for `smooth` and discards the recorded ones.
+    #[cfg(feature = "alloc")]
+    pub fn disable_smoothing(&mut self) {
+        self.trajectory = None;
+    }
+
+    /// Returns the posterior states recorded for `smooth`, or `None` if smoothing is not
+    /// enabled, see `enable_smoothing`.
+    #[cfg(feature = "alloc")]
+    pub fn trajectory(&self) -> Option<&Trajectory<F, DimX>> {
+        self.trajectory.as_ref()
+    }
+
+    /// Returns the smoothed state estimates and covariances of all updates since
+    /// `enable_smoothing` was called, computed with `rts_smoother` from the recorded posteriors.
+    ///
+    /// Like `rts_smoother`, this assumes that each update was preceded by one prediction with the
+    /// current `F` and `Q`. Returns empty vectors if smoothing is not enabled.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    pub fn smooth(&self) -> (Vec<VectorN<F, DimX>>, Vec<MatrixMN<F, DimX, DimX>>) {
+        let (mut xs, mut Ps) = match self.trajectory.as_ref() {
+            Some(trajectory) => (trajectory.xs.clone(), trajectory.Ps.clone()),
+            None => (Vec::new(), Vec::new()),
+        };
+        self.rts_smoother(&mut xs, &mut Ps);
+        (xs, Ps)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn record_posterior(&mut self) {
+        if let Some(trajectory) = self.trajectory.as_mut() {
+            trajectory.xs.push(self.x.clone());
+            trajectory.Ps.push(self.P.clone());
         }
     }
+
+    #[cfg(not(feature = "alloc"))]
+    fn record_posterior(&mut self) {}
+
+    /// Returns the residual `y` of the last update, or `None` before the first update.
+    ///
+    /// Unlike the field `y`, which holds a placeholder until the filter is updated, this cannot
+    /// return a stale default value.
+    pub fn innovation(&self) -> Option<&VectorN<F, DimZ>> {
+        self.z.as_ref().map(|_| &self.y)
+    }
+
+    /// Returns the system uncertainty `S` of the last update, or `None` before the first update.
+    pub fn innovation_covariance(&self) -> Option<&MatrixMN<F, DimZ, DimZ>> {
+        self.z.as_ref().map(|_| &self.S)
+    }
+
+    /// Returns the Kalman gain `K` of the last update, or `None` before the first update.
+    pub fn kalman_gain(&self) -> Option<&MatrixMN<F, DimX, DimZ>> {
+        self.z.as_ref().map(|_| &self.K)
+    }
+
+    /// Returns the normalized innovation squared (NIS) `y^T SI y` of the last update.
+    ///
+    /// For a consistent filter the NIS is chi-square distributed with `DimZ` degrees of freedom,
+    /// so its average over many updates should be close to `DimZ`. Consistently larger values
+    /// indicate that the filter underestimates its uncertainty, smaller values that it
+    /// overestimates it. See `stats::chi2_ppf` for gating thresholds.
+    pub fn nis(&self) -> F {
+        self.y.dot(&(&self.SI * &self.y))
+    }
+
+    /// Enables recording the NIS of the last `window` updates, so that their average can be
+    /// monitored with `average_nis`.
+    #[cfg(feature = "alloc")]
+    pub fn enable_nis_window(&mut self, window: usize) {
+        self.nis_window = Some(NisWindow::new(window));
+    }
+
+    /// Returns the average NIS over the rolling window, or `None` if the window is disabled or
+    /// no update has been recorded yet.
+    #[cfg(feature = "alloc")]
+    pub fn average_nis(&self) -> Option<F> {
+        self.nis_window.as_ref().and_then(NisWindow::mean)
+    }
+
+    /// Returns the rolling window of the NIS of the last updates, or `None` if it is disabled,
+    /// see `enable_nis_window`.
+    #[cfg(feature = "alloc")]
+    pub fn nis_window(&self) -> Option<&NisWindow<F>> {
+        self.nis_window.as_ref()
+    }
+
+    /// Stops recording the NIS of the updates and discards the recorded values.
+    #[cfg(feature = "alloc")]
+    pub fn disable_nis_window(&mut self) {
+        self.nis_window = None;
+    }
+
+    #[cfg(feature = "alloc")]
+    fn record_nis(&mut self) {
+        let nis = self.nis();
+        if let Some(window) = self.nis_window.as_mut() {
+            window.push(nis);
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn record_nis(&mut self) {}
+
+    /// Registers a callback that is called after each update that computes the system
+    /// uncertainty, i.e., `update`, `update_with_residual`, `update_correlated`,
+    /// `update_scalar`, and `update_multiple` for each of its measurements, with the residual,
+    /// system uncertainty, NIS and log-likelihood of the update. The callback is still called
+    /// once the filter has switched to the steady state, see `auto_steadystate`, with the fixed
+    /// system uncertainty. This replaces a previously registered callback.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{U1, Vector1};
+    ///
+    /// let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+    /// kf.H[0] = 1.0;
+    /// kf.set_on_update(|info| assert!(info.nis >= 0.0));
+    /// kf.update(&Vector1::new(1.0), None, None);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn set_on_update(&mut self, f: impl FnMut(&UpdateInfo<F, DimZ>) + Send + 'static) {
+        self.on_update = Some(OnUpdate(Box::new(f)));
+    }
+
+    #[cfg(feature = "alloc")]
+    fn notify_update(&mut self) {
+        if self.on_update.is_none() {
+            return;
+        }
+        let info = UpdateInfo {
+            y: self.y.clone(),
+            S: self.S.clone(),
+            nis: self.nis(),
+            log_likelihood: self.log_likelihood(),
+        };
+        if let Some(on_update) = self.on_update.as_mut() {
+            (on_update.0)(&info);
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn notify_update(&mut self) {}
+
+    /// Scales the process noise according to the normalized innovation of the last update,
+    /// if adaptive process noise is enabled.
+    fn adapt_process_noise(&mut self) {
+        if let Some(adaptive) = self.adaptive_q.as_mut() {
+            let epsilon = self.y.dot(&(&self.SI * &self.y));
+            adaptive.epsilon += (epsilon - adaptive.epsilon) * adaptive.smoothing;
+
+            if adaptive.epsilon > adaptive.threshold {
+                self.Q *= adaptive.scale;
+                adaptive.factor *= adaptive.scale;
+                adaptive.count += 1;
+            } else if adaptive.count > 0 {
+                self.Q /= adaptive.scale;
+                adaptive.factor /= adaptive.scale;
+                adaptive.count -= 1;
+            }
+        }
+    }
+
+    /// Checks that the covariance matrices `P`, `Q` and `R` are symmetric and positive
+    /// semidefinite, so a misconfigured filter can be detected before it is run.
+    ///
+    /// The dimensions of all matrices are enforced by their types and need no check.
+    pub fn check_consistency(&self) -> Result<(), ConsistencyError> {
+        check_covariance(&self.P, "P")?;
+        check_covariance(&self.Q, "Q")?;
+        check_covariance(&self.R, "R")
+    }
+
+    /// Returns the covariance the filter converges to after an update, if `F`, `H`, `Q` and `R`
+    /// stay constant, without running the filter.
+    ///
+    /// The predicted covariance of the steady state is the solution of the discrete algebraic
+    /// Riccati equation `P = F (P - P H^T (H P H^T + R)^-1 H P) F^T + Q`, which is found by
+    /// iterating it from `Q`. The returned covariance is the one of the updated estimate,
+    /// `P - P H^T (H P H^T + R)^-1 H P`. The fading memory factor `alpha_sq` is taken into account.
+    ///
+    /// Returns an error if the system uncertainty becomes singular, or if the iteration does not
+    /// converge, e.g., because a state that is not observed through `H` is unstable.
+    pub fn steady_state_covariance(&self) -> Result<MatrixMN<F, DimX, DimX>, SteadyStateError> {
+        const MAX_ITERATIONS: usize = 10_000;
+
+        // The iteration converges linearly, often slowly, so the change per step must be close
+        // to the precision of `F` to get close to the solution.
+        let tolerance = F::default_epsilon() * nalgebra::convert(1000.0);
+        let HT = self.H.transpose();
+
+        let posterior = |P: &MatrixMN<F, DimX, DimX>| {
+            let PHT = P * &HT;
+            let S = &self.H * &PHT + &self.R;
+            let SI = S.try_inverse().ok_or(SteadyStateError::Singular)?;
+            Ok(P - &PHT * SI * PHT.transpose())
+        };
+
+        let mut P = self.Q.clone();
+        for _ in 0..MAX_ITERATIONS {
+            let P_post = posterior(&P)?;
+            let P_next = &self.F * P_post * self.F.transpose() * self.alpha_sq + &self.Q;
+            let change = (&P_next - &P).amax();
+            let converged = change <= tolerance * P_next.amax().max(F::one());
+            P = P_next;
+            if converged {
+                return posterior(&P);
+            }
+        }
+        Err(SteadyStateError::NotConverged)
+    }
+
+    /// Returns the expected root mean square error of the state estimate once the filter has
+    /// converged, `sqrt(trace(P_ss) / DimX)`, where `P_ss` is the covariance from
+    /// `steady_state_covariance`.
+    ///
+    /// This allows comparing tuning choices analytically. Note that the error is averaged over
+    /// all state variables, which may have different units; see `steady_state_covariance` for
+    /// the error of individual variables.
+    pub fn steady_state_error(&self) -> Result<F, SteadyStateError> {
+        let P = self.steady_state_covariance()?;
+        Ok((P.trace() / nalgebra::convert(DimX::dim() as f64)).sqrt())
+    }
+
+    /// Returns the log-likelihood of the last measurement, computed from the residual `y`
+    /// and the system uncertainty `S` of the last update.
+    pub fn log_likelihood(&self) -> F {
+        // ln det(S) from the Cholesky factor; a singular S yields a likelihood of zero.
+        let ln_det = match self.S.clone().cholesky() {
+            Some(chol) => chol.l_dirty().diagonal().iter().fold(F::zero(), |acc, l| acc + l.ln()),
+            None => return F::zero().ln(),
+        } * nalgebra::convert(2.0);
+        let dist = self.y.dot(&(&self.SI * &self.y));
+        let dim: F = nalgebra::convert(DimZ::dim() as f64);
+        -(dist + dim * F::two_pi().ln() + ln_det) / nalgebra::convert(2.0)
+    }
+
+    /// Returns the likelihood of the last measurement, computed from the residual `y`
+    /// and the system uncertainty `S` of the last update.
+    pub fn likelihood(&self) -> F {
+        self.log_likelihood().exp()
+    }
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimU> KalmanFilter<F, DimX, U1, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, U1, DimX>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Add a new scalar measurement (z) to a Kalman filter with a one-dimensional measurement.
+    /// `R` is the variance of the measurement noise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::U1;
+    ///
+    /// let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+    /// kf.H[0] = 1.0;
+    ///
+    /// kf.predict(None, None, None, None);
+    /// kf.update_scalar(2.0, None, None);
+    /// kf.update_scalar(2.5, Some(0.5), None);
+    /// ```
+    pub fn update_scalar(&mut self, z: F, R: Option<F>, H: Option<&MatrixMN<F, U1, DimX>>) {
+        let R = R.map(Vector1::new);
+        self.update(&Vector1::new(z), R.as_ref(), H);
+    }
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName + DimSub<U1>,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>
+        + Allocator<F, DimDiff<DimX, U1>>,
+{
+    /// Returns the condition number of the state covariance `P`, i.e., the ratio of its largest
+    /// to its smallest eigenvalue in magnitude.
+    ///
+    /// A growing condition number indicates that `P` is becoming ill-conditioned and that the
+    /// update will lose precision, long before the inversion of `S` fails. A singular `P` has an
+    /// infinite condition number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{U1, U2, Matrix2};
+    ///
+    /// let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+    /// kf.P = Matrix2::new(
+    ///     4.0, 0.0,
+    ///     0.0, 1.0,
+    /// );
+    /// assert!((kf.condition_number() - 4.0).abs() < 1e-12);
+    /// ```
+    pub fn condition_number(&self) -> F {
+        let eigenvalues = self.P.symmetric_eigenvalues();
+        let (min, max) = eigenvalues.iter().fold(
+            (F::max_value(), F::zero()),
+            |(min, max), e| (min.min(e.abs()), max.max(e.abs())),
+        );
+        max / min
+    }
+
+    /// Makes the state covariance `P` positive definite again if it has lost this property, e.g.,
+    /// through rounding errors after a long run or a bad update, and returns whether a repair was
+    /// needed.
+    ///
+    /// `P` is decomposed into its eigenvalues and eigenvectors, and eigenvalues below a small
+    /// positive floor, `sqrt(epsilon)` times the largest eigenvalue in magnitude, are raised to
+    /// the floor before `P` is reconstructed. A repaired `P` is also symmetric. If all
+    /// eigenvalues are above the floor, `P` is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::kalman::kalman_filter::KalmanFilter;
+    /// use nalgebra::{U1, U2, Matrix2};
+    ///
+    /// let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+    /// kf.P = Matrix2::new(
+    ///     1.0, 1.0,
+    ///     1.0, 1.0 - 1e-9,
+    /// );
+    /// assert!(kf.repair_covariance());
+    /// assert!(kf.P.cholesky().is_some());
+    /// assert!(!kf.repair_covariance());
+    /// ```
+    pub fn repair_covariance(&mut self) -> bool {
+        let mut eigen = self.P.clone().symmetric_eigen();
+        let max = eigen.eigenvalues.amax();
+        let floor = F::default_epsilon().sqrt() * if max > F::zero() { max } else { F::one() };
+        if eigen.eigenvalues.iter().all(|&e| e >= floor) {
+            return false;
+        }
+
+        eigen.eigenvalues.apply(|e| e.max(floor));
+        let P = eigen.recompose();
+        self.P = (&P + P.transpose()) * nalgebra::convert::<_, F>(0.5);
+        true
+    }
+}
+
+/// Predicts without control input and updates with the stored `R` and `H`.
+impl<F, DimX, DimZ, DimU> Filter for KalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    type Measurement = VectorN<F, DimZ>;
+    type State = VectorN<F, DimX>;
+
+    fn predict(&mut self) {
+        KalmanFilter::predict(self, None, None, None, None);
+    }
+
+    fn update(&mut self, z: &VectorN<F, DimZ>) {
+        KalmanFilter::update(self, z, None, None);
+    }
+
+    fn state(&self) -> &VectorN<F, DimX> {
+        &self.x
+    }
+}
+
+/// Method used to invert the system uncertainty `S` in the update step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InverseStrategy {
+    /// LU decomposition, which works for any invertible matrix.
+    Lu,
+    /// Cholesky decomposition, which is faster and numerically more stable, but requires `S`
+    /// to be symmetric positive-definite. Falls back to LU decomposition if it fails.
+    Cholesky,
+}
+
+impl InverseStrategy {
+    /// Returns the inverse of the square matrix `m`.
+    ///
+    /// # Panics
+    /// Panics if `m` is singular.
+    pub fn invert<F, D>(self, m: &MatrixMN<F, D, D>) -> MatrixMN<F, D, D>
+        where
+            F: RealField,
+            D: DimName,
+            DefaultAllocator: Allocator<F, D, D>,
+    {
+        if self == InverseStrategy::Cholesky {
+            if let Some(cholesky) = m.clone().cholesky() {
+                return cholesky.inverse();
+            }
+        }
+        m.clone().try_inverse().unwrap()
+    }
+}
+
+/// Settings and state of the adaptive scaling of the process noise `Q`.
+///
+/// After each `update`, the normalized innovation squared `epsilon = y^T S^-1 y` is averaged with
+/// an exponential moving average. For a filter that matches the system, `epsilon` follows a
+/// chi-square distribution with `DimZ` degrees of freedom. While its average exceeds the
+/// `threshold`, the process model is assumed to be wrong and `Q` is multiplied by `scale` after
+/// each update. Once the average drops below the threshold, `Q` is divided by `scale` again,
+/// until it is back at its original value.
+///
+/// # References
+/// * Roger Labbe. "Kalman and Bayesian Filters in Python", chapter 14.
+///   https://github.com/rlabbe/Kalman-and-Bayesian-Filters-in-Python
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveQ<F: RealField> {
+    /// Threshold of the averaged normalized innovation above which `Q` is scaled up.
+    pub threshold: F,
+    /// Factor by which `Q` is scaled in each step.
+    pub scale: F,
+    /// Coefficient of the exponential moving average of the normalized innovation.
+    pub smoothing: F,
+    /// Averaged normalized innovation.
+    pub epsilon: F,
+    /// Factor by which `Q` is currently scaled relative to its original value.
+    pub factor: F,
+    count: usize,
+}
+
+impl<F: RealField> AdaptiveQ<F> {
+    /// Returns adaptive process noise settings with the given `threshold` on the averaged
+    /// normalized innovation, the `scale` applied to `Q` in each step and the `smoothing`
+    /// coefficient in `(0, 1]` of the moving average.
+    pub fn new(threshold: F, scale: F, smoothing: F) -> Self {
+        AdaptiveQ {
+            threshold,
+            scale,
+            smoothing,
+            epsilon: F::zero(),
+            factor: F::one(),
+            count: 0,
+        }
+    }
+}
+
+/// Detector of the convergence of the Kalman gain, see `KalmanFilter::detect_convergence`.
+///
+/// The gain is considered converged once no element of it changed by more than `tol` in
+/// `window` consecutive updates. A converged detector stays converged.
+#[derive(Debug, Clone, Copy)]
+pub struct Convergence<F: RealField> {
+    /// Largest change of an element of the gain that is considered converged.
+    pub tol: F,
+    /// Number of consecutive updates in which the gain must not change by more than `tol`.
+    pub window: usize,
+    /// Whether the filter switches to the steady state equations once the gain has converged.
+    pub auto_steadystate: bool,
+    count: usize,
+}
+
+impl<F: RealField> Convergence<F> {
+    /// Returns a detector with the tolerance `tol` and the number of consecutive updates `window`,
+    /// which does not switch the filter to the steady state equations.
+    pub fn new(tol: F, window: usize) -> Self {
+        Convergence {
+            tol,
+            window,
+            auto_steadystate: false,
+            count: 0,
+        }
+    }
+
+    /// Returns whether the gain has converged.
+    pub fn converged(&self) -> bool {
+        self.count >= self.window
+    }
+
+    fn observe(&mut self, gain_change: F) {
+        if self.converged() {
+            return;
+        }
+        if gain_change <= self.tol {
+            self.count += 1;
+        } else {
+            self.count = 0;
+        }
+    }
+}
+
+/// Errors that can occur when rewinding a Kalman filter.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryError {
+    /// The filter does not record its history.
+    Disabled,
+    /// The history contains fewer steps than requested.
+    InsufficientHistory {
+        /// Number of steps requested.
+        requested: usize,
+        /// Number of steps available.
+        available: usize,
+    },
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Disabled => write!(f, "history is disabled"),
+            HistoryError::InsufficientHistory { requested, available } => write!(
+                f,
+                "cannot rewind {} steps, history contains {}",
+                requested, available
+            ),
+        }
+    }
+}
+
+/// Bounded record of the past states of a Kalman filter.
+#[cfg(feature = "alloc")]
+#[allow(non_snake_case)]
+#[derive(Debug, Clone)]
+pub struct History<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    capacity: usize,
+    xs: VecDeque<VectorN<F, DimX>>,
+    Ps: VecDeque<MatrixMN<F, DimX, DimX>>,
+}
+
+#[cfg(feature = "alloc")]
+#[allow(non_snake_case)]
+impl<F, DimX> History<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    /// Returns an empty history that keeps at most `capacity` states.
+    pub fn new(capacity: usize) -> Self {
+        History {
+            capacity,
+            xs: VecDeque::with_capacity(capacity),
+            Ps: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of recorded states.
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    /// Returns whether no states are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    fn push(&mut self, x: VectorN<F, DimX>, P: MatrixMN<F, DimX, DimX>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.xs.len() == self.capacity {
+            self.xs.pop_front();
+            self.Ps.pop_front();
+        }
+        self.xs.push_back(x);
+        self.Ps.push_back(P);
+    }
+
+    /// Removes the last `n` states and writes the oldest of them to `x` and `P`.
+    fn rewind(
+        &mut self,
+        n: usize,
+        x: &mut VectorN<F, DimX>,
+        P: &mut MatrixMN<F, DimX, DimX>,
+    ) -> Result<(), HistoryError> {
+        let available = self.xs.len();
+        if n == 0 || n > available {
+            return Err(HistoryError::InsufficientHistory { requested: n, available });
+        }
+        self.xs.truncate(available - n + 1);
+        self.Ps.truncate(available - n + 1);
+        *x = self.xs.pop_back().unwrap();
+        *P = self.Ps.pop_back().unwrap();
+        Ok(())
+    }
+}
+
+/// Statistics of an update of a Kalman filter, passed to the callback registered with
+/// `KalmanFilter::set_on_update`.
+#[cfg(feature = "alloc")]
+#[allow(non_snake_case)]
+#[derive(Debug, Clone)]
+pub struct UpdateInfo<F, DimZ>
+    where
+        F: RealField,
+        DimZ: DimName,
+        DefaultAllocator: Allocator<F, DimZ> + Allocator<F, DimZ, DimZ>,
+{
+    /// Residual of the update.
+    pub y: VectorN<F, DimZ>,
+    /// System uncertainty of the update.
+    pub S: MatrixMN<F, DimZ, DimZ>,
+    /// Normalized innovation squared, see `KalmanFilter::nis`.
+    pub nis: F,
+    /// Log-likelihood of the measurement, see `KalmanFilter::log_likelihood`.
+    pub log_likelihood: F,
+}
+
+/// Callback registered with `KalmanFilter::set_on_update`.
+#[cfg(feature = "alloc")]
+#[allow(clippy::type_complexity)]
+struct OnUpdate<F, DimZ>(Box<dyn FnMut(&UpdateInfo<F, DimZ>) + Send>)
+    where
+        F: RealField,
+        DimZ: DimName,
+        DefaultAllocator: Allocator<F, DimZ> + Allocator<F, DimZ, DimZ>;
+
+#[cfg(feature = "alloc")]
+impl<F, DimZ> fmt::Debug for OnUpdate<F, DimZ>
+    where
+        F: RealField,
+        DimZ: DimName,
+        DefaultAllocator: Allocator<F, DimZ> + Allocator<F, DimZ, DimZ>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OnUpdate")
+    }
+}
+
+/// Posterior state estimates and covariances of all updates of a Kalman filter, see
+/// `KalmanFilter::enable_smoothing`.
+#[cfg(feature = "alloc")]
+#[allow(non_snake_case)]
+#[derive(Debug, Clone)]
+pub struct Trajectory<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    /// Posterior state estimates.
+    pub xs: Vec<VectorN<F, DimX>>,
+    /// Posterior state covariances.
+    pub Ps: Vec<MatrixMN<F, DimX, DimX>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<F, DimX> Default for Trajectory<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    fn default() -> Self {
+        Trajectory {
+            xs: Vec::new(),
+            Ps: Vec::new(),
+        }
+    }
+}
+
+/// Rolling window of the normalized innovation squared of the last updates.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct NisWindow<F: RealField> {
+    /// Maximum number of recorded values.
+    pub capacity: usize,
+    values: VecDeque<F>,
+    sum: F,
+}
+
+#[cfg(feature = "alloc")]
+impl<F: RealField> NisWindow<F> {
+    /// Returns an empty window holding at most `capacity` values.
+    pub fn new(capacity: usize) -> Self {
+        NisWindow {
+            capacity,
+            values: VecDeque::with_capacity(capacity),
+            sum: F::zero(),
+        }
+    }
+
+    /// Returns the number of recorded values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no value has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Records `nis`, dropping the oldest value if the window is full.
+    pub fn push(&mut self, nis: F) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+            // Recompute instead of subtracting, so rounding errors do not accumulate.
+            self.sum = self.values.iter().fold(F::zero(), |acc, v| acc + *v);
+        }
+        self.values.push_back(nis);
+        self.sum += nis;
+    }
+
+    /// Returns the average of the recorded values, or `None` if the window is empty.
+    pub fn mean(&self) -> Option<F> {
+        if self.values.is_empty() {
+            None
+        } else {
+            Some(self.sum / nalgebra::convert(self.values.len() as f64))
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> Default for KalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns a Kalman filter initialised with default parameters.
+    fn default() -> Self {
+        let x = VectorN::<F, DimX>::from_element(F::one());
+        let P = MatrixMN::<F, DimX, DimX>::identity();
+        let Q = MatrixMN::<F, DimX, DimX>::identity();
+        let F = MatrixMN::<F, DimX, DimX>::identity();
+        let H = MatrixMN::<F, DimZ, DimX>::from_element(F::zero());
+        let R = MatrixMN::<F, DimZ, DimZ>::identity();
+        let alpha_sq = F::one();
+
+        let z = None;
+
+        let K = MatrixMN::<F, DimX, DimZ>::from_element(F::zero());
+        let y = VectorN::<F, DimZ>::from_element(F::one());
+        let S = MatrixMN::<F, DimZ, DimZ>::from_element(F::zero());
+        let SI = MatrixMN::<F, DimZ, DimZ>::from_element(F::zero());
+
+        let x_prior = x.clone();
+        let P_prior = P.clone();
+
+        let x_post = x.clone();
+        let P_post = P.clone();
+
+        KalmanFilter {
+            x,
+            P,
+            x_prior,
+            P_prior,
+            x_post,
+            P_post,
+            z,
+            R,
+            Q,
+            B: None,
+            F,
+            H,
+            y,
+            K,
+            S,
+            SI,
+            alpha_sq,
+            adaptive_q: None,
+            convergence: None,
+            inv_strategy: InverseStrategy::Lu,
+            #[cfg(feature = "alloc")]
+            history: None,
+            #[cfg(feature = "alloc")]
+            nis_window: None,
+            #[cfg(feature = "alloc")]
+            trajectory: None,
+            #[cfg(feature = "alloc")]
+            on_update: None,
+        }
+    }
+}
+
+/// Returns a one-dimensional Kalman filter with the state `x = [mean]` and the state covariance
+/// `P = [[var]]` of the distribution, which measures the state directly, i.e., `H = [[1]]`. The
+/// other parameters are the defaults.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::KalmanFilter;
+/// use filter::stats::GaussianDistribution;
+/// use nalgebra::U1;
+///
+/// let mut kf: KalmanFilter<f64, U1, U1, U1> = GaussianDistribution::new(10.0, 4.0).into();
+/// kf.update_scalar(12.0, Some(4.0), None);
+///
+/// let posterior = GaussianDistribution::from(&kf);
+/// assert!((posterior.mean - 11.0).abs() < 1e-12);
+/// assert!((posterior.var - 2.0).abs() < 1e-12);
+/// ```
+#[allow(non_snake_case)]
+impl<F> From<GaussianDistribution<F>> for KalmanFilter<F, U1, U1, U1>
+    where
+        F: RealField + num_traits::Float,
+{
+    fn from(distribution: GaussianDistribution<F>) -> Self {
+        let x = Vector1::new(distribution.mean);
+        let P = MatrixMN::<F, U1, U1>::new(distribution.var);
+        KalmanFilter {
+            x_prior: x,
+            P_prior: P,
+            x_post: x,
+            P_post: P,
+            x,
+            P,
+            H: MatrixMN::<F, U1, U1>::identity(),
+            ..KalmanFilter::default()
+        }
+    }
+}
+
+/// Returns the distribution of the state of a one-dimensional Kalman filter, with the mean `x[0]`
+/// and the variance `P[(0, 0)]`.
+impl<F> From<&KalmanFilter<F, U1, U1, U1>> for GaussianDistribution<F>
+    where
+        F: RealField + num_traits::Float,
+{
+    fn from(kf: &KalmanFilter<F, U1, U1, U1>) -> Self {
+        GaussianDistribution::new(kf.x[0], kf.P[(0, 0)])
+    }
+}
+
+/// Errors returned by `KalmanFilter::check_consistency`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsistencyError {
+    /// The named matrix is not symmetric.
+    NotSymmetric(&'static str),
+    /// The named matrix is not positive semidefinite.
+    NotPositiveSemidefinite(&'static str),
+}
+
+impl fmt::Display for ConsistencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsistencyError::NotSymmetric(name) => write!(f, "{} is not symmetric", name),
+            ConsistencyError::NotPositiveSemidefinite(name) => {
+                write!(f, "{} is not positive semidefinite", name)
+            }
+        }
+    }
+}
+
+/// Errors returned by `KalmanFilter::steady_state_covariance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SteadyStateError {
+    /// The system uncertainty is singular.
+    Singular,
+    /// The Riccati equation did not converge.
+    NotConverged,
+}
+
+impl fmt::Display for SteadyStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SteadyStateError::Singular => write!(f, "system uncertainty is singular"),
+            SteadyStateError::NotConverged => write!(f, "Riccati equation did not converge"),
+        }
+    }
+}
+
+/// Checks that the covariance matrix `m` is symmetric and positive semidefinite, up to a
+/// tolerance relative to its largest element.
+fn check_covariance<F, D>(m: &MatrixMN<F, D, D>, name: &'static str) -> Result<(), ConsistencyError>
+    where
+        F: RealField,
+        D: DimName,
+        DefaultAllocator: Allocator<F, D, D>,
+{
+    let scale = m.amax().max(F::one());
+    let tolerance = F::default_epsilon().sqrt() * scale;
+
+    if !m.relative_eq(&m.transpose(), tolerance, F::default_epsilon().sqrt()) {
+        return Err(ConsistencyError::NotSymmetric(name));
+    }
+
+    // A positive semidefinite matrix becomes positive definite when its diagonal is increased
+    // slightly, while any negative eigenvalue larger than the tolerance remains.
+    let shifted = m + MatrixMN::<F, D, D>::identity() * tolerance;
+    if shifted.cholesky().is_none() {
+        return Err(ConsistencyError::NotPositiveSemidefinite(name));
+    }
+    Ok(())
+}
+
+/// Returns the measurement function `H` that measures the state variables with the given
+/// `observed` indices, where the `i`-th measurement is the state variable `observed[i]`.
+///
+/// # Panics
+/// Panics if the number of indices differs from `DimZ`, or if an index is out of bounds of
+/// `DimX`.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::measurement_selector;
+/// use nalgebra::{Matrix1x2, U1, U2};
+///
+/// // Observe only the position of a position-velocity state.
+/// let H = measurement_selector::<f64, U1, U2>(&[0]);
+/// assert_eq!(H, Matrix1x2::new(1.0, 0.0));
+/// ```
+#[allow(non_snake_case)]
+pub fn measurement_selector<F, DimZ, DimX>(observed: &[usize]) -> MatrixMN<F, DimZ, DimX>
+    where
+        F: RealField,
+        DimZ: DimName,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimZ, DimX>,
+{
+    assert_eq!(
+        observed.len(),
+        DimZ::dim(),
+        "number of observed indices must match the measurement dimension"
+    );
+    assert!(
+        observed.iter().all(|&i| i < DimX::dim()),
+        "observed index out of bounds of the state dimension"
+    );
+
+    let mut H = MatrixMN::<F, DimZ, DimX>::zeros();
+    for (row, &column) in observed.iter().enumerate() {
+        H[(row, column)] = F::one();
+    }
+    H
+}
+
+/// Returns the initial state `[position, velocity]` and covariance of a constant velocity filter
+/// from the first two position measurements `z0` and `z1`, taken `dt` apart with the noise
+/// variance `measurement_variance`.
+///
+/// The state is valid at the time of `z1`, so the filter continues with the third measurement.
+/// The velocity is the difference of the measurements divided by `dt`, and the covariance follows
+/// from the measurement noise, so no guess of the initial uncertainty is needed.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::{two_point_init, KalmanFilter};
+/// use nalgebra::U1;
+///
+/// let (x, P) = two_point_init(10.0_f64, 12.0, 0.5, 1.0);
+/// assert_eq!(x[1], 4.0);
+///
+/// let mut kf: KalmanFilter<f64, nalgebra::U2, U1, U1> = KalmanFilter::default();
+/// kf.x = x;
+/// kf.P = P;
+/// ```
+///
+/// # References
+/// * Bar-Shalom, Li, Kirubarajan, "Estimation with Applications to Tracking and Navigation",
+///   section 5.5.3. John Wiley and Sons, 2001.
+#[allow(non_snake_case)]
+pub fn two_point_init<F: RealField>(
+    z0: F,
+    z1: F,
+    dt: F,
+    measurement_variance: F,
+) -> (Vector2<F>, Matrix2<F>) {
+    let r = measurement_variance;
+    let x = Vector2::new(z1, (z1 - z0) / dt);
+    let P = Matrix2::new(
+        r, r / dt,
+        r / dt, (r + r) / (dt * dt),
+    );
+    (x, P)
+}
+
+/// Fuses the state estimates of the filters `a` and `b` of the same system, e.g., run on
+/// different nodes of a distributed tracking setup, and returns the fused state and covariance.
+///
+/// The estimates are combined in information form, `P = (Pa^-1 + Pb^-1)^-1` and
+/// `x = P (Pa^-1 xa + Pb^-1 xb)`, see `MultivariateGaussian`. This assumes that the estimation
+/// errors of the two filters are independent. If the filters share information, e.g., the same
+/// process noise, the same measurements or previously fused estimates, the fused covariance is
+/// too small; use `covariance_intersection` in that case.
+///
+/// # Panics
+/// Panics if `Pa + Pb` is singular.
+#[allow(clippy::type_complexity)]
+pub fn fuse<F, DimX, DimZ, DimU>(
+    a: &KalmanFilter<F, DimX, DimZ, DimU>,
+    b: &KalmanFilter<F, DimX, DimZ, DimU>,
+) -> (VectorN<F, DimX>, MatrixMN<F, DimX, DimX>)
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    let fused = MultivariateGaussian::new(a.x.clone(), a.P.clone())
+        * MultivariateGaussian::new(b.x.clone(), b.P.clone());
+    (fused.mean, fused.cov)
+}
+
+/// Fuses the estimates `(a_mean, a_cov)` and `(b_mean, b_cov)` of the same state with
+/// covariance intersection and returns the fused mean and covariance.
+///
+/// The fused estimate is `P^-1 = w Pa^-1 + (1 - w) Pb^-1` and
+/// `x = P (w Pa^-1 xa + (1 - w) Pb^-1 xb)`, where the weight `w` in `[0, 1]` minimizes the trace
+/// of `P`. Unlike `fuse`, the result is
+/// consistent for any correlation between the errors of the two estimates, at the cost of a
+/// larger covariance when they are in fact independent. Fusing an estimate with itself returns
+/// it unchanged.
+///
+/// # Panics
+/// Panics if `a_cov` or `b_cov` is singular.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::covariance_intersection;
+/// use nalgebra::{Matrix2, Vector2};
+///
+/// let (x, P) = covariance_intersection(
+///     &Vector2::new(1.0, 0.0),
+///     &Matrix2::new(1.0, 0.0, 0.0, 10.0),
+///     &Vector2::new(0.0, 1.0),
+///     &Matrix2::new(10.0, 0.0, 0.0, 1.0),
+/// );
+/// assert!(P.trace() < 11.0_f64);
+/// ```
+///
+/// # References
+/// * Julier and Uhlmann, "A non-divergent estimation algorithm in the presence of unknown
+///   correlations". Proceedings of the American Control Conference, 1997.
+#[allow(non_snake_case, clippy::type_complexity)]
+pub fn covariance_intersection<F, DimX>(
+    a_mean: &VectorN<F, DimX>,
+    a_cov: &MatrixMN<F, DimX, DimX>,
+    b_mean: &VectorN<F, DimX>,
+    b_cov: &MatrixMN<F, DimX, DimX>,
+) -> (VectorN<F, DimX>, MatrixMN<F, DimX, DimX>)
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    let AI = a_cov.clone().try_inverse().expect("a_cov must be invertible");
+    let BI = b_cov.clone().try_inverse().expect("b_cov must be invertible");
+
+    let fused_cov = |w: F| (&AI * w + &BI * (F::one() - w)).try_inverse();
+    let trace = |w: F| fused_cov(w).map_or(F::max_value(), |P| P.trace());
+
+    // Golden-section search, the trace is convex in w.
+    // (sqrt(5) - 1) / 2
+    let ratio: F = nalgebra::convert(0.618_033_988_749_894_9);
+    let (mut low, mut high) = (F::zero(), F::one());
+    let mut w1 = high - (high - low) * ratio;
+    let mut w2 = low + (high - low) * ratio;
+    let (mut t1, mut t2) = (trace(w1), trace(w2));
+    while high - low > F::default_epsilon().sqrt() {
+        if t1 < t2 {
+            high = w2;
+            w2 = w1;
+            t2 = t1;
+            w1 = high - (high - low) * ratio;
+            t1 = trace(w1);
+        } else {
+            low = w1;
+            w1 = w2;
+            t1 = t2;
+            w2 = low + (high - low) * ratio;
+            t2 = trace(w2);
+        }
+    }
+
+    // The optimum may be at the boundary, i.e., one estimate is better in every direction.
+    let half: F = nalgebra::convert(0.5);
+    let w = [F::zero(), F::one(), (low + high) * half]
+        .iter()
+        .fold((F::zero(), F::max_value()), |best, &w| {
+            let t = trace(w);
+            if t < best.1 { (w, t) } else { best }
+        })
+        .0;
+
+    let P = fused_cov(w).unwrap();
+    let x = &P * (&AI * a_mean * w + &BI * b_mean * (F::one() - w));
+    (x, P)
+}
+
+/// Returns a Kalman filter for the system of `kf` with colored, i.e., time-correlated,
+/// measurement noise, by augmenting the state with the noise.
+///
+/// The measurement noise `v` follows the auto-regressive model `v' = noise_F v + w`, where `w` is
+/// white noise with the covariance `noise_Q`, e.g., `noise_F = [[phi]]` for an AR(1) process. The
+/// state of the returned filter is `[x, v]`: the first `DimX` elements are the state of `kf`, the
+/// last `DimZ` elements the current measurement noise. Accordingly
+///
+/// * `F = [[kf.F, 0], [0, noise_F]]`,
+/// * `Q = [[kf.Q, 0], [0, noise_Q]]`,
+/// * `H = [kf.H, I]`,
+/// * `B = [kf.B, 0]`.
+///
+/// `x` and `P` start from those of `kf`, with zero noise and the stationary covariance of the
+/// noise process, and `R`, the fading memory and the inversion strategy are taken over from
+/// `kf`. `R` now only models the white part of the measurement noise, and may be zero if the
+/// noise is purely colored.
+///
+/// `noise_F` must be stable, i.e., all its eigenvalues must have magnitudes below one.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::{augment_colored_noise, KalmanFilter};
+/// use nalgebra::{Matrix1, Vector1, U1, U2};
+///
+/// let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+/// kf.H = Matrix1::new(1.0);
+/// kf.R = Matrix1::new(0.0);
+///
+/// let mut augmented = augment_colored_noise(&kf, &Matrix1::new(0.9), &Matrix1::new(0.19));
+/// augmented.predict(None, None, None, None);
+/// augmented.update(&Vector1::new(1.0), None, None);
+/// assert_eq!(2, augmented.x.len());
+/// ```
+#[allow(non_snake_case)]
+pub fn augment_colored_noise<F, DimX, DimZ, DimU>(
+    kf: &KalmanFilter<F, DimX, DimZ, DimU>,
+    noise_F: &MatrixMN<F, DimZ, DimZ>,
+    noise_Q: &MatrixMN<F, DimZ, DimZ>,
+) -> KalmanFilter<F, DimNameSum<DimX, DimZ>, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName + DimNameAdd<DimZ>,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>
+        + Allocator<F, DimNameSum<DimX, DimZ>>
+        + Allocator<F, DimNameSum<DimX, DimZ>, DimZ>
+        + Allocator<F, DimZ, DimNameSum<DimX, DimZ>>
+        + Allocator<F, DimNameSum<DimX, DimZ>, DimNameSum<DimX, DimZ>>
+        + Allocator<F, DimNameSum<DimX, DimZ>, DimU>,
+{
+    let n = DimX::dim();
+    let block_diagonal = |a: &MatrixMN<F, DimX, DimX>, b: &MatrixMN<F, DimZ, DimZ>| {
+        MatrixMN::<F, DimNameSum<DimX, DimZ>, DimNameSum<DimX, DimZ>>::from_fn(|r, c| {
+            match (r < n, c < n) {
+                (true, true) => a[(r, c)],
+                (false, false) => b[(r - n, c - n)],
+                _ => F::zero(),
+            }
+        })
+    };
+
+    // Stationary covariance of the noise, the solution of `V = noise_F V noise_F^T + noise_Q`,
+    // by doubling: after k steps, V sums the first 2^k terms of the series.
+    let mut V = noise_Q.clone();
+    let mut A = noise_F.clone();
+    for _ in 0..32 {
+        V = &V + &A * &V * A.transpose();
+        A = &A * &A;
+    }
+
+    let mut augmented = KalmanFilter::<F, DimNameSum<DimX, DimZ>, DimZ, DimU>::default();
+    augmented.x = VectorN::<F, DimNameSum<DimX, DimZ>>::from_fn(|r, _| {
+        if r < n { kf.x[r] } else { F::zero() }
+    });
+    augmented.P = block_diagonal(&kf.P, &V);
+    augmented.x_prior = augmented.x.clone();
+    augmented.P_prior = augmented.P.clone();
+    augmented.x_post = augmented.x.clone();
+    augmented.P_post = augmented.P.clone();
+    augmented.F = block_diagonal(&kf.F, noise_F);
+    augmented.Q = block_diagonal(&kf.Q, noise_Q);
+    augmented.H = MatrixMN::<F, DimZ, DimNameSum<DimX, DimZ>>::from_fn(|r, c| {
+        if c < n {
+            kf.H[(r, c)]
+        } else if c - n == r {
+            F::one()
+        } else {
+            F::zero()
+        }
+    });
+    augmented.B = kf.B.as_ref().map(|B| {
+        MatrixMN::<F, DimNameSum<DimX, DimZ>, DimU>::from_fn(|r, c| {
+            if r < n { B[(r, c)] } else { F::zero() }
+        })
+    });
+    augmented.R = kf.R.clone();
+    augmented.alpha_sq = kf.alpha_sq;
+    augmented.inv_strategy = kf.inv_strategy;
+    augmented
+}
+
+/// Errors that can occur when building a Kalman filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// The measurement function `H` was not set.
+    MissingMeasurementFunction,
+    /// A parameter contains NaN or infinite values.
+    NonFinite(&'static str),
+    /// The measurement noise `R` is singular, so the system uncertainty could not be inverted.
+    SingularMeasurementNoise,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingMeasurementFunction => write!(f, "measurement function is not set"),
+            BuildError::NonFinite(name) => write!(f, "{} contains non-finite values", name),
+            BuildError::SingularMeasurementNoise => write!(f, "measurement noise is singular"),
+        }
+    }
+}
+
+/// Builder for a `KalmanFilter`.
+///
+/// All parameters that are not set keep the values of `KalmanFilter::default`, except for the
+/// measurement function `H`, which is required.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::KalmanFilter;
+/// use nalgebra::{U1, U2, Vector1, Vector2, Matrix1, Matrix2};
+///
+/// let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::builder()
+///     .state(Vector2::new(2.0, 0.0))
+///     .state_transition(Matrix2::new(
+///         1.0, 1.0,
+///         0.0, 1.0,
+///     ))
+///     .measurement_function(Vector2::new(1.0, 0.0).transpose())
+///     .covariance(Matrix2::identity() * 1000.0)
+///     .measurement_noise(Matrix1::new(5.0))
+///     .process_noise(Matrix2::repeat(0.0001))
+///     .build()
+///     .unwrap();
+///
+/// kf.update(&Vector1::new(0.0), None, None);
+/// kf.predict(None, None, None, None);
+/// ```
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct KalmanFilterBuilder<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    kf: KalmanFilter<F, DimX, DimZ, DimU>,
+    has_measurement_function: bool,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> KalmanFilter<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns a builder for a Kalman filter.
+    pub fn builder() -> KalmanFilterBuilder<F, DimX, DimZ, DimU> {
+        KalmanFilterBuilder {
+            kf: KalmanFilter::default(),
+            has_measurement_function: false,
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> KalmanFilterBuilder<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Sets the initial state estimate `x`.
+    pub fn state(mut self, x: VectorN<F, DimX>) -> Self {
+        self.kf.x = x;
+        self
+    }
+
+    /// Sets the initial state covariance `P`.
+    pub fn covariance(mut self, P: MatrixMN<F, DimX, DimX>) -> Self {
+        self.kf.P = P;
+        self
+    }
+
+    /// Sets the state transition matrix `F`.
+    pub fn state_transition(mut self, F: MatrixMN<F, DimX, DimX>) -> Self {
+        self.kf.F = F;
+        self
+    }
+
+    /// Sets the measurement function `H`.
+    pub fn measurement_function(mut self, H: MatrixMN<F, DimZ, DimX>) -> Self {
+        self.kf.H = H;
+        self.has_measurement_function = true;
+        self
+    }
+
+    /// Sets the process noise matrix `Q`.
+    pub fn process_noise(mut self, Q: MatrixMN<F, DimX, DimX>) -> Self {
+        self.kf.Q = Q;
+        self
+    }
+
+    /// Sets the measurement noise matrix `R`.
+    pub fn measurement_noise(mut self, R: MatrixMN<F, DimZ, DimZ>) -> Self {
+        self.kf.R = R;
+        self
+    }
+
+    /// Sets the control transition matrix `B`.
+    pub fn control_transition(mut self, B: MatrixMN<F, DimX, DimU>) -> Self {
+        self.kf.B = Some(B);
+        self
+    }
+
+    /// Sets the fading memory factor, see `KalmanFilter::fading_memory`.
+    pub fn fading_memory(mut self, alpha: F) -> Self {
+        self.kf.alpha_sq = alpha * alpha;
+        self
+    }
+
+    /// Returns the Kalman filter, or an error if the measurement function is missing, a
+    /// parameter is not finite, or the measurement noise is singular.
+    pub fn build(self) -> Result<KalmanFilter<F, DimX, DimZ, DimU>, BuildError> {
+        if !self.has_measurement_function {
+            return Err(BuildError::MissingMeasurementFunction);
+        }
+
+        let mut kf = self.kf;
+        let finite = |values: &[F]| values.iter().all(|v| v.is_finite());
+        if !finite(kf.x.as_slice()) {
+            return Err(BuildError::NonFinite("x"));
+        }
+        if !finite(kf.P.as_slice()) {
+            return Err(BuildError::NonFinite("P"));
+        }
+        if !finite(kf.F.as_slice()) {
+            return Err(BuildError::NonFinite("F"));
+        }
+        if !finite(kf.H.as_slice()) {
+            return Err(BuildError::NonFinite("H"));
+        }
+        if !finite(kf.Q.as_slice()) {
+            return Err(BuildError::NonFinite("Q"));
+        }
+        if !finite(kf.R.as_slice()) {
+            return Err(BuildError::NonFinite("R"));
+        }
+        if kf.R.clone().try_inverse().is_none() {
+            return Err(BuildError::SingularMeasurementNoise);
+        }
+
+        kf.x_prior = kf.x.clone();
+        kf.P_prior = kf.P.clone();
+        kf.x_post = kf.x.clone();
+        kf.P_post = kf.P.clone();
+        Ok(kf)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+    use nalgebra::base::Vector1;
+    use nalgebra::{U1, U2, Vector2, Matrix2, Matrix1};
+
+    use super::*;
+
+    /// Returns the filter of the reference example that is checked against filterpy in
+    /// `test_1d_reference`, which tracks position and velocity from position measurements.
+    fn reference_filter() -> KalmanFilter<f64, U2, U1, U1> {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(2.0, 0.0);
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.P *= 1000.0;
+        kf.R = Matrix1::new(5.0);
+        kf.Q = Matrix2::repeat(0.0001);
+        kf
+    }
+
+    #[test]
+    fn test_univariate_kf_setup() {
+        let mut kf: KalmanFilter<f32, U1, U1, U1> = KalmanFilter::<f32, U1, U1, U1>::default();
+
+        for i in 0..1000 {
+            let zf = i as f32;
+            let z = Vector1::new(zf);
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            assert_approx_eq!(zf, kf.z.unwrap()[0]);
+        }
+    }
+
+    #[test]
+    fn test_log_likelihood() {
+        let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+        kf.x = Vector1::new(0.0);
+        kf.H = Matrix1::new(1.0);
+        kf.P = Matrix1::new(3.0);
+        kf.R = Matrix1::new(1.0);
+
+        kf.update(&Vector1::new(2.0), None, None);
+
+        // Density of N(0, 4) at 2.
+        let expected = -0.5 * (1.0 + (2.0 * core::f64::consts::PI * 4.0).ln());
+        assert_approx_eq!(kf.log_likelihood(), expected);
+        assert_approx_eq!(kf.likelihood(), expected.exp());
+    }
+
+    #[test]
+    fn test_update_correlated_without_correlation() {
+        let mut kf = reference_filter();
+
+        let mut kf_correlated: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf_correlated.x = kf.x;
+        kf_correlated.F = kf.F;
+        kf_correlated.H = kf.H;
+        kf_correlated.P = kf.P;
+        kf_correlated.R = kf.R;
+        kf_correlated.Q = kf.Q;
+
+        let uncorrelated = Vector2::zeros();
+        for t in 0..20 {
+            let z = Vector1::new(t as f64);
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            kf_correlated.predict(None, None, None, None);
+            kf_correlated.update_correlated(&z, None, None, &uncorrelated);
+
+            assert_approx_eq!(kf.x[0], kf_correlated.x[0], 1e-9);
+            assert_approx_eq!(kf.x[1], kf_correlated.x[1], 1e-9);
+            assert_approx_eq!(kf.P[(0, 0)], kf_correlated.P[(0, 0)], 1e-9);
+            assert_approx_eq!(kf.P[(1, 1)], kf_correlated.P[(1, 1)], 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_update_correlated_steadystate_and_adaptive_q() {
+        let setup = || {
+            let mut kf = reference_filter();
+            kf.x = Vector2::new(0.0, 1.0);
+            kf.adaptive_q = Some(AdaptiveQ::new(4.0, 10.0, 0.5));
+            kf.auto_steadystate(1e-6, 5);
+            kf
+        };
+        let mut kf = setup();
+        let mut kf_correlated = setup();
+
+        let uncorrelated = Vector2::zeros();
+        for t in 0..300 {
+            // An outlier early on triggers the adaptive process noise.
+            let z = Vector1::new(t as f64 + if t == 10 { 50.0 } else { 0.0 });
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            kf_correlated.predict(None, None, None, None);
+            kf_correlated.update_correlated(&z, None, None, &uncorrelated);
+
+            assert_eq!(kf.is_steady_state(), kf_correlated.is_steady_state());
+            assert_approx_eq!(
+                kf.adaptive_q.unwrap().factor,
+                kf_correlated.adaptive_q.unwrap().factor,
+                1e-9
+            );
+            assert_approx_eq!(kf.x[0], kf_correlated.x[0], 1e-6);
+        }
+
+        assert!(kf_correlated.is_steady_state());
+        let covariance = kf_correlated.P;
+        kf_correlated.update_correlated(&Vector1::new(300.0), None, None, &uncorrelated);
+        assert_eq!(kf_correlated.P, covariance);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_get_prediction_full() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(2.0, 1.0);
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.P *= 10.0;
+        kf.R = Matrix1::new(5.0);
+
+        let H = Vector2::new(0.0, 1.0).transpose();
+        let R = Matrix1::new(2.0);
+        let (x, P, S, K) = kf.get_prediction_full(None, Some(&H), Some(&R));
+
+        assert_eq!(Vector2::new(2.0, 1.0), kf.x);
+
+        kf.predict(None, None, None, None);
+        assert_eq!(kf.x, x);
+        assert_eq!(kf.P, P);
+
+        kf.update(&Vector1::new(1.5), Some(&R), Some(&H));
+        assert_approx_eq!(kf.S[0], S[0]);
+        assert_approx_eq!(kf.K[0], K[0]);
+        assert_approx_eq!(kf.K[1], K[1]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_rewind() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(2.0, 1.0);
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.P *= 10.0;
+
+        assert_eq!(Err(HistoryError::Disabled), kf.rewind(1));
+
+        kf.enable_history(2);
+        let x = kf.x;
+        let P = kf.P;
+
+        kf.predict(None, None, None, None);
+        let x_prior = kf.x;
+        let P_prior = kf.P;
+        kf.update(&Vector1::new(4.0), None, None);
+
+        kf.rewind(1).unwrap();
+        assert_eq!(x_prior, kf.x);
+        assert_eq!(P_prior, kf.P);
+
+        kf.rewind(1).unwrap();
+        assert_eq!(x, kf.x);
+        assert_eq!(P, kf.P);
+
+        assert_eq!(
+            Err(HistoryError::InsufficientHistory { requested: 1, available: 0 }),
+            kf.rewind(1)
+        );
+
+        // Only the last two steps are kept.
+        for t in 0..3 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(t as f64), None, None);
+        }
+        assert_eq!(2, kf.history().unwrap().len());
+
+        kf.disable_history();
+        assert!(kf.history().is_none());
+        assert_eq!(Err(HistoryError::Disabled), kf.rewind(1));
+    }
+
+    #[test]
+    fn test_adaptive_q() {
+        let setup = || {
+            let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+            kf.x = Vector2::new(0.0, 1.0);
+            kf.F = Matrix2::new(
+                1.0, 1.0,
+                0.0, 1.0,
+            );
+            kf.H = Vector2::new(1.0, 0.0).transpose();
+            kf.R = Matrix1::new(1.0);
+            kf.Q = Matrix2::new(
+                0.25, 0.5,
+                0.5, 1.0,
+            ) * 0.0001;
+            kf
+        };
+        let mut kf = setup();
+        let mut kf_adaptive = setup();
+        kf_adaptive.adaptive_q = Some(AdaptiveQ::new(4.0, 10.0, 0.5));
+
+        let mut position = 0.0;
+        let mut velocity = 1.0;
+        let mut error = 0.0;
+        let mut error_adaptive = 0.0;
+        for t in 0..60 {
+            // The target suddenly turns around.
+            if t == 30 {
+                velocity = -2.0;
+            }
+            position += velocity;
+            let z = Vector1::new(position + 0.5 * (t as f64 * 2.7).sin());
+
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            kf_adaptive.predict(None, None, None, None);
+            kf_adaptive.update(&z, None, None);
+
+            if t >= 30 {
+                error += (kf.x[0] - position).abs();
+                error_adaptive += (kf_adaptive.x[0] - position).abs();
+            }
+            if t == 29 {
+                assert_approx_eq!(kf_adaptive.adaptive_q.unwrap().factor, 1.0);
+            }
+        }
+
+        assert!(error_adaptive < 0.5 * error);
+    }
+
+    #[test]
+    fn test_fading_memory_tracks_step() {
+        let setup = |mut kf: KalmanFilter<f64, U2, U1, U1>| {
+            kf.x = Vector2::new(0.0, 1.0);
+            kf.F = Matrix2::new(
+                1.0, 1.0,
+                0.0, 1.0,
+            );
+            kf.H = Vector2::new(1.0, 0.0).transpose();
+            kf.R = Matrix1::new(1.0);
+            kf.Q = Matrix2::repeat(0.0001);
+            kf
+        };
+        let mut kf = setup(KalmanFilter::default());
+        let mut kf_fading = setup(KalmanFilter::fading_memory(1.1));
+
+        let mut error = 0.0;
+        let mut error_fading = 0.0;
+        for t in 0..60 {
+            // Ramp with a step of 10 at t = 30.
+            let position = t as f64 + if t >= 30 { 10.0 } else { 0.0 };
+            let z = Vector1::new(position + 0.5 * (t as f64 * 2.7).sin());
+
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            kf_fading.predict(None, None, None, None);
+            kf_fading.update(&z, None, None);
+
+            if (30..40).contains(&t) {
+                error += (kf.x[0] - position).abs();
+                error_fading += (kf_fading.x[0] - position).abs();
+            }
+        }
+
+        assert!(error_fading < 0.5 * error);
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let missing = KalmanFilter::<f64, U2, U1, U1>::builder().build();
+        assert_eq!(BuildError::MissingMeasurementFunction, missing.unwrap_err());
+
+        let non_finite = KalmanFilter::<f64, U2, U1, U1>::builder()
+            .measurement_function(Vector2::new(1.0, 0.0).transpose())
+            .process_noise(Matrix2::repeat(f64::NAN))
+            .build();
+        assert_eq!(BuildError::NonFinite("Q"), non_finite.unwrap_err());
+
+        let singular = KalmanFilter::<f64, U2, U1, U1>::builder()
+            .measurement_function(Vector2::new(1.0, 0.0).transpose())
+            .measurement_noise(Matrix1::new(0.0))
+            .build();
+        assert_eq!(BuildError::SingularMeasurementNoise, singular.unwrap_err());
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.P *= 1000.0;
+        kf.Q = Matrix2::new(
+            0.25, 0.5,
+            0.5, 1.0,
+        );
+        assert_eq!(Ok(()), kf.check_consistency());
+
+        kf.Q = Matrix2::new(
+            0.25, 0.5,
+            0.1, 1.0,
+        );
+        assert_eq!(Err(ConsistencyError::NotSymmetric("Q")), kf.check_consistency());
+
+        kf.Q = Matrix2::identity();
+        kf.R = Matrix1::new(-1.0);
+        assert_eq!(Err(ConsistencyError::NotPositiveSemidefinite("R")), kf.check_consistency());
+    }
+
+    #[test]
+    fn test_update_with_angle_residual() {
+        let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+        kf.x = Vector1::new((-179.0_f64).to_radians());
+        kf.H = Matrix1::new(1.0);
+
+        kf.update_with_residual(&Vector1::new(179.0_f64.to_radians()), None, None, |z, hx| {
+            Vector1::new(crate::stats::angle_residual(z[0], hx[0]))
+        });
+
+        assert_approx_eq!(kf.y[0], (-2.0_f64).to_radians());
+        assert_approx_eq!(kf.x[0], (-180.0_f64).to_radians());
+    }
+
+    #[test]
+    fn test_predict_dt() {
+        let transition = |dt: f64| Matrix2::new(
+            1.0, dt,
+            0.0, 1.0,
+        );
+        // Continuous white noise acceleration model, which is exact for any step size.
+        let noise = |dt: f64| Matrix2::new(
+            dt.powi(3) / 3.0, dt.powi(2) / 2.0,
+            dt.powi(2) / 2.0, dt,
+        ) * 0.1;
+
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(1.0, 2.0);
+        kf.P = Matrix2::new(
+            2.0, 0.5,
+            0.5, 1.0,
+        );
+        let mut kf_half: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf_half.x = kf.x;
+        kf_half.P = kf.P;
+
+        kf.predict_dt(1.0, transition, noise);
+        kf_half.predict_dt(0.5, transition, noise);
+        kf_half.predict_dt(0.5, transition, noise);
+
+        assert_eq!(transition(0.5), kf_half.F);
+        assert_eq!(noise(0.5), kf_half.Q);
+        assert_approx_eq!(kf.x[0], 3.0);
+        for i in 0..2 {
+            assert_approx_eq!(kf.x[i], kf_half.x[i]);
+            for j in 0..2 {
+                assert_approx_eq!(kf.P[(i, j)], kf_half.P[(i, j)]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse_strategies_agree() {
+        let setup = |inv_strategy| {
+            let mut kf = reference_filter();
+            kf.inv_strategy = inv_strategy;
+            kf
+        };
+        let mut kf_lu = setup(InverseStrategy::Lu);
+        let mut kf_cholesky = setup(InverseStrategy::Cholesky);
+
+        for t in 0..100 {
+            let z = Vector1::new(t as f64);
+            kf_lu.update(&z, None, None);
+            kf_lu.predict(None, None, None, None);
+            kf_cholesky.update(&z, None, None);
+            kf_cholesky.predict(None, None, None, None);
+
+            assert!((kf_lu.x_post - kf_cholesky.x_post).norm() < 1e-9);
+            assert!((kf_lu.P_post - kf_cholesky.P_post).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_predicted_measurement() {
+        let mut kf = reference_filter();
+
+        for t in 0..10 {
+            kf.predict(None, None, None, None);
+
+            let (z_hat, S) = kf.predicted_measurement();
+            let z = Vector1::new(t as f64);
+            assert_eq!(kf.residual_of(&z), z - z_hat);
+
+            kf.update(&z, None, None);
+            assert_approx_eq!(kf.S[0], S[0]);
+        }
+    }
+
+    #[test]
+    fn test_1d_reference() {
+        let mut kf = reference_filter();
+
+        for t in 0..100 {
+            let z = Vector1::new(t as f64);
+            kf.update(&z, None, None);
+            kf.predict(None, None, None, None);
+            // This matches the results from an equivalent filterpy filter.
+            assert_approx_eq!(kf.x[0],
+                              if t == 0 { 0.0099502487 } else { t as f64 + 1.0 },
+                              0.05);
+        }
+    }
+
+    #[test]
+    fn test_repair_covariance() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        let original = Matrix2::new(
+            4.0, 2.0,
+            2.0, 1.0 - 1e-6,
+        );
+        kf.P = original;
+        assert!(kf.P.cholesky().is_none());
+
+        assert!(kf.repair_covariance());
+        assert!(kf.P.cholesky().is_some());
+        assert!(kf.P.symmetric_eigenvalues().iter().all(|&e| e > 0.0));
+        assert_eq!(kf.P, kf.P.transpose());
+        assert!((kf.P - original).amax() < 1e-6);
+
+        let repaired = kf.P;
+        assert!(!kf.repair_covariance());
+        assert_eq!(repaired, kf.P);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_expected_information_gain() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.P = Matrix2::new(
+            4.0, 1.0,
+            1.0, 2.0,
+        );
+        let H = Vector2::new(1.0, 0.5).transpose();
+
+        let precise = kf.expected_information_gain(&H, &Matrix1::new(0.5));
+        let coarse = kf.expected_information_gain(&H, &Matrix1::new(5.0));
+        assert!(precise > coarse);
+        assert!(coarse > 0.0);
+
+        kf.R = Matrix1::new(0.5);
+        kf.H = H;
+        let (_, posterior) = kf.get_update(&Vector1::new(1.0));
+        assert_approx_eq!(0.5 * (kf.P.determinant() / posterior.determinant()).ln(), precise);
+        assert_eq!(Matrix2::new(4.0, 1.0, 1.0, 2.0), kf.P);
+    }
+
+    #[test]
+    fn test_accessors_before_and_after_update() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        assert!(kf.innovation().is_none());
+        assert!(kf.innovation_covariance().is_none());
+        assert!(kf.kalman_gain().is_none());
+
+        kf.predict(None, None, None, None);
+        assert!(kf.innovation().is_none());
+
+        kf.update(&Vector1::new(3.0), None, None);
+        assert_eq!(Some(&kf.y), kf.innovation());
+        assert_eq!(Some(&kf.S), kf.innovation_covariance());
+        assert_eq!(Some(&kf.K), kf.kalman_gain());
+    }
+
+    #[test]
+    fn test_gaussian_distribution_round_trip() {
+        let distribution = GaussianDistribution::new(3.5_f64, 0.25);
+        let kf: KalmanFilter<f64, U1, U1, U1> = distribution.into();
+        assert_eq!(Vector1::new(3.5), kf.x);
+        assert_eq!(Matrix1::new(0.25), kf.P);
+        assert_eq!(Matrix1::new(1.0), kf.H);
+
+        let round_trip = GaussianDistribution::from(&kf);
+        assert_eq!(distribution.mean, round_trip.mean);
+        assert_eq!(distribution.var, round_trip.var);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_forecast() {
+        let mut kf = reference_filter();
+
+        for t in 0..100 {
+            kf.update(&Vector1::new(t as f64), None, None);
+            kf.predict(None, None, None, None);
+        }
+        let (x, covariance) = (kf.x, kf.P);
+
+        let forecast = kf.forecast(10);
+        assert_eq!(10, forecast.len());
+        assert_eq!(x, kf.x);
+        assert_eq!(covariance, kf.P);
+
+        let mut last_variance = kf.P[(0, 0)];
+        for (i, (x, covariance)) in forecast.iter().enumerate() {
+            assert_approx_eq!(kf.x[0] + (i + 1) as f64 * kf.x[1], x[0], 1e-9);
+            assert_approx_eq!(kf.x[1], x[1], 1e-9);
+            assert!(covariance[(0, 0)] > last_variance);
+            last_variance = covariance[(0, 0)];
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_nis_averages_to_measurement_dimension() {
+        let mut normal = crate::test_util::standard_normal(42);
+
+        let mut kf: KalmanFilter<f64, U2, U2, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(0.0, 0.0);
+        kf.H = Matrix2::identity();
+        kf.R = Matrix2::identity() * 4.0;
+        kf.Q = Matrix2::identity() * 0.01;
+        kf.enable_nis_window(1000);
+        assert_eq!(kf.average_nis(), None);
+
+        let mut truth = Vector2::new(0.0, 0.0);
+        for _ in 0..2000 {
+            truth += Vector2::new(normal(), normal()) * 0.1;
+            kf.predict(None, None, None, None);
+            kf.update(&(truth + Vector2::new(normal(), normal()) * 2.0), None, None);
+        }
+
+        assert_eq!(kf.nis_window().unwrap().len(), 1000);
+        let average = kf.average_nis().unwrap();
+        assert!((average - 2.0).abs() < 0.2, "average NIS {}", average);
+
+        // Underestimating the measurement noise inflates the NIS.
+        kf.R = Matrix2::identity() * 0.25;
+        for _ in 0..1000 {
+            truth += Vector2::new(normal(), normal()) * 0.1;
+            kf.predict(None, None, None, None);
+            kf.update(&(truth + Vector2::new(normal(), normal()) * 2.0), None, None);
+        }
+        assert!(kf.average_nis().unwrap() > 4.0);
+
+        kf.disable_nis_window();
+        assert!(kf.nis_window().is_none());
+        assert_eq!(kf.average_nis(), None);
+    }
+
+    #[test]
+    fn test_condition_number() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        assert_approx_eq!(kf.condition_number(), 1.0);
+
+        for t in 0..20 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(t as f64), None, None);
+        }
+        assert!(kf.condition_number() < 100.0);
+
+        // Position in kilometres next to a velocity in millimetres per second.
+        kf.P = Matrix2::new(
+            1e6, 0.0,
+            0.0, 1e-6,
+        );
+        assert_approx_eq!(kf.condition_number(), 1e12, 1e3);
+
+        kf.P = Matrix2::new(
+            1.0, 1.0,
+            1.0, 1.0,
+        );
+        assert!(kf.condition_number() > 1e12);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_marginal() {
+        let mut kf = reference_filter();
+
+        for t in 0..10 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(t as f64), None, None);
+        }
+
+        let (x, P) = kf.marginal::<U1>(&[0]);
+        assert_eq!(x[0], kf.x[0]);
+        assert_eq!(P[(0, 0)], kf.P[(0, 0)]);
+
+        let (x, P) = kf.marginal::<U2>(&[1, 0]);
+        assert_eq!(x, Vector2::new(kf.x[1], kf.x[0]));
+        assert_eq!(P[(0, 0)], kf.P[(1, 1)]);
+        assert_eq!(P[(1, 1)], kf.P[(0, 0)]);
+        assert_eq!(P[(0, 1)], kf.P[(1, 0)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_marginal_dimension_mismatch() {
+        let kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.marginal::<U1>(&[0, 1]);
+    }
+
+    #[test]
+    fn test_filter_trait() {
+        fn track<'a, T: Filter>(filter: &'a mut T, zs: &[T::Measurement]) -> &'a T::State {
+            for z in zs {
+                filter.predict();
+                filter.update(z);
+            }
+            filter.state()
+        }
+
+        let mut kf = reference_filter();
+        let mut reference = reference_filter();
+        let zs: [Vector1<f64>; 20] = core::array::from_fn(|t| Vector1::new(t as f64));
+        for z in &zs {
+            reference.predict(None, None, None, None);
+            reference.update(z, None, None);
+        }
+
+        assert_eq!(*track(&mut kf, &zs), reference.x);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_innovations() {
+        let mut kf = reference_filter();
+
+        let data: Vec<_> = (0..50).map(|t| Vector1::new(t as f64 + 3.0)).collect();
+        let innovations = kf.innovations(&data);
+
+        assert_eq!(innovations.len(), data.len());
+        assert_eq!(innovations[49], kf.y);
+        assert_approx_eq!(innovations[0][0], 1.0, 0.01);
+        for window in innovations.windows(2).skip(1) {
+            assert!(window[1][0].abs() <= window[0][0].abs());
+        }
+        assert!(innovations[49][0].abs() < 1e-3);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_two_filter_smoother_matches_rts() {
+        let mut kf = reference_filter();
+        kf.Q = Matrix2::new(
+            0.25, 0.5,
+            0.5, 1.0,
+        ) * 0.01;
+
+        let zs: [Vector1<f64>; 30] =
+            core::array::from_fn(|t| Vector1::new(t as f64 + 2.0 * (t as f64 * 2.7).sin()));
+        let mut xs = [Vector2::zeros(); 30];
+        let mut Ps = [Matrix2::zeros(); 30];
+        for ((z, x), P) in zs.iter().zip(xs.iter_mut()).zip(Ps.iter_mut()) {
+            kf.predict(None, None, None, None);
+            kf.update(z, None, None);
+            *x = kf.x;
+            *P = kf.P;
+        }
+
+        let (mut xs_rts, mut Ps_rts) = (xs, Ps);
+        kf.rts_smoother(&mut xs_rts, &mut Ps_rts);
+        kf.two_filter_smoother(&zs, &mut xs, &mut Ps);
+
+        for k in 0..zs.len() {
+            assert!((xs[k] - xs_rts[k]).norm() < 1e-6, "x differs at step {}", k);
+            assert!((Ps[k] - Ps_rts[k]).norm() < 1e-6, "P differs at step {}", k);
+        }
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_two_point_init() {
+        let (r, dt): (f64, f64) = (4.0, 0.5);
+        let (x, P) = two_point_init(3.0, 5.0, dt, r);
+
+        assert_eq!(x, Vector2::new(5.0, 4.0));
+        assert_approx_eq!(P[(0, 0)], r);
+        assert_approx_eq!(P[(1, 1)], 2.0 * r / (dt * dt));
+        assert_approx_eq!(P[(0, 1)], r / dt);
+        assert_eq!(P, P.transpose());
+
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = x;
+        kf.P = P;
+        kf.R = Matrix1::new(r);
+        assert_eq!(kf.check_consistency(), Ok(()));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_transform_preserves_measurements() {
+        let mut kf = reference_filter();
+        let mut transformed = reference_filter();
+        for t in 0..5 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(t as f64), None, None);
+        }
+        for t in 0..5 {
+            transformed.predict(None, None, None, None);
+            transformed.update(&Vector1::new(t as f64), None, None);
+        }
+
+        // Position in millimetres and velocity in metres per minute.
+        let t = Matrix2::new(
+            1000.0, 0.0,
+            0.0, 60.0,
+        );
+        transformed.transform(&t);
+        assert!((transformed.x - t * kf.x).norm() < 1e-9);
+
+        for t in 5..20 {
+            let z = Vector1::new(t as f64 + (t as f64).sin());
+            kf.predict(None, None, None, None);
+            transformed.predict(None, None, None, None);
+
+            let (z_kf, S_kf) = kf.predicted_measurement();
+            let (z_transformed, S_transformed) = transformed.predicted_measurement();
+            assert_approx_eq!(z_kf[0], z_transformed[0], 1e-9);
+            assert_approx_eq!(S_kf[0], S_transformed[0], 1e-9);
+
+            kf.update(&z, None, None);
+            transformed.update(&z, None, None);
+            assert_approx_eq!(kf.y[0], transformed.y[0], 1e-9);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    #[allow(non_snake_case)]
+    fn test_transform_records() {
+        let setup = || {
+            let mut kf = reference_filter();
+            kf.enable_history(40);
+            kf.enable_smoothing();
+            kf
+        };
+
+        let t = Matrix2::new(
+            1000.0, 0.0,
+            0.0, 60.0,
+        );
+        let mut kf = setup();
+        let mut reference = setup();
+        reference.transform(&t);
+
+        for step in 0..20 {
+            if step == 10 {
+                kf.transform(&t);
+            }
+            let z = Vector1::new(step as f64 + (step as f64).sin());
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            reference.predict(None, None, None, None);
+            reference.update(&z, None, None);
+        }
+
+        let (xs, Ps) = kf.smooth();
+        let (xs_reference, Ps_reference) = reference.smooth();
+        assert_eq!(20, xs.len());
+        for i in 0..xs.len() {
+            assert!((xs[i] - xs_reference[i]).norm() < 1e-6 * xs_reference[i].norm());
+            assert!((Ps[i] - Ps_reference[i]).norm() < 1e-6 * Ps_reference[i].norm());
+        }
+
+        // Back to before the transformation.
+        kf.rewind(30).unwrap();
+        reference.rewind(30).unwrap();
+        assert!((kf.x - reference.x).norm() < 1e-6 * reference.x.norm());
+        assert!((kf.P - reference.P).norm() < 1e-6 * reference.P.norm());
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_fuse() {
+        let mut a: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        a.x = Vector2::new(1.0, 2.0);
+        a.P = Matrix2::new(
+            4.0, 1.0,
+            1.0, 2.0,
+        );
+        let mut b: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        b.x = a.x;
+        b.P = a.P;
+
+        let (x, P) = fuse(&a, &b);
+        assert!((x - a.x).norm() < 1e-12);
+        assert!((P - a.P * 0.5).norm() < 1e-12);
+
+        // Each filter only knows one component well, the fused estimate knows both.
+        a.P = Matrix2::new(
+            1.0, 0.0,
+            0.0, 100.0,
+        );
+        b.x = Vector2::new(3.0, 4.0);
+        b.P = Matrix2::new(
+            100.0, 0.0,
+            0.0, 1.0,
+        );
+        let (x, P) = fuse(&a, &b);
+        assert_approx_eq!(x[0], (100.0 * 1.0 + 3.0) / 101.0);
+        assert_approx_eq!(x[1], (2.0 + 100.0 * 4.0) / 101.0);
+        assert_approx_eq!(P[(0, 0)], 100.0 / 101.0);
+        assert_approx_eq!(P[(1, 1)], 100.0 / 101.0);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_covariance_intersection() {
+        let a_mean = Vector2::new(1.0, 2.0);
+        let a_cov = Matrix2::new(
+            3.0, 0.5,
+            0.5, 1.0,
+        );
+        let b_mean = Vector2::new(2.0, 1.0);
+        let b_cov = Matrix2::new(
+            1.0, -0.2,
+            -0.2, 4.0,
+        );
+
+        let (_, P) = covariance_intersection(&a_mean, &a_cov, &b_mean, &b_cov);
+        let independent = MultivariateGaussian::new(a_mean, a_cov)
+            * MultivariateGaussian::new(b_mean, b_cov);
+
+        // Conservative compared to the fusion of independent estimates, but never worse than
+        // the better input.
+        assert!(P.trace() >= independent.cov.trace());
+        assert!(P.trace() <= a_cov.trace().min(b_cov.trace()));
+        for &w in &[0.0, 0.2, 0.4, 0.6, 0.8, 1.0] {
+            let Pw = (a_cov.try_inverse().unwrap() * w + b_cov.try_inverse().unwrap() * (1.0 - w))
+                .try_inverse()
+                .unwrap();
+            assert!(P.trace() <= Pw.trace() + 1e-9);
+        }
+
+        // Fusing an estimate with itself gains no information.
+        let (x, P) = covariance_intersection(&a_mean, &a_cov, &a_mean, &a_cov);
+        assert!((x - a_mean).norm() < 1e-9);
+        assert!((P - a_cov).norm() < 1e-9);
+
+        // An estimate that is better in every direction is returned unchanged.
+        let (x, P) = covariance_intersection(&a_mean, &a_cov, &b_mean, &(a_cov * 10.0));
+        assert!((x - a_mean).norm() < 1e-9);
+        assert!((P - a_cov).norm() < 1e-9);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_update_multiple_matches_sequential() {
+        let setup = || {
+            let mut kf: KalmanFilter<f64, U2, U2, U1> = KalmanFilter::default();
+            kf.x = Vector2::new(2.0, 0.0);
+            kf.F = Matrix2::new(
+                1.0, 1.0,
+                0.0, 1.0,
+            );
+            kf.P *= 100.0;
+            kf.Q *= 0.01;
+            kf
+        };
+
+        // A position sensor, a velocity sensor and a sensor measuring both.
+        let measurements = [
+            (Vector2::new(1.0, 0.0), Matrix2::new(2.0, 0.0, 0.0, 1.0), Matrix2::new(1.0, 0.0, 0.0, 0.0)),
+            (Vector2::new(0.0, 0.8), Matrix2::new(1.0, 0.0, 0.0, 0.5), Matrix2::new(0.0, 0.0, 0.0, 1.0)),
+            (Vector2::new(1.5, 1.1), Matrix2::new(3.0, 0.5, 0.5, 2.0), Matrix2::identity()),
+        ];
+
+        let mut sequential = setup();
+        let mut stacked = setup();
+        for t in 0..5 {
+            let shifted = measurements.map(|(z, R, H)| (z + H * Vector2::new(t as f64, 0.0), R, H));
+
+            sequential.predict(None, None, None, None);
+            for (z, R, H) in &shifted {
+                sequential.update(z, Some(R), Some(H));
+            }
+
+            stacked.predict(None, None, None, None);
+            stacked.update_multiple(&shifted);
+
+            assert!((stacked.x - sequential.x).norm() < 1e-9);
+            assert!((stacked.P - sequential.P).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_update_multiple_bookkeeping() {
+        let mut kf: KalmanFilter<f64, U2, U2, U1> = KalmanFilter::default();
+        kf.x = Vector2::zeros();
+        kf.P = Matrix2::zeros();
+        kf.enable_history(10);
+        kf.enable_smoothing();
+        kf.enable_nis_window(10);
+
+        let measurements = [
+            (Vector2::new(1.0, 0.0), Matrix2::identity(), Matrix2::identity()),
+            (Vector2::new(2.0, 0.5), Matrix2::identity() * 2.0, Matrix2::identity()),
+        ];
+        kf.update_multiple(&measurements);
+
+        assert_eq!(kf.x, Vector2::zeros());
+        assert_eq!(kf.innovation(), Some(&Vector2::new(2.0, 0.5)));
+        assert_eq!(kf.innovation_covariance(), Some(&(Matrix2::identity() * 2.0)));
+        assert_eq!(kf.kalman_gain(), Some(&Matrix2::zeros()));
+        assert_eq!(1, kf.history().unwrap().len());
+        assert_eq!(1, kf.trajectory().unwrap().xs.len());
+        assert_eq!(Some(2.125), kf.average_nis());
+    }
+
+    #[test]
+    fn test_update_multiple_steadystate() {
+        let mut reference = reference_filter();
+        let mut kf = reference_filter();
+        kf.auto_steadystate(1e-6, 5);
+
+        let mut switched = false;
+        for t in 0..300 {
+            let noise = if t % 2 == 0 { 0.5 } else { -0.5 };
+            // A position sensor and a velocity sensor.
+            let measurements = [
+                (Vector1::new(t as f64 + noise), Matrix1::new(5.0), Vector2::new(1.0, 0.0).transpose()),
+                (Vector1::new(1.0 - 0.2 * noise), Matrix1::new(1.0), Vector2::new(0.0, 1.0).transpose()),
+            ];
+            reference.update_multiple(&measurements);
+            reference.predict(None, None, None, None);
+            kf.update_multiple(&measurements);
+            kf.predict(None, None, None, None);
+
+            switched |= kf.is_steady_state();
+            assert_approx_eq!(kf.x[0], reference.x[0], 1e-3);
+            assert_approx_eq!(kf.x[1], reference.x[1], 1e-3);
+        }
+        assert!(switched);
+
+        // Once switched, the covariance is no longer propagated.
+        let covariance = kf.P;
+        kf.update_multiple(&[(Vector1::new(300.0), Matrix1::new(5.0), Vector2::new(1.0, 0.0).transpose())]);
+        assert_eq!(kf.P, covariance);
+    }
+
+    #[test]
+    fn test_auto_steadystate() {
+        let mut reference = reference_filter();
+        let mut kf = reference_filter();
+        kf.auto_steadystate(1e-6, 5);
+        assert!(!kf.converged());
+
+        let mut switched_at = None;
+        for t in 0..300 {
+            let z = Vector1::new(t as f64 + if t % 2 == 0 { 0.5 } else { -0.5 });
+            reference.update(&z, None, None);
+            reference.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            kf.predict(None, None, None, None);
+
+            if kf.is_steady_state() && switched_at.is_none() {
+                switched_at = Some(t);
+            }
+            assert_approx_eq!(kf.x[0], reference.x[0], 1e-3);
+            assert_approx_eq!(kf.x[1], reference.x[1], 1e-3);
+        }
+
+        let switched_at = switched_at.expect("filter did not switch to steady state");
+        assert!(switched_at > 5);
+        assert!(kf.converged());
+
+        // Once switched, the covariance is no longer propagated.
+        let covariance = kf.P;
+        kf.update(&Vector1::new(300.0), None, None);
+        kf.predict(None, None, None, None);
+        assert_eq!(kf.P, covariance);
+    }
+
+    #[test]
+    fn test_isotropic_noise() {
+        let mut kf: KalmanFilter<f64, U2, U2, U1> = KalmanFilter::default();
+        kf.R = Matrix2::new(
+            3.0, 1.0,
+            1.0, 3.0,
+        );
+
+        kf.set_isotropic_measurement_noise(4.0);
+        kf.set_isotropic_process_noise(0.5);
+
+        assert_eq!(kf.R, Matrix2::new(
+            4.0, 0.0,
+            0.0, 4.0,
+        ));
+        assert_eq!(kf.Q, Matrix2::new(
+            0.5, 0.0,
+            0.0, 0.5,
+        ));
+    }
+
+    #[test]
+    fn test_steady_state_error() {
+        let mut kf = reference_filter();
+
+        let expected = kf.steady_state_covariance().unwrap();
+        let error = kf.steady_state_error().unwrap();
+
+        for t in 0..2000 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(t as f64), None, None);
+        }
+
+        assert!((kf.P - expected).amax() < 1e-9);
+        assert_approx_eq!((kf.P.trace() / 2.0).sqrt(), error, 1e-9);
+
+        // An unobserved random walk never converges.
+        kf.H = Vector2::new(0.0, 1.0).transpose();
+        kf.F = Matrix2::identity();
+        kf.Q = Matrix2::identity();
+        assert_eq!(Err(SteadyStateError::NotConverged), kf.steady_state_error());
+    }
+
+    #[test]
+    fn test_update_robust_rejects_outlier() {
+        let setup = || {
+            let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+            kf.F = Matrix2::new(
+                1.0, 1.0,
+                0.0, 1.0,
+            );
+            kf.H = Vector2::new(1.0, 0.0).transpose();
+            kf.P *= 100.0;
+            kf.R = Matrix1::new(1.0);
+            kf.Q = Matrix2::identity() * 0.001;
+            kf
+        };
+
+        let mut standard = setup();
+        let mut robust = setup();
+        for t in 0..50 {
+            let z = Vector1::new(t as f64 + if t % 2 == 0 { 0.5 } else { -0.5 });
+            standard.predict(None, None, None, None);
+            standard.update(&z, None, None);
+            robust.predict(None, None, None, None);
+            robust.update_robust(&z, 4.0);
+        }
+        // Measurements consistent with the prediction are fused as usual.
+        assert!((standard.x - robust.x).norm() < 0.1);
+
+        standard.predict(None, None, None, None);
+        robust.predict(None, None, None, None);
+        let prediction = robust.x[0];
+        let outlier = Vector1::new(1000.0);
+        standard.update(&outlier, None, None);
+        robust.update_robust(&outlier, 4.0);
+
+        assert!((standard.x[0] - prediction).abs() > 50.0);
+        assert!((robust.x[0] - prediction).abs() < 1.0);
+        assert_eq!(1.0, robust.R[0]);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_measurement_selector() {
+        let H = measurement_selector::<f64, U2, nalgebra::U4>(&[2, 0]);
+        assert_eq!(H, nalgebra::Matrix2x4::new(
+            0.0, 0.0, 1.0, 0.0,
+            1.0, 0.0, 0.0, 0.0,
+        ));
+
+        let x = nalgebra::Vector4::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(H * x, Vector2::new(3.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_measurement_selector_out_of_bounds() {
+        measurement_selector::<f64, U1, U2>(&[2]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    #[allow(non_snake_case)]
+    fn test_smooth_matches_rts_smoother() {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.P *= 100.0;
+        kf.R = Matrix1::new(4.0);
+        kf.Q = Matrix2::identity() * 0.01;
+
+        assert!(kf.smooth().0.is_empty());
+        kf.enable_smoothing();
+
+        let mut xs = Vec::new();
+        let mut Ps = Vec::new();
+        for t in 0..30 {
+            let z = Vector1::new(t as f64 + 3.0 * (t as f64).sin());
+            kf.predict(None, None, None, None);
+            kf.update(&z, None, None);
+            xs.push(kf.x);
+            Ps.push(kf.P);
+        }
+        kf.rts_smoother(&mut xs, &mut Ps);
+
+        let (smoothed_xs, smoothed_Ps) = kf.smooth();
+        assert_eq!(xs, smoothed_xs);
+        assert_eq!(Ps, smoothed_Ps);
+        assert_eq!(30, kf.trajectory().unwrap().xs.len());
+
+        kf.disable_smoothing();
+        assert!(kf.trajectory().is_none());
+        assert!(kf.smooth().0.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_sample_moments() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(3.0, -1.0);
+        kf.P = Matrix2::new(
+            4.0, 1.2,
+            1.2, 1.0,
+        );
+
+        let mut rng = SmallRng::seed_from_u64(17);
+        let n = 20_000;
+        let samples = kf.sample(&mut rng, n);
+        assert_eq!(n, samples.len());
+
+        let mean = samples.iter().fold(Vector2::zeros(), |sum, x| sum + x) / n as f64;
+        let covariance = samples
+            .iter()
+            .map(|x| (x - mean) * (x - mean).transpose())
+            .fold(Matrix2::zeros(), |sum, c| sum + c)
+            / (n - 1) as f64;
+
+        assert!((mean - kf.x).amax() < 0.05);
+        assert!((covariance - kf.P).amax() < 0.1);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_on_update() {
+        use std::sync::{Arc, Mutex};
+
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.F = Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        );
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.R = Matrix1::new(2.0);
+
+        let infos = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&infos);
+        kf.set_on_update(move |info| recorded.lock().unwrap().push(info.clone()));
+
+        for t in 0..5 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(t as f64 * 1.5), None, None);
+
+            let infos = infos.lock().unwrap();
+            assert_eq!(t + 1, infos.len());
+            let info = infos.last().unwrap();
+            assert_eq!(kf.y, info.y);
+            assert_eq!(kf.S, info.S);
+            assert_approx_eq!(kf.nis(), info.nis);
+            assert_approx_eq!(kf.log_likelihood(), info.log_likelihood);
+        }
+
+        // Predictions do not trigger the callback.
+        kf.predict(None, None, None, None);
+        assert_eq!(5, infos.lock().unwrap().len());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_on_update_in_steady_state() {
+        use std::sync::{Arc, Mutex};
+
+        let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+        kf.H = Matrix1::new(1.0);
+        kf.Q = Matrix1::new(0.01);
+        kf.auto_steadystate(1e-9, 3);
+        kf.enable_nis_window(100);
+
+        let count = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&count);
+        kf.set_on_update(move |_| *counter.lock().unwrap() += 1);
+
+        let mut t = 0;
+        while !kf.is_steady_state() {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(1.0), None, None);
+            t += 1;
+            assert!(t < 1000, "filter did not switch to steady state");
+        }
+
+        for _ in 0..5 {
+            kf.predict(None, None, None, None);
+            kf.update(&Vector1::new(2.0), None, None);
+        }
+
+        assert_eq!(t + 5, *count.lock().unwrap());
+        assert_eq!(t + 5, kf.nis_window().unwrap().len());
+        assert!(kf.nis() > 0.0);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_batch_log_likelihood_recovers_measurement_noise() {
+        let mut normal = crate::test_util::standard_normal(3);
+        let mut truth = Vector2::new(0.0, 1.0);
+        let data: Vec<_> = (0..2000)
+            .map(|_| {
+                truth = Vector2::new(truth[0] + truth[1], truth[1] + 0.1 * normal());
+                Vector1::new(truth[0] + 2.0 * normal())
+            })
+            .collect();
+
+        let log_likelihood = |r: f64| {
+            let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+            kf.x = Vector2::new(0.0, 1.0);
+            kf.F = Matrix2::new(
+                1.0, 1.0,
+                0.0, 1.0,
+            );
+            kf.H = Vector2::new(1.0, 0.0).transpose();
+            kf.Q = Matrix2::new(
+                0.0, 0.0,
+                0.0, 0.01,
+            );
+            kf.R = Matrix1::new(r);
+            kf.batch_log_likelihood(&data)
+        };
+
+        let grid = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 16.0];
+        let best = grid
+            .iter()
+            .copied()
+            .max_by(|&a, &b| log_likelihood(a).partial_cmp(&log_likelihood(b)).unwrap())
+            .unwrap();
+        assert_eq!(4.0, best);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_augment_colored_noise() {
+        // A random walk measured with AR(1) noise of unit variance.
+        let phi: f64 = 0.95;
+        let mut normal = crate::test_util::standard_normal(5);
+        let (mut truth, mut noise) = (0.0, 0.0);
+        let samples: Vec<_> = (0..2000)
+            .map(|_| {
+                truth += 0.1 * normal();
+                noise = phi * noise + (1.0 - phi * phi).sqrt() * normal();
+                (truth, Vector1::new(truth + noise))
+            })
+            .collect();
+
+        let random_walk = |r: f64| {
+            let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+            kf.x = Vector1::new(0.0);
+            kf.H = Matrix1::new(1.0);
+            kf.Q = Matrix1::new(0.01);
+            kf.R = Matrix1::new(r);
+            kf
+        };
+
+        // Ignoring the correlation, the noise is white with the same variance.
+        let mut white = random_walk(1.0);
+        let mut colored = augment_colored_noise(
+            &random_walk(0.0),
+            &Matrix1::new(phi),
+            &Matrix1::new(1.0 - phi * phi),
+        );
+        assert_approx_eq!(1.0, colored.P[(1, 1)], 1e-9);
+        assert_eq!(Vector2::new(1.0, 1.0).transpose(), colored.H);
+
+        let (mut white_error, mut colored_error) = (0.0, 0.0);
+        for (truth, z) in &samples {
+            white.predict(None, None, None, None);
+            white.update(z, None, None);
+            colored.predict(None, None, None, None);
+            colored.update(z, None, None);
+            white_error += (white.x[0] - truth).powi(2);
+            colored_error += (colored.x[0] - truth).powi(2);
+        }
+        assert!(colored_error < 0.8 * white_error);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_em_estimate_noise() {
+        // Local level model: a random walk with variance 0.5, measured with variance 2.
+        let mut normal = crate::test_util::standard_normal(11);
+        let mut truth = 0.0;
+        let data: Vec<_> = (0..3000)
+            .map(|_| {
+                truth += 0.5_f64.sqrt() * normal();
+                Vector1::new(truth + 2.0_f64.sqrt() * normal())
+            })
+            .collect();
+
+        let mut kf: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+        kf.H = Matrix1::new(1.0);
+        kf.P = Matrix1::new(10.0);
+        kf.Q = Matrix1::new(0.1);
+        kf.R = Matrix1::new(5.0);
+
+        let initial_log_likelihood = {
+            let mut copy: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+            copy.H = kf.H;
+            copy.P = kf.P;
+            copy.Q = kf.Q;
+            copy.R = kf.R;
+            copy.batch_log_likelihood(&data)
+        };
+
+        kf.em_estimate_noise(&data, 100);
+
+        assert_eq!(Matrix1::new(10.0), kf.P);
+        assert_approx_eq!(0.5, kf.Q[0], 0.1);
+        assert_approx_eq!(2.0, kf.R[0], 0.3);
+        assert!(kf.batch_log_likelihood(&data) > initial_log_likelihood);
+    }
 }
@@ -0,0 +1,233 @@
+/*!
+This module implements a Multiple Model Adaptive Estimation (MMAE) filter bank.
+*/
+
+use alloc::vec::Vec;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+use crate::kalman::kalman_filter::KalmanFilter;
+use crate::kalman::outer;
+use crate::stats::bayes_update_log;
+
+/// Implements a Multiple Model Adaptive Estimation (MMAE) filter bank.
+///
+/// Each filter of the bank implements a fixed model of the system. All filters are updated with
+/// every measurement and the probability of each model is updated with the likelihood of the
+/// measurement under that model. The combined estimate is the probability weighted average of
+/// the filter estimates. Unlike the `IMMEstimator`, the models do not interact, so the bank is
+/// suited to identifying which of a set of fixed models describes the system, not to tracking
+/// a system that switches between models.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::mmae::MMAEFilterBank;
+/// use filter::kalman::kalman_filter::KalmanFilter;
+/// use nalgebra::{U1, Vector1, Matrix1};
+///
+/// let mut drifting: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+/// drifting.x = Vector1::new(0.0);
+/// drifting.H = Matrix1::new(1.0);
+/// drifting.Q = Matrix1::new(1.0);
+///
+/// let mut constant: KalmanFilter<f64, U1, U1, U1> = KalmanFilter::default();
+/// constant.x = Vector1::new(0.0);
+/// constant.H = Matrix1::new(1.0);
+/// constant.Q = Matrix1::new(0.0);
+///
+/// let mut bank = MMAEFilterBank::new(vec![drifting, constant], vec![0.5, 0.5]);
+///
+/// for t in 0..10 {
+///     bank.predict(None);
+///     bank.update(&Vector1::new(t as f64));
+/// }
+///
+/// assert!(bank.p[0] > bank.p[1]);
+/// ```
+///
+/// # References
+/// * Roger Labbe. "Kalman and Bayesian Filters in Python", chapter 14.
+///   https://github.com/rlabbe/Kalman-and-Bayesian-Filters-in-Python
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct MMAEFilterBank<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Bank of filters, one per model.
+    pub filters: Vec<KalmanFilter<F, DimX, DimZ, DimU>>,
+    /// Normalized probability of each model being the correct one.
+    pub p: Vec<F>,
+    /// Combined state estimate.
+    pub x: VectorN<F, DimX>,
+    /// Combined state covariance matrix.
+    pub P: MatrixMN<F, DimX, DimX>,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU> MMAEFilterBank<F, DimX, DimZ, DimU>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns a filter bank for the given `filters` and initial model probabilities `p`.
+    ///
+    /// # Panics
+    /// Panics if `filters` is empty or `p` does not have one entry per filter.
+    pub fn new(filters: Vec<KalmanFilter<F, DimX, DimZ, DimU>>, p: Vec<F>) -> Self {
+        assert!(!filters.is_empty(), "filter bank must not be empty");
+        assert_eq!(filters.len(), p.len(), "p must have one entry per filter");
+
+        let mut bank = MMAEFilterBank {
+            filters,
+            p,
+            x: VectorN::<F, DimX>::zeros(),
+            P: MatrixMN::<F, DimX, DimX>::zeros(),
+        };
+        bank.compute_state_estimate();
+        bank
+    }
+
+    /// Predict next state (prior) of all filters in the bank.
+    pub fn predict(&mut self, u: Option<&VectorN<F, DimU>>) {
+        for kf in self.filters.iter_mut() {
+            kf.predict(u, None, None, None);
+        }
+        self.compute_state_estimate();
+    }
+
+    /// Add a new measurement (z) to all filters and update the model probabilities.
+    ///
+    /// The probabilities are updated with the log-likelihoods of the measurement, so that an
+    /// outlier that is practically impossible under every model does not zero them out. If the
+    /// measurement has zero probability under every model, the probabilities are kept.
+    pub fn update(&mut self, z: &VectorN<F, DimZ>) {
+        let log_likelihoods: Vec<F> = self
+            .filters
+            .iter_mut()
+            .map(|kf| {
+                kf.update(z, None, None);
+                kf.log_likelihood()
+            })
+            .collect();
+        bayes_update_log(&mut self.p, &log_likelihoods);
+        self.compute_state_estimate();
+    }
+
+    fn compute_state_estimate(&mut self) {
+        self.x = VectorN::<F, DimX>::zeros();
+        for (kf, p) in self.filters.iter().zip(&self.p) {
+            self.x += &kf.x * *p;
+        }
+
+        self.P = MatrixMN::<F, DimX, DimX>::zeros();
+        for (kf, p) in self.filters.iter().zip(&self.p) {
+            let y = &kf.x - &self.x;
+            self.P += (outer(&y) + &kf.P) * *p;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use nalgebra::{Matrix1, Matrix2, Vector1, Vector2, U1, U2};
+
+    use super::*;
+
+    fn filter(transition: Matrix2<f64>) -> KalmanFilter<f64, U2, U1, U1> {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = Vector2::new(0.0, 0.0);
+        kf.F = transition;
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.R = Matrix1::new(1.0);
+        kf.Q *= 0.001;
+        kf.P *= 10.0;
+        kf
+    }
+
+    #[test]
+    fn test_correct_model_dominates() {
+        let stationary = filter(Matrix2::identity());
+        let correct = filter(Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        ));
+        let wrong_dt = filter(Matrix2::new(
+            1.0, 3.0,
+            0.0, 1.0,
+        ));
+
+        let mut bank = MMAEFilterBank::new(
+            vec![stationary, correct, wrong_dt],
+            vec![1.0 / 3.0; 3],
+        );
+
+        for t in 0..50 {
+            let z = 0.5 * t as f64 + 0.3 * (t as f64 * 2.7).sin();
+            bank.predict(None);
+            bank.update(&Vector1::new(z));
+        }
+
+        assert!(bank.p[1] > 0.99);
+        assert!((bank.p.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((bank.x[0] - 0.5 * 49.0).abs() < 1.0);
+        assert!((bank.x[1] - 0.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_outlier_keeps_probabilities() {
+        let stationary = filter(Matrix2::identity());
+        let moving = filter(Matrix2::new(
+            1.0, 1.0,
+            0.0, 1.0,
+        ));
+        let mut bank = MMAEFilterBank::new(vec![stationary, moving], vec![0.5, 0.5]);
+
+        for t in 0..20 {
+            bank.predict(None);
+            bank.update(&Vector1::new(t as f64));
+        }
+        let p_before = bank.p[1];
+        assert!(p_before > 0.5);
+
+        bank.predict(None);
+        bank.update(&Vector1::new(1e6));
+
+        assert!(bank.p.iter().all(|p| p.is_finite()));
+        assert!((bank.p.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(bank.x[0] > 20.0);
+        assert!(bank.P[(0, 0)] > 0.0);
+
+        for t in 21..40 {
+            bank.predict(None);
+            bank.update(&Vector1::new(t as f64));
+        }
+        assert!(bank.p[1] > 0.5);
+        assert!(bank.p.iter().all(|p| p.is_finite()));
+    }
+}
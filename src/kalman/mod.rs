@@ -1,4 +1,31 @@
 /*!
 This module contains implementations of different version of the Kalman filter.
 */
+#[cfg(feature = "alloc")]
+use nalgebra::{allocator::Allocator, DefaultAllocator, DimName, MatrixMN, RealField, VectorN};
+
 pub mod kalman_filter;
+pub mod h_infinity;
+pub mod extended;
+pub mod const_generic;
+#[cfg(feature = "alloc")]
+pub mod imm;
+#[cfg(feature = "alloc")]
+pub mod mmae;
+#[cfg(feature = "alloc")]
+pub mod fixed_lag_smoother;
+#[cfg(feature = "alloc")]
+pub mod multi_rate;
+#[cfg(feature = "alloc")]
+pub mod unscented;
+
+/// Returns the outer product `y y^T`.
+#[cfg(feature = "alloc")]
+pub(crate) fn outer<F, DimX>(y: &VectorN<F, DimX>) -> MatrixMN<F, DimX, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    MatrixMN::<F, DimX, DimX>::from_fn(|r, c| y[r] * y[c])
+}
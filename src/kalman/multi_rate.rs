@@ -0,0 +1,247 @@
+/*!
+This module implements a scheduler for Kalman filters with sensors running at different rates.
+*/
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+use crate::kalman::kalman_filter::KalmanFilter;
+
+/// Event scheduled on a `MultiRateFilter`.
+#[derive(Debug)]
+enum Event<F, DimZ>
+    where
+        F: RealField,
+        DimZ: DimName,
+        DefaultAllocator: Allocator<F, DimZ>,
+{
+    Predict(F),
+    Update(F, VectorN<F, DimZ>),
+}
+
+impl<F, DimZ> Event<F, DimZ>
+    where
+        F: RealField,
+        DimZ: DimName,
+        DefaultAllocator: Allocator<F, DimZ>,
+{
+    fn time(&self) -> F {
+        match self {
+            Event::Predict(t) | Event::Update(t, _) => *t,
+        }
+    }
+}
+
+/// Runs a Kalman filter on predict and update events that arrive at different rates.
+///
+/// Events are registered with their timestamp and processed in timestamp order by `process`.
+/// Before each event the filter is predicted forward to the time of the event, rebuilding `F` and
+/// `Q` for the elapsed time with `state_transition` and `process_noise`, see
+/// `KalmanFilter::predict_dt`. An update event additionally adds its measurement. Events with
+/// the same timestamp are processed in the order they were registered.
+///
+/// Events older than the current time of the filter are processed without a prediction, i.e.,
+/// out-of-sequence measurements are not retrodicted.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::kalman_filter::KalmanFilter;
+/// use filter::kalman::multi_rate::MultiRateFilter;
+/// use nalgebra::{U1, U2, Vector1, Vector2, Matrix2};
+///
+/// let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+/// kf.H = Vector2::new(1.0, 0.0).transpose();
+///
+/// let mut mrf = MultiRateFilter::new(
+///     kf,
+///     0.0,
+///     |dt: f64| Matrix2::new(1.0, dt, 0.0, 1.0),
+///     |dt: f64| Matrix2::identity() * 0.01 * dt,
+/// );
+///
+/// // Predict at 100Hz, update at 1Hz.
+/// for i in 1..=200 {
+///     mrf.add_predict(i as f64 * 0.01);
+/// }
+/// mrf.add_update(1.0, Vector1::new(1.0));
+/// mrf.add_update(2.0, Vector1::new(2.0));
+/// mrf.process();
+///
+/// assert_eq!(mrf.t, 2.0);
+/// assert!((mrf.kf.x[0] - 2.0).abs() < 0.5);
+/// ```
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct MultiRateFilter<F, DimX, DimZ, DimU, FF, QF>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        FF: Fn(F) -> MatrixMN<F, DimX, DimX>,
+        QF: Fn(F) -> MatrixMN<F, DimX, DimX>,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Underlying Kalman filter, which holds the current estimate.
+    pub kf: KalmanFilter<F, DimX, DimZ, DimU>,
+    /// Time of the current estimate.
+    pub t: F,
+    state_transition: FF,
+    process_noise: QF,
+    events: Vec<Event<F, DimZ>>,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, DimU, FF, QF> MultiRateFilter<F, DimX, DimZ, DimU, FF, QF>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        DimU: DimName,
+        FF: Fn(F) -> MatrixMN<F, DimX, DimX>,
+        QF: Fn(F) -> MatrixMN<F, DimX, DimX>,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimU>
+        + Allocator<F, DimX, DimU>,
+{
+    /// Returns a scheduler for the filter `kf`, whose estimate is valid at time `t`.
+    ///
+    /// `state_transition` and `process_noise` return `F` and `Q` for a given time step.
+    pub fn new(
+        kf: KalmanFilter<F, DimX, DimZ, DimU>,
+        t: F,
+        state_transition: FF,
+        process_noise: QF,
+    ) -> Self {
+        MultiRateFilter {
+            kf,
+            t,
+            state_transition,
+            process_noise,
+            events: Vec::new(),
+        }
+    }
+
+    /// Registers a prediction to time `t`.
+    pub fn add_predict(&mut self, t: F) {
+        self.events.push(Event::Predict(t));
+    }
+
+    /// Registers the measurement `z` taken at time `t`.
+    pub fn add_update(&mut self, t: F, z: VectorN<F, DimZ>) {
+        self.events.push(Event::Update(t, z));
+    }
+
+    /// Returns the number of registered events that have not been processed yet.
+    pub fn pending(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Processes all registered events in timestamp order.
+    pub fn process(&mut self) {
+        let mut events = core::mem::take(&mut self.events);
+        events.sort_by(|a, b| a.time().partial_cmp(&b.time()).unwrap_or(Ordering::Equal));
+
+        for event in events {
+            self.predict_to(event.time());
+            if let Event::Update(_, z) = event {
+                self.kf.update(&z, None, None);
+            }
+        }
+    }
+
+    fn predict_to(&mut self, t: F) {
+        if t > self.t {
+            self.kf.predict_dt(t - self.t, &self.state_transition, &self.process_noise);
+            self.t = t;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use nalgebra::{Matrix2, Vector1, Vector2, U1, U2};
+
+    use super::*;
+
+    fn state_transition(dt: f64) -> Matrix2<f64> {
+        Matrix2::new(
+            1.0, dt,
+            0.0, 1.0,
+        )
+    }
+
+    fn process_noise(dt: f64) -> Matrix2<f64> {
+        Matrix2::new(
+            dt * dt * dt / 3.0, dt * dt / 2.0,
+            dt * dt / 2.0, dt,
+        ) * 0.1
+    }
+
+    fn setup_filter() -> KalmanFilter<f64, U2, U1, U1> {
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.H = Vector2::new(1.0, 0.0).transpose();
+        kf.P *= 10.0;
+        kf
+    }
+
+    #[test]
+    fn test_matches_manually_ordered_sequence() {
+        let dt = 0.01;
+        let measurements: Vec<_> = (1..=3)
+            .map(|i| (i as f64 * 0.995, Vector1::new(i as f64 * 2.0)))
+            .collect();
+
+        // Register the events out of order.
+        let mut mrf = MultiRateFilter::new(setup_filter(), 0.0, state_transition, process_noise);
+        for (t, z) in measurements.iter().rev() {
+            mrf.add_update(*t, *z);
+        }
+        for i in 1..=300 {
+            mrf.add_predict(i as f64 * dt);
+        }
+        assert_eq!(mrf.pending(), 303);
+        mrf.process();
+        assert_eq!(mrf.pending(), 0);
+
+        let mut kf = setup_filter();
+        let mut t = 0.0;
+        let mut measurements = measurements.iter().peekable();
+        for i in 1..=300 {
+            let t_predict = i as f64 * dt;
+            while let Some((t_z, z)) = measurements.peek() {
+                if *t_z > t_predict {
+                    break;
+                }
+                kf.predict_dt(t_z - t, state_transition, process_noise);
+                kf.update(z, None, None);
+                t = *t_z;
+                measurements.next();
+            }
+            kf.predict_dt(t_predict - t, state_transition, process_noise);
+            t = t_predict;
+        }
+
+        assert_eq!(mrf.t, t);
+        assert!((mrf.kf.x - kf.x).norm() < 1e-12);
+        assert!((mrf.kf.P - kf.P).norm() < 1e-12);
+    }
+}
@@ -0,0 +1,532 @@
+/*!
+This module implements the Unscented Kalman filter (UKF).
+*/
+
+use alloc::vec::Vec;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+use crate::kalman::outer;
+use crate::Filter;
+
+/// Van der Merwe's scaled sigma points and their weights.
+///
+/// For a distribution of dimension `n`, `2 n + 1` sigma points are placed at the mean and
+/// symmetrically around it along the columns of the scaled square root of the covariance.
+/// `alpha` determines the spread of the points, usually a small positive value such as `1e-3`,
+/// `beta` incorporates prior knowledge of the distribution, 2 is optimal for Gaussians, and
+/// `kappa` is a secondary scaling parameter, usually `0` or `3 - n`.
+///
+/// # References
+/// * Van der Merwe, "Sigma-Point Kalman Filters for Probabilistic Inference in Dynamic
+///   State-Space Models". PhD thesis, Oregon Health & Science University, 2004.
+#[derive(Debug, Clone, Copy)]
+pub struct MerweScaledSigmaPoints<F: RealField> {
+    /// Spread of the sigma points around the mean.
+    pub alpha: F,
+    /// Prior knowledge of the distribution, 2 is optimal for Gaussians.
+    pub beta: F,
+    /// Secondary scaling parameter.
+    pub kappa: F,
+}
+
+impl<F: RealField> MerweScaledSigmaPoints<F> {
+    /// Returns sigma points with the given parameters.
+    pub fn new(alpha: F, beta: F, kappa: F) -> Self {
+        MerweScaledSigmaPoints { alpha, beta, kappa }
+    }
+
+    /// Returns the scaling parameter `lambda = alpha^2 (n + kappa) - n` for dimension `n`.
+    pub fn lambda(&self, n: usize) -> F {
+        let n: F = nalgebra::convert(n as f64);
+        self.alpha * self.alpha * (n + self.kappa) - n
+    }
+
+    /// Returns the weights of the sigma points for dimension `n` as the tuple
+    /// `(mean weight of the center point, covariance weight of the center point,
+    /// weight of every other point)`.
+    pub fn weights(&self, n: usize) -> (F, F, F) {
+        let lambda = self.lambda(n);
+        let n_lambda = nalgebra::convert::<_, F>(n as f64) + lambda;
+        let wm = lambda / n_lambda;
+        let wc = wm + F::one() - self.alpha * self.alpha + self.beta;
+        let wi = F::one() / (n_lambda + n_lambda);
+        (wm, wc, wi)
+    }
+
+    /// Returns the sigma points of the state `x` with the lower Cholesky factor `L` of its
+    /// covariance, extended by the noise with the lower Cholesky factor `noise_L` of its
+    /// covariance, if given. Each point is returned as a pair of state and noise.
+    ///
+    /// The covariance of the state and the noise is block diagonal, so the points along the
+    /// noise directions have the mean state and the points along the state directions zero noise.
+    #[allow(non_snake_case)]
+    fn points<DimX, DimN>(
+        &self,
+        x: &VectorN<F, DimX>,
+        L: &MatrixMN<F, DimX, DimX>,
+        noise_L: Option<&MatrixMN<F, DimN, DimN>>,
+    ) -> Vec<(VectorN<F, DimX>, VectorN<F, DimN>)>
+        where
+            DimX: DimName,
+            DimN: DimName,
+            DefaultAllocator: Allocator<F, DimX>
+            + Allocator<F, DimX, DimX>
+            + Allocator<F, DimN>
+            + Allocator<F, DimN, DimN>,
+    {
+        let n = DimX::dim() + noise_L.map_or(0, |_| DimN::dim());
+        let scale = (nalgebra::convert::<_, F>(n as f64) + self.lambda(n)).sqrt();
+        let zero = VectorN::<F, DimN>::zeros();
+
+        let mut points = Vec::with_capacity(2 * n + 1);
+        points.push((x.clone(), zero.clone()));
+        for column in L.column_iter() {
+            points.push((x + column * scale, zero.clone()));
+            points.push((x - column * scale, zero.clone()));
+        }
+        if let Some(noise_L) = noise_L {
+            for column in noise_L.column_iter() {
+                points.push((x.clone(), column * scale));
+                points.push((x.clone(), -column * scale));
+            }
+        }
+        points
+    }
+}
+
+/// Propagates the Gaussian distribution with the mean `x` and the covariance `P` through the
+/// function `f` with the unscented transform, using the sigma `points`.
+///
+/// Returns the mean and covariance of the transformed distribution, and the cross-covariance
+/// between the original and the transformed distribution. For a linear function `f(x) = A x`
+/// the result is exact, i.e., `A x`, `A P A^T` and `P A^T`.
+///
+/// # Panics
+/// Panics if `P` is not positive definite.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::unscented::{unscented_transform, MerweScaledSigmaPoints};
+/// use nalgebra::{Matrix2, Vector1, Vector2};
+///
+/// // Converts polar coordinates to the distance along the x axis.
+/// let (mean, cov, _) = unscented_transform(
+///     &Vector2::new(10.0, 0.0),
+///     &Matrix2::new(0.01, 0.0, 0.0, 0.01),
+///     |x: &Vector2<f64>| Vector1::new(x[0] * x[1].cos()),
+///     &MerweScaledSigmaPoints::new(0.5, 2.0, 0.0),
+/// );
+/// assert!(mean[0] < 10.0);
+/// assert!(cov[0] < 0.02);
+/// ```
+#[allow(non_snake_case, clippy::type_complexity)]
+pub fn unscented_transform<F, DimX, DimY, Fun>(
+    x: &VectorN<F, DimX>,
+    P: &MatrixMN<F, DimX, DimX>,
+    f: Fun,
+    points: &MerweScaledSigmaPoints<F>,
+) -> (VectorN<F, DimY>, MatrixMN<F, DimY, DimY>, MatrixMN<F, DimX, DimY>)
+    where
+        F: RealField,
+        DimX: DimName,
+        DimY: DimName,
+        Fun: Fn(&VectorN<F, DimX>) -> VectorN<F, DimY>,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimY>
+        + Allocator<F, DimX, DimX>
+        + Allocator<F, DimY, DimY>
+        + Allocator<F, DimX, DimY>,
+{
+    let L = P.clone().cholesky().expect("P must be positive definite").unpack();
+    let sigmas = points.points::<DimX, DimX>(x, &L, None);
+    let (wm, wc, wi) = points.weights(DimX::dim());
+
+    let transformed: Vec<_> = sigmas.iter().map(|(x, _)| f(x)).collect();
+    let mean = transformed.iter().skip(1).fold(&transformed[0] * wm, |y, t| y + t * wi);
+
+    let mut cov = MatrixMN::<F, DimY, DimY>::zeros();
+    let mut cross = MatrixMN::<F, DimX, DimY>::zeros();
+    for (i, ((sigma, _), t)) in sigmas.iter().zip(&transformed).enumerate() {
+        let w = if i == 0 { wc } else { wi };
+        let dx = sigma - x;
+        let dy = t - &mean;
+        cov += outer(&dy) * w;
+        cross += MatrixMN::<F, DimX, DimY>::from_fn(|r, c| dx[r] * dy[c]) * w;
+    }
+    (mean, cov, cross)
+}
+
+/// Determines how the unscented Kalman filter propagates the process and measurement noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseModel {
+    /// The noise is added to the results of the process and measurement functions, so `Q` and
+    /// `R` are added to the transformed covariances. The noise passed to the functions is zero.
+    /// This needs `2 DimX + 1` sigma points per step.
+    Additive,
+    /// The state is augmented with the noise, which is passed to the process and measurement
+    /// functions through additional sigma points, so the noise can enter non-linearly. This needs
+    /// `4 DimX + 1` sigma points to predict and `2 (DimX + DimZ) + 1` to update, and `Q` and `R`
+    /// have to be positive definite.
+    Augmented,
+}
+
+/// Implements the Unscented Kalman filter (UKF).
+///
+/// The process function `fx(x, w, dt)` propagates the state `x` by the time step `dt` with the
+/// process noise `w`, and the measurement function `hx(x, v)` returns the measurement of the
+/// state `x` with the measurement noise `v`. Depending on the `NoiseModel`, the noise is either
+/// additive and the functions are called with zero noise, or the noise is passed through sigma
+/// points of the state augmented with the noise.
+///
+/// # Example
+///
+/// ```
+/// use filter::kalman::unscented::{MerweScaledSigmaPoints, NoiseModel, UnscentedKalmanFilter};
+/// use nalgebra::{Matrix1, Matrix2, Vector1, Vector2};
+///
+/// let mut ukf = UnscentedKalmanFilter::new(
+///     Vector2::new(0.0, 1.0),
+///     Matrix2::identity(),
+///     Matrix2::identity() * 0.01,
+///     Matrix1::new(0.5),
+///     1.0,
+///     MerweScaledSigmaPoints::new(0.1, 2.0, 0.0),
+///     NoiseModel::Additive,
+///     |x: &Vector2<f64>, w: &Vector2<f64>, dt: f64| Vector2::new(x[0] + x[1] * dt, x[1]) + w,
+///     |x: &Vector2<f64>, v: &Vector1<f64>| Vector1::new((x[0] * x[0] + 1.0).sqrt()) + v,
+/// );
+///
+/// for t in 1..10 {
+///     let position = t as f64;
+///     ukf.predict();
+///     ukf.update(&Vector1::new((position * position + 1.0).sqrt()));
+/// }
+/// assert!((ukf.x[0] - 9.0).abs() < 0.5);
+/// ```
+///
+/// # References
+/// * Roger Labbe. "Kalman and Bayesian Filters in Python", chapter 10.
+///   https://github.com/rlabbe/Kalman-and-Bayesian-Filters-in-Python
+/// * Wan and Van der Merwe, "The Unscented Kalman Filter for Nonlinear Estimation".
+///   Proceedings of the IEEE 2000 Adaptive Systems for Signal Processing, Communications, and
+///   Control Symposium, 2000.
+#[allow(non_snake_case)]
+#[derive(Debug)]
+pub struct UnscentedKalmanFilter<F, DimX, DimZ, FX, HX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        FX: Fn(&VectorN<F, DimX>, &VectorN<F, DimX>, F) -> VectorN<F, DimX>,
+        HX: Fn(&VectorN<F, DimX>, &VectorN<F, DimZ>) -> VectorN<F, DimZ>,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>,
+{
+    /// Current state estimate.
+    pub x: VectorN<F, DimX>,
+    /// Current state covariance matrix.
+    pub P: MatrixMN<F, DimX, DimX>,
+    /// Prior (predicted) state estimate.
+    pub x_prior: VectorN<F, DimX>,
+    /// Prior (predicted) state covariance matrix.
+    pub P_prior: MatrixMN<F, DimX, DimX>,
+    /// Posterior (updated) state estimate.
+    pub x_post: VectorN<F, DimX>,
+    /// Posterior (updated) state covariance matrix.
+    pub P_post: MatrixMN<F, DimX, DimX>,
+    /// Process noise matrix.
+    pub Q: MatrixMN<F, DimX, DimX>,
+    /// Measurement noise matrix.
+    pub R: MatrixMN<F, DimZ, DimZ>,
+    /// Time step of the prediction.
+    pub dt: F,
+    /// Residual of the update step.
+    pub y: VectorN<F, DimZ>,
+    /// Kalman gain of the update step.
+    pub K: MatrixMN<F, DimX, DimZ>,
+    /// System uncertainty (P projected to measurement space).
+    pub S: MatrixMN<F, DimZ, DimZ>,
+    /// Sigma points used for the unscented transform.
+    pub points: MerweScaledSigmaPoints<F>,
+    /// How the process and measurement noise is propagated.
+    pub noise_model: NoiseModel,
+    fx: FX,
+    hx: HX,
+}
+
+#[allow(non_snake_case)]
+impl<F, DimX, DimZ, FX, HX> UnscentedKalmanFilter<F, DimX, DimZ, FX, HX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        FX: Fn(&VectorN<F, DimX>, &VectorN<F, DimX>, F) -> VectorN<F, DimX>,
+        HX: Fn(&VectorN<F, DimX>, &VectorN<F, DimZ>) -> VectorN<F, DimZ>,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>,
+{
+    /// Returns an unscented Kalman filter with the initial state `x` and covariance `P`, the
+    /// process noise `Q`, the measurement noise `R`, the time step `dt`, the sigma `points`,
+    /// the `noise_model` and the process and measurement functions `fx` and `hx`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        x: VectorN<F, DimX>,
+        P: MatrixMN<F, DimX, DimX>,
+        Q: MatrixMN<F, DimX, DimX>,
+        R: MatrixMN<F, DimZ, DimZ>,
+        dt: F,
+        points: MerweScaledSigmaPoints<F>,
+        noise_model: NoiseModel,
+        fx: FX,
+        hx: HX,
+    ) -> Self {
+        UnscentedKalmanFilter {
+            x_prior: x.clone(),
+            P_prior: P.clone(),
+            x_post: x.clone(),
+            P_post: P.clone(),
+            x,
+            P,
+            Q,
+            R,
+            dt,
+            y: VectorN::<F, DimZ>::zeros(),
+            K: MatrixMN::<F, DimX, DimZ>::zeros(),
+            S: MatrixMN::<F, DimZ, DimZ>::zeros(),
+            points,
+            noise_model,
+            fx,
+            hx,
+        }
+    }
+
+    /// Predict next state (prior) by propagating the sigma points through the process function.
+    ///
+    /// # Panics
+    /// Panics if `P`, or `Q` with augmented noise, is not positive definite.
+    pub fn predict(&mut self) {
+        let L = self.P.clone().cholesky().expect("P must be positive definite").unpack();
+        let noise_L = match self.noise_model {
+            NoiseModel::Additive => None,
+            NoiseModel::Augmented => {
+                Some(self.Q.clone().cholesky().expect("Q must be positive definite").unpack())
+            }
+        };
+
+        let sigmas = self.points.points(&self.x, &L, noise_L.as_ref());
+        let (wm, wc, wi) = self.points.weights((sigmas.len() - 1) / 2);
+
+        let transformed: Vec<_> = sigmas.iter().map(|(x, w)| (self.fx)(x, w, self.dt)).collect();
+
+        self.x = transformed.iter().skip(1).fold(&transformed[0] * wm, |x, t| x + t * wi);
+        let mut P = outer(&(&transformed[0] - &self.x)) * wc;
+        for t in transformed.iter().skip(1) {
+            P += outer(&(t - &self.x)) * wi;
+        }
+        if self.noise_model == NoiseModel::Additive {
+            P += &self.Q;
+        }
+        self.P = P;
+
+        self.x_prior = self.x.clone();
+        self.P_prior = self.P.clone();
+    }
+
+    /// Add a new measurement (z) to the unscented Kalman filter.
+    ///
+    /// # Panics
+    /// Panics if `P`, or `R` with augmented noise, is not positive definite, or if the system
+    /// uncertainty `S` is singular.
+    pub fn update(&mut self, z: &VectorN<F, DimZ>) {
+        let L = self.P.clone().cholesky().expect("P must be positive definite").unpack();
+        let noise_L = match self.noise_model {
+            NoiseModel::Additive => None,
+            NoiseModel::Augmented => {
+                Some(self.R.clone().cholesky().expect("R must be positive definite").unpack())
+            }
+        };
+
+        let sigmas = self.points.points(&self.x, &L, noise_L.as_ref());
+        let (wm, wc, wi) = self.points.weights((sigmas.len() - 1) / 2);
+
+        let measurements: Vec<_> = sigmas.iter().map(|(x, v)| (self.hx)(x, v)).collect();
+        let z_mean = measurements.iter().skip(1).fold(&measurements[0] * wm, |z, m| z + m * wi);
+
+        let mut S = MatrixMN::<F, DimZ, DimZ>::zeros();
+        let mut Pxz = MatrixMN::<F, DimX, DimZ>::zeros();
+        for (i, ((x, _), m)) in sigmas.iter().zip(&measurements).enumerate() {
+            let w = if i == 0 { wc } else { wi };
+            let dx = x - &self.x;
+            let dz = m - &z_mean;
+            S += MatrixMN::<F, DimZ, DimZ>::from_fn(|r, c| dz[r] * dz[c]) * w;
+            Pxz += MatrixMN::<F, DimX, DimZ>::from_fn(|r, c| dx[r] * dz[c]) * w;
+        }
+        if self.noise_model == NoiseModel::Additive {
+            S += &self.R;
+        }
+
+        let SI = S.clone().try_inverse().expect("S must be invertible");
+        self.K = Pxz * SI;
+        self.y = z - z_mean;
+        self.x = &self.x + &self.K * &self.y;
+        self.P = &self.P - &self.K * &S * self.K.transpose();
+        self.S = S;
+
+        self.x_post = self.x.clone();
+        self.P_post = self.P.clone();
+    }
+}
+
+impl<F, DimX, DimZ, FX, HX> Filter for UnscentedKalmanFilter<F, DimX, DimZ, FX, HX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DimZ: DimName,
+        FX: Fn(&VectorN<F, DimX>, &VectorN<F, DimX>, F) -> VectorN<F, DimX>,
+        HX: Fn(&VectorN<F, DimX>, &VectorN<F, DimZ>) -> VectorN<F, DimZ>,
+        DefaultAllocator: Allocator<F, DimX>
+        + Allocator<F, DimZ>
+        + Allocator<F, DimX, DimZ>
+        + Allocator<F, DimZ, DimX>
+        + Allocator<F, DimZ, DimZ>
+        + Allocator<F, DimX, DimX>,
+{
+    type Measurement = VectorN<F, DimZ>;
+    type State = VectorN<F, DimX>;
+
+    fn predict(&mut self) {
+        UnscentedKalmanFilter::predict(self);
+    }
+
+    fn update(&mut self, z: &VectorN<F, DimZ>) {
+        UnscentedKalmanFilter::update(self, z);
+    }
+
+    fn state(&self) -> &VectorN<F, DimX> {
+        &self.x
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use nalgebra::{Matrix1, Matrix2, Matrix3x2, Vector1, Vector2, U1, U2};
+
+    use super::*;
+    use crate::kalman::kalman_filter::KalmanFilter;
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_noise_models_match_linear_kalman_filter() {
+        let F = Matrix2::new(
+            1.0, 0.5,
+            0.0, 1.0,
+        );
+        let H = Vector2::new(1.0, 0.0).transpose();
+        let Q = Matrix2::new(
+            0.02, 0.01,
+            0.01, 0.05,
+        );
+        let R = Matrix1::new(2.0);
+        let x0 = Vector2::new(0.0, 1.0);
+        let P0 = Matrix2::identity() * 10.0;
+
+        let mut kf: KalmanFilter<f64, U2, U1, U1> = KalmanFilter::default();
+        kf.x = x0;
+        kf.P = P0;
+        kf.F = F;
+        kf.H = H;
+        kf.Q = Q;
+        kf.R = R;
+
+        let fx = |x: &Vector2<f64>, w: &Vector2<f64>, _dt: f64| F * x + w;
+        let hx = |x: &Vector2<f64>, v: &Vector1<f64>| H * x + v;
+        let points = MerweScaledSigmaPoints::new(0.5, 2.0, 0.0);
+        let mut additive =
+            UnscentedKalmanFilter::new(x0, P0, Q, R, 0.5, points, NoiseModel::Additive, fx, hx);
+        let mut augmented =
+            UnscentedKalmanFilter::new(x0, P0, Q, R, 0.5, points, NoiseModel::Augmented, fx, hx);
+
+        for t in 0..30 {
+            let z = Vector1::new(0.5 * t as f64 + (t as f64 * 1.3).sin());
+
+            kf.predict(None, None, None, None);
+            additive.predict();
+            augmented.predict();
+            for ukf_P in &[additive.P, augmented.P] {
+                assert!((ukf_P - kf.P).norm() < 1e-9);
+            }
+
+            kf.update(&z, None, None);
+            additive.update(&z);
+            augmented.update(&z);
+            for (ukf_x, ukf_P) in &[(additive.x, additive.P), (augmented.x, augmented.P)] {
+                assert!((ukf_x - kf.x).norm() < 1e-9);
+                assert!((ukf_P - kf.P).norm() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_unscented_transform_linear_is_exact() {
+        let A = Matrix3x2::new(
+            1.0, 0.5,
+            0.0, 1.0,
+            2.0, -1.0,
+        );
+        let x = Vector2::new(1.0, -2.0);
+        let P = Matrix2::new(
+            2.0, 0.3,
+            0.3, 0.5,
+        );
+
+        let (mean, cov, cross) = unscented_transform(
+            &x,
+            &P,
+            |x: &Vector2<f64>| A * x,
+            &MerweScaledSigmaPoints::new(0.3, 2.0, 1.0),
+        );
+        assert!((mean - A * x).norm() < 1e-9);
+        assert!((cov - A * P * A.transpose()).norm() < 1e-9);
+        assert!((cross - P * A.transpose()).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_augmented_non_additive_noise() {
+        // The measurement noise scales with the measured value, which additive noise cannot
+        // express.
+        let fx = |x: &Vector1<f64>, w: &Vector1<f64>, _dt: f64| x + w;
+        let hx = |x: &Vector1<f64>, v: &Vector1<f64>| Vector1::new(x[0] * (1.0 + v[0]));
+        let mut ukf = UnscentedKalmanFilter::new(
+            Vector1::new(10.0),
+            Matrix1::new(1.0),
+            Matrix1::new(0.01),
+            Matrix1::new(0.01),
+            1.0,
+            MerweScaledSigmaPoints::new(0.5, 2.0, 0.0),
+            NoiseModel::Augmented,
+            fx,
+            hx,
+        );
+
+        ukf.predict();
+        ukf.update(&Vector1::new(10.0));
+
+        // A relative noise of 10% on a measurement of 10 has a variance of about 1.
+        assert!((ukf.S[0] - (1.01 + 1.0)).abs() < 0.05);
+        assert!((ukf.x[0] - 10.0).abs() < 1e-9);
+    }
+}
@@ -12,10 +12,62 @@ trivial_casts, trivial_numeric_casts, unsafe_code, unstable_features, unused_imp
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(test)]
+#[macro_use]
+extern crate std;
 
 pub mod common;
-#[cfg(feature = "alloc")]
 pub mod discrete_bayes;
 pub mod gh;
 pub mod kalman;
+pub mod moving_averages;
+#[cfg(feature = "alloc")]
+pub mod particle;
 pub mod stats;
+#[cfg(test)]
+mod test_util;
+
+/// Common interface of filters that are updated with measurements of type `Measurement` and
+/// estimate a state of type `State`.
+///
+/// This allows writing tracking code that is generic over the filter. Filter specific
+/// parameters, like the control input of the Kalman filter, are not part of this interface,
+/// so `predict` and `update` use the parameters stored in the filter.
+///
+/// # Example
+///
+/// ```
+/// use filter::Filter;
+/// use filter::gh::{GHFilter, GHKFilter};
+///
+/// let mut filters: Vec<Box<dyn Filter<Measurement = f64, State = f64>>> = vec![
+///     Box::new(GHFilter::new(0.0, 1.0, 0.5, 0.1, 1.0)),
+///     Box::new(GHKFilter::new(0.0, 1.0, 0.0, 0.5, 0.1, 0.01, 1.0)),
+/// ];
+///
+/// for t in 1..10 {
+///     for filter in filters.iter_mut() {
+///         filter.predict();
+///         filter.update(&(t as f64));
+///     }
+/// }
+///
+/// for filter in &filters {
+///     assert!((filter.state() - 9.0).abs() < 0.5);
+/// }
+/// ```
+pub trait Filter {
+    /// Type of the measurements.
+    type Measurement;
+    /// Type of the state estimate.
+    type State;
+
+    /// Predicts the next state (prior).
+    fn predict(&mut self);
+
+    /// Adds a new measurement (z) to the filter.
+    fn update(&mut self, z: &Self::Measurement);
+
+    /// Returns the current state estimate.
+    fn state(&self) -> &Self::State;
+}
@@ -0,0 +1,150 @@
+/*!
+This module implements infinite impulse response (IIR) filters.
+*/
+
+use nalgebra::RealField;
+
+/// Implements a second-order IIR filter (biquad) in direct form II.
+///
+/// The filter computes `y[n] = b0 x[n] + b1 x[n-1] + b2 x[n-2] - a1 y[n-1] - a2 y[n-2]`, with the
+/// coefficients normalized so that `a0 = 1`. The low-pass, high-pass and band-pass constructors
+/// compute the coefficients from the cutoff (or center) frequency and the quality factor `q`
+/// following Robert Bristow-Johnson's Audio EQ Cookbook. A `q` of `1 / sqrt(2)` gives a
+/// Butterworth response.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::Biquad;
+///
+/// let mut low_pass: Biquad<f64> = Biquad::low_pass(10.0, 100.0, 0.5_f64.sqrt());
+/// let mut y = 0.0;
+/// for _ in 0..100 {
+///     y = low_pass.process(1.0);
+/// }
+/// assert!((y - 1.0).abs() < 1e-6);
+/// ```
+///
+/// # References
+/// * Bristow-Johnson, "Cookbook formulae for audio EQ biquad filter coefficients".
+#[derive(Debug)]
+pub struct Biquad<F: RealField> {
+    b0: F,
+    b1: F,
+    b2: F,
+    a1: F,
+    a2: F,
+    w1: F,
+    w2: F,
+}
+
+impl<F: RealField> Biquad<F> {
+    /// Returns a biquad with the given coefficients of the numerator `b` and denominator `a` of
+    /// its transfer function. The coefficients are normalized by `a[0]`.
+    pub fn from_coefficients(b: [F; 3], a: [F; 3]) -> Self {
+        Biquad {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            w1: F::zero(),
+            w2: F::zero(),
+        }
+    }
+
+    /// Returns a low-pass biquad with the given `cutoff` frequency and quality factor `q`
+    /// for samples taken at `sample_rate`.
+    pub fn low_pass(cutoff: F, sample_rate: F, q: F) -> Self {
+        let (cos, alpha) = Self::intermediates(cutoff, sample_rate, q);
+        let two: F = nalgebra::convert(2.0);
+        let b1 = F::one() - cos;
+        Self::from_coefficients(
+            [b1 / two, b1, b1 / two],
+            [F::one() + alpha, -two * cos, F::one() - alpha],
+        )
+    }
+
+    /// Returns a high-pass biquad with the given `cutoff` frequency and quality factor `q`
+    /// for samples taken at `sample_rate`.
+    pub fn high_pass(cutoff: F, sample_rate: F, q: F) -> Self {
+        let (cos, alpha) = Self::intermediates(cutoff, sample_rate, q);
+        let two: F = nalgebra::convert(2.0);
+        let b1 = F::one() + cos;
+        Self::from_coefficients(
+            [b1 / two, -b1, b1 / two],
+            [F::one() + alpha, -two * cos, F::one() - alpha],
+        )
+    }
+
+    /// Returns a band-pass biquad with unit gain at the given `center` frequency and quality
+    /// factor `q` for samples taken at `sample_rate`.
+    pub fn band_pass(center: F, sample_rate: F, q: F) -> Self {
+        let (cos, alpha) = Self::intermediates(center, sample_rate, q);
+        let two: F = nalgebra::convert(2.0);
+        Self::from_coefficients(
+            [alpha, F::zero(), -alpha],
+            [F::one() + alpha, -two * cos, F::one() - alpha],
+        )
+    }
+
+    /// Filters the next sample and returns the output.
+    pub fn process(&mut self, sample: F) -> F {
+        let w0 = sample - self.a1 * self.w1 - self.a2 * self.w2;
+        let y = self.b0 * w0 + self.b1 * self.w1 + self.b2 * self.w2;
+        self.w2 = self.w1;
+        self.w1 = w0;
+        y
+    }
+
+    /// Returns `cos(w0)` and `alpha` of the cookbook formulae.
+    fn intermediates(frequency: F, sample_rate: F, q: F) -> (F, F) {
+        let two: F = nalgebra::convert(2.0);
+        let w0 = two * F::pi() * frequency / sample_rate;
+        (w0.cos(), w0.sin() / (two * q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use super::*;
+
+    /// Returns the amplitude of the output after the transient has decayed.
+    fn steady_state_amplitude(filter: &mut Biquad<f64>, input: impl Fn(usize) -> f64) -> f64 {
+        (0..1000)
+            .map(|n| filter.process(input(n)))
+            .skip(900)
+            .fold(0.0, |max: f64, y| max.max(y.abs()))
+    }
+
+    #[test]
+    fn test_low_pass() {
+        let q = 0.5_f64.sqrt();
+
+        let mut low_pass = Biquad::low_pass(5.0, 100.0, q);
+        assert_approx_eq!(1.0, steady_state_amplitude(&mut low_pass, |_| 1.0));
+
+        let mut low_pass = Biquad::low_pass(5.0, 100.0, q);
+        let nyquist = |n: usize| (-1.0_f64).powi(n as i32);
+        assert!(steady_state_amplitude(&mut low_pass, nyquist) < 1e-6);
+    }
+
+    #[test]
+    fn test_high_pass_and_band_pass() {
+        let q = 0.5_f64.sqrt();
+        let nyquist = |n: usize| (-1.0_f64).powi(n as i32);
+
+        let mut high_pass = Biquad::high_pass(5.0, 100.0, q);
+        assert!(steady_state_amplitude(&mut high_pass, |_| 1.0) < 1e-6);
+        let mut high_pass = Biquad::high_pass(5.0, 100.0, q);
+        assert_approx_eq!(1.0, steady_state_amplitude(&mut high_pass, nyquist));
+
+        let mut band_pass = Biquad::band_pass(25.0, 100.0, 2.0);
+        assert!(steady_state_amplitude(&mut band_pass, |_| 1.0) < 1e-6);
+        let mut band_pass = Biquad::band_pass(25.0, 100.0, 2.0);
+        let center = |n: usize| (core::f64::consts::PI * n as f64 / 2.0).sin();
+        assert_approx_eq!(1.0, steady_state_amplitude(&mut band_pass, center), 1e-3);
+    }
+}
@@ -0,0 +1,12 @@
+/*!
+This module contains implementations of moving averages.
+*/
+mod iir;
+mod regular;
+#[cfg(feature = "alloc")]
+mod windowed;
+
+pub use iir::*;
+pub use regular::*;
+#[cfg(feature = "alloc")]
+pub use windowed::*;
@@ -0,0 +1,666 @@
+/*!
+This module implements moving averages with a fixed weighting scheme.
+*/
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, RealField, Vector1, VectorN, U1};
+
+/// Implements an exponentially weighted moving average (EWMA).
+///
+/// Each update moves the estimate towards the new value by the fraction `coef`:
+/// `estimate = estimate + coef * (value - estimate)`. A `coef` of one discards all history,
+/// while a `coef` close to zero averages over a long history.
+///
+/// The exponentially weighted variance of each component is tracked alongside the estimate as
+/// `variance = (1 - coef) * (variance + coef * (value - previous_estimate)^2)`, e.g., for
+/// uncertainty bands around the estimate.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::ExponentialWMA;
+/// use nalgebra::{Vector2, U2};
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut ewma: ExponentialWMA<f64, U2> = ExponentialWMA::new(0.5, Vector2::new(0.0, 10.0));
+/// let estimate = ewma.update(&Vector2::new(1.0, 0.0));
+///
+/// assert_approx_eq!(0.5, estimate[0]);
+/// assert_approx_eq!(5.0, estimate[1]);
+/// ```
+#[derive(Debug)]
+pub struct ExponentialWMA<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX>,
+{
+    coef: F,
+    estimate: VectorN<F, DimX>,
+    variance: VectorN<F, DimX>,
+}
+
+impl<F, DimX> ExponentialWMA<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX>,
+{
+    /// Returns an exponentially weighted moving average with the given initialisation parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `coef` - weight of a new value, between zero and one.
+    /// * `start` - initial value of the estimate.
+    pub fn new(coef: F, start: VectorN<F, DimX>) -> Self {
+        ExponentialWMA {
+            coef,
+            estimate: start,
+            variance: VectorN::<F, DimX>::zeros(),
+        }
+    }
+
+    /// Returns an exponentially weighted moving average with a weight given by the `span`,
+    /// as used by pandas: `coef = 2 / (span + 1)`. A span of `N` gives the same center of
+    /// mass as a simple moving average over `N` values.
+    pub fn from_span(span: F, start: VectorN<F, DimX>) -> Self {
+        let two: F = nalgebra::convert(2.0);
+        Self::new(two / (span + F::one()), start)
+    }
+
+    /// Returns an exponentially weighted moving average with a weight given by the `halflife`,
+    /// as used by pandas: `coef = 1 - exp(ln(0.5) / halflife)`. The weight of a value decays
+    /// to half after `halflife` further updates.
+    pub fn from_halflife(halflife: F, start: VectorN<F, DimX>) -> Self {
+        let half: F = nalgebra::convert(0.5);
+        Self::new(F::one() - (half.ln() / halflife).exp(), start)
+    }
+
+    /// Adds a new value to the moving average and returns the new estimate.
+    pub fn update(&mut self, value: &VectorN<F, DimX>) -> VectorN<F, DimX> {
+        let diff = value - &self.estimate;
+        self.variance =
+            (&self.variance + diff.component_mul(&diff) * self.coef) * (F::one() - self.coef);
+        self.estimate = &self.estimate + diff * self.coef;
+        self.estimate.clone()
+    }
+
+    /// Returns the current estimate.
+    pub fn estimate(&self) -> &VectorN<F, DimX> {
+        &self.estimate
+    }
+
+    /// Returns the exponentially weighted variance of each component of the values.
+    pub fn variance(&self) -> &VectorN<F, DimX> {
+        &self.variance
+    }
+
+    /// Returns a mutable reference to the current estimate, e.g., to correct it directly.
+    pub fn estimate_mut(&mut self) -> &mut VectorN<F, DimX> {
+        &mut self.estimate
+    }
+
+    /// Resets the moving average to the initial estimate `start` and the variance to zero,
+    /// discarding all history. Subsequent updates behave as for a newly constructed moving average.
+    pub fn reset(&mut self, start: VectorN<F, DimX>) {
+        self.estimate = start;
+        self.variance = VectorN::<F, DimX>::zeros();
+    }
+
+    /// Returns the weight of a new value.
+    pub fn coef(&self) -> F {
+        self.coef
+    }
+}
+
+/// Implements an exponentially weighted moving average for irregularly sampled values.
+///
+/// This is the continuous-time analog of `ExponentialWMA`. Each update is given the time `dt`
+/// that passed since the previous value, and the weight of the previous estimate decays by
+/// `exp(-dt / tau)`, so the result does not depend on how the time is divided into updates.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::IrregularEWMA;
+/// use nalgebra::{Vector1, U1};
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut ewma: IrregularEWMA<f64, U1> = IrregularEWMA::new(1.0, Vector1::new(0.0));
+/// let estimate = ewma.update(&Vector1::new(1.0), 2.0_f64.ln());
+///
+/// assert_approx_eq!(0.5, estimate[0]);
+/// ```
+#[derive(Debug)]
+pub struct IrregularEWMA<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX>,
+{
+    tau: F,
+    estimate: VectorN<F, DimX>,
+}
+
+impl<F, DimX> IrregularEWMA<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX>,
+{
+    /// Returns an irregular exponentially weighted moving average with the given
+    /// initialisation parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `tau` - time constant after which the weight of a value has decayed to `1 / e`.
+    /// * `start` - initial value of the estimate.
+    pub fn new(tau: F, start: VectorN<F, DimX>) -> Self {
+        IrregularEWMA {
+            tau,
+            estimate: start,
+        }
+    }
+
+    /// Adds a new value, observed `dt` after the previous one, and returns the new estimate.
+    pub fn update(&mut self, value: &VectorN<F, DimX>, dt: F) -> VectorN<F, DimX> {
+        let coef = F::one() - (-dt / self.tau).exp();
+        self.estimate = &self.estimate + (value - &self.estimate) * coef;
+        self.estimate.clone()
+    }
+
+    /// Returns the current estimate.
+    pub fn estimate(&self) -> &VectorN<F, DimX> {
+        &self.estimate
+    }
+
+    /// Returns the time constant.
+    pub fn tau(&self) -> F {
+        self.tau
+    }
+}
+
+/// Implements Holt's linear trend method (double exponential smoothing).
+///
+/// In addition to the smoothed level of the input, a smoothed trend is tracked. Unlike
+/// `ExponentialWMA`, this does not lag behind inputs that change linearly.
+///
+/// * `level = alpha * value + (1 - alpha) * (level + trend)`
+/// * `trend = beta * (level - previous_level) + (1 - beta) * trend`
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::HoltLinear;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut holt: HoltLinear<f64> = HoltLinear::new(0.5, 0.5, 0.0, 1.0);
+/// assert_approx_eq!(1.0, holt.update(1.0));
+/// assert_approx_eq!(1.0, holt.trend());
+/// assert_approx_eq!(4.0, holt.forecast(3));
+/// ```
+///
+/// # References
+/// * Holt, "Forecasting seasonals and trends by exponentially weighted moving averages".
+///   International Journal of Forecasting 20, 2004.
+#[derive(Debug)]
+pub struct HoltLinear<F: RealField> {
+    alpha: F,
+    beta: F,
+    level: F,
+    trend: F,
+}
+
+impl<F: RealField> HoltLinear<F> {
+    /// Returns a double exponential smoothing filter with the given initialisation parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - weight of a new value in the level, between zero and one.
+    /// * `beta` - weight of a new level difference in the trend, between zero and one.
+    /// * `level` - initial value of the level.
+    /// * `trend` - initial value of the trend (change per step).
+    pub fn new(alpha: F, beta: F, level: F, trend: F) -> Self {
+        HoltLinear {
+            alpha,
+            beta,
+            level,
+            trend,
+        }
+    }
+
+    /// Adds a new value and returns the new level.
+    pub fn update(&mut self, value: F) -> F {
+        let previous_level = self.level;
+        self.level = self.alpha * value + (F::one() - self.alpha) * (self.level + self.trend);
+        self.trend = self.beta * (self.level - previous_level) + (F::one() - self.beta) * self.trend;
+        self.level
+    }
+
+    /// Returns the value expected `h` steps after the last update.
+    pub fn forecast(&self, h: usize) -> F {
+        let h: F = nalgebra::convert(h as f64);
+        self.level + h * self.trend
+    }
+
+    /// Returns the current level.
+    pub fn level(&self) -> F {
+        self.level
+    }
+
+    /// Returns the current trend, i.e., the change per step.
+    pub fn trend(&self) -> F {
+        self.trend
+    }
+}
+
+/// Implements the additive Holt-Winters method (triple exponential smoothing).
+///
+/// This extends `HoltLinear` by a seasonal component that repeats every `season_length` steps
+/// and is added to the level and trend.
+///
+/// * `level = alpha * (value - seasonal) + (1 - alpha) * (level + trend)`
+/// * `trend = beta * (level - previous_level) + (1 - beta) * trend`
+/// * `seasonal = gamma * (value - level) + (1 - gamma) * seasonal`
+///
+/// The filter is initialised from the first full period of values: the level is set to their
+/// mean, the trend to zero, and each seasonal component to the difference of the corresponding
+/// value and the mean. Until the first full period has been observed, the estimate is the mean
+/// of the values so far.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::HoltWinters;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut hw: HoltWinters<f64> = HoltWinters::new(0.5, 0.1, 0.5, 2);
+/// hw.update(1.0);
+/// hw.update(3.0);
+///
+/// assert_approx_eq!(2.0, hw.level());
+/// assert_approx_eq!(1.0, hw.forecast(1));
+/// assert_approx_eq!(3.0, hw.forecast(2));
+/// ```
+///
+/// # References
+/// * Winters, "Forecasting sales by exponentially weighted moving averages".
+///   Management Science 6, 1960.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct HoltWinters<F: RealField> {
+    alpha: F,
+    beta: F,
+    gamma: F,
+    season_length: usize,
+    level: F,
+    trend: F,
+    seasonal: Vec<F>,
+    /// Number of values added so far.
+    count: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<F: RealField> HoltWinters<F> {
+    /// Returns a triple exponential smoothing filter with the given parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - weight of a new value in the level, between zero and one.
+    /// * `beta` - weight of a new level difference in the trend, between zero and one.
+    /// * `gamma` - weight of a new value in the seasonal component, between zero and one.
+    /// * `season_length` - number of steps after which the seasonal pattern repeats.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `season_length` is zero.
+    pub fn new(alpha: F, beta: F, gamma: F, season_length: usize) -> Self {
+        assert!(season_length > 0, "season length must not be zero");
+        HoltWinters {
+            alpha,
+            beta,
+            gamma,
+            season_length,
+            level: F::zero(),
+            trend: F::zero(),
+            seasonal: Vec::with_capacity(season_length),
+            count: 0,
+        }
+    }
+
+    /// Adds a new value and returns the new estimate of the current value
+    /// (level plus seasonal component).
+    pub fn update(&mut self, value: F) -> F {
+        self.count += 1;
+
+        if self.count <= self.season_length {
+            self.seasonal.push(value);
+            let n: F = nalgebra::convert(self.count as f64);
+            self.level += (value - self.level) / n;
+            if self.count == self.season_length {
+                let level = self.level;
+                self.seasonal.iter_mut().for_each(|s| *s -= level);
+                return value;
+            }
+            return self.level;
+        }
+
+        let ix = (self.count - 1) % self.season_length;
+        let previous_level = self.level;
+        self.level = self.alpha * (value - self.seasonal[ix])
+            + (F::one() - self.alpha) * (self.level + self.trend);
+        self.trend = self.beta * (self.level - previous_level) + (F::one() - self.beta) * self.trend;
+        self.seasonal[ix] =
+            self.gamma * (value - self.level) + (F::one() - self.gamma) * self.seasonal[ix];
+
+        self.level + self.seasonal[ix]
+    }
+
+    /// Returns the value expected `h` steps after the last update.
+    /// Before the first full period has been observed, this is the mean of the values so far.
+    pub fn forecast(&self, h: usize) -> F {
+        if self.count < self.season_length {
+            return self.level;
+        }
+        let ix = (self.count - 1 + h) % self.season_length;
+        let h: F = nalgebra::convert(h as f64);
+        self.level + h * self.trend + self.seasonal[ix]
+    }
+
+    /// Returns the current level.
+    pub fn level(&self) -> F {
+        self.level
+    }
+
+    /// Returns the current trend, i.e., the change of the level per step.
+    pub fn trend(&self) -> F {
+        self.trend
+    }
+
+    /// Returns the seasonal components. Before the first full period has been observed,
+    /// these are the raw values so far.
+    pub fn seasonal(&self) -> &[F] {
+        &self.seasonal
+    }
+}
+
+/// Parameters of a `JambonAdaptiveMA`.
+#[derive(Debug, Clone, Copy)]
+pub struct JAMAParameters<F> {
+    /// Weight of a new value while the input is dominated by noise.
+    pub min_coef: F,
+    /// Weight of a new value while the input follows a trend.
+    pub max_coef: F,
+    /// Weight of a new residual sign in the moving average used to detect trends.
+    pub trend_coef: F,
+    /// Exponent applied to the trend strength before interpolating between `min_coef`
+    /// and `max_coef`. Larger values require a more consistent trend to increase the weight.
+    pub exponent: F,
+}
+
+/// Implements Martin Jambon's adaptive moving average.
+///
+/// This is an exponentially weighted moving average whose weight adapts to the input.
+/// The signs of the residuals between the input and the estimate are tracked by a second
+/// moving average. While the input follows a trend, the residuals keep the same sign, the trend
+/// strength (the magnitude of their average) approaches one, and the weight increases towards
+/// `max_coef` so the estimate does not lag behind. While the input is dominated by noise, the signs
+/// alternate, the trend strength approaches zero, and the weight decreases towards `min_coef` to
+/// smooth the noise.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::{JAMAParameters, JambonAdaptiveMA};
+///
+/// let params = JAMAParameters {
+///     min_coef: 0.05,
+///     max_coef: 0.5,
+///     trend_coef: 0.2,
+///     exponent: 2.0,
+/// };
+/// let mut jama: JambonAdaptiveMA<f64> = JambonAdaptiveMA::new(params, 0.0);
+///
+/// for t in 0..20 {
+///     jama.update(t as f64);
+/// }
+/// assert!(jama.coef() > 0.4);
+/// ```
+///
+/// # References
+/// * Martin Jambon, "Adaptive moving average".
+#[derive(Debug)]
+pub struct JambonAdaptiveMA<F: RealField> {
+    params: JAMAParameters<F>,
+    trend: ExponentialWMA<F, U1>,
+    previous_coef: F,
+    estimate: F,
+}
+
+impl<F: RealField> JambonAdaptiveMA<F> {
+    /// Returns an adaptive moving average with the given parameters and initial estimate.
+    pub fn new(params: JAMAParameters<F>, start: F) -> Self {
+        JambonAdaptiveMA {
+            params,
+            trend: ExponentialWMA::new(params.trend_coef, Vector1::new(F::zero())),
+            previous_coef: params.min_coef,
+            estimate: start,
+        }
+    }
+
+    /// Adds a new value to the moving average and returns the new estimate.
+    pub fn update(&mut self, value: F) -> F {
+        let residual = value - self.estimate;
+        let sign = if residual > F::zero() {
+            F::one()
+        } else if residual < F::zero() {
+            -F::one()
+        } else {
+            F::zero()
+        };
+
+        let strength = self.trend.update(&Vector1::new(sign))[0].abs();
+        let JAMAParameters {
+            min_coef,
+            max_coef,
+            exponent,
+            ..
+        } = self.params;
+        self.previous_coef = min_coef + (max_coef - min_coef) * strength.powf(exponent);
+
+        self.estimate += self.previous_coef * residual;
+        self.estimate
+    }
+
+    /// Returns the current estimate.
+    pub fn estimate(&self) -> F {
+        self.estimate
+    }
+
+    /// Returns the weight that was applied to the last value.
+    pub fn coef(&self) -> F {
+        self.previous_coef
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+    use nalgebra::Vector1;
+
+    use crate::test_util;
+
+    use super::*;
+
+    #[test]
+    fn test_ewma_converges_to_constant() {
+        let mut ewma = ExponentialWMA::new(0.1, Vector1::new(0.0_f64));
+
+        for _ in 0..500 {
+            ewma.update(&Vector1::new(3.0));
+        }
+
+        assert_approx_eq!(3.0, ewma.estimate()[0]);
+    }
+
+    #[test]
+    fn test_ewma_variance() {
+        // Uniform noise in (-1, 1), with a variance of 1/3.
+        let mut uniform = test_util::uniform(1);
+        let mut noise = || 2.0 * uniform() - 1.0;
+
+        let mut ewma = ExponentialWMA::new(0.005, Vector1::new(5.0_f64));
+        assert_eq!(0.0, ewma.variance()[0]);
+
+        let mut average = 0.0;
+        for i in 0..20000 {
+            ewma.update(&Vector1::new(5.0 + noise()));
+            if i >= 10000 {
+                average += ewma.variance()[0] / 10000.0;
+            }
+        }
+
+        assert_approx_eq!(1.0 / 3.0, average, 0.02);
+        assert_approx_eq!(5.0, ewma.estimate()[0], 0.1);
+
+        ewma.reset(Vector1::new(0.0));
+        assert_eq!(0.0, ewma.variance()[0]);
+    }
+
+    #[test]
+    fn test_ewma_reset() {
+        let mut ewma = ExponentialWMA::new(0.3, Vector1::new(0.0_f64));
+        for t in 0..10 {
+            ewma.update(&Vector1::new(f64::from(t)));
+        }
+
+        ewma.reset(Vector1::new(2.0));
+        let mut fresh = ExponentialWMA::new(0.3, Vector1::new(2.0_f64));
+        for t in 0..10 {
+            let value = Vector1::new(f64::from(t).sin());
+            assert_eq!(fresh.update(&value), ewma.update(&value));
+        }
+
+        *ewma.estimate_mut() = Vector1::new(-1.0);
+        assert_approx_eq!(-0.7, ewma.update(&Vector1::new(0.0))[0]);
+    }
+
+    #[test]
+    fn test_ewma_from_span() {
+        let mut ewma = ExponentialWMA::from_span(1.0, Vector1::new(0.0_f64));
+        assert_approx_eq!(1.0, ewma.coef());
+        assert_approx_eq!(5.0, ewma.update(&Vector1::new(5.0))[0]);
+        assert_approx_eq!(-2.0, ewma.update(&Vector1::new(-2.0))[0]);
+
+        // pandas: ewm(span=9) has alpha = 0.2, ewm(span=3) has alpha = 0.5.
+        assert_approx_eq!(0.2, ExponentialWMA::from_span(9.0, Vector1::new(0.0_f64)).coef());
+        assert_approx_eq!(0.5, ExponentialWMA::from_span(3.0, Vector1::new(0.0_f64)).coef());
+    }
+
+    #[test]
+    fn test_ewma_from_halflife() {
+        // pandas: ewm(halflife=1) has alpha = 0.5, ewm(halflife=2) has alpha = 1 - sqrt(0.5).
+        assert_approx_eq!(0.5, ExponentialWMA::from_halflife(1.0, Vector1::new(0.0_f64)).coef());
+        assert_approx_eq!(
+            0.2928932188134524,
+            ExponentialWMA::from_halflife(2.0, Vector1::new(0.0_f64)).coef()
+        );
+
+        // The weight of the start value halves after `halflife` updates.
+        let mut ewma = ExponentialWMA::from_halflife(4.0, Vector1::new(1.0_f64));
+        for _ in 0..4 {
+            ewma.update(&Vector1::new(0.0));
+        }
+        assert_approx_eq!(0.5, ewma.estimate()[0]);
+    }
+
+    #[test]
+    fn test_irregular_ewma_is_independent_of_step_division() {
+        let mut split = IrregularEWMA::new(2.0, Vector1::new(0.0_f64));
+        split.update(&Vector1::new(4.0), 0.5);
+        split.update(&Vector1::new(4.0), 0.5);
+
+        let mut single = IrregularEWMA::new(2.0, Vector1::new(0.0_f64));
+        single.update(&Vector1::new(4.0), 1.0);
+
+        assert_approx_eq!(single.estimate()[0], split.estimate()[0]);
+
+        // The discrete filter depends on the number of updates.
+        let coef = 1.0 - (-0.5_f64 / 2.0).exp();
+        let mut discrete = ExponentialWMA::new(coef, Vector1::new(0.0_f64));
+        discrete.update(&Vector1::new(4.0));
+        assert!((single.estimate()[0] - discrete.estimate()[0]).abs() > 0.1);
+    }
+
+    #[test]
+    fn test_jama_adapts_to_trend_and_noise() {
+        let params = JAMAParameters {
+            min_coef: 0.05,
+            max_coef: 0.8,
+            trend_coef: 0.2,
+            exponent: 2.0,
+        };
+        let mut jama = JambonAdaptiveMA::new(params, 0.0_f64);
+
+        for t in 0..50 {
+            jama.update(if t % 2 == 0 { 1.0 } else { -1.0 });
+        }
+        let noise_coef = jama.coef();
+        assert!(noise_coef < 0.1);
+        assert!(jama.estimate().abs() < 0.2);
+
+        for t in 0..50 {
+            jama.update(f64::from(t));
+        }
+        let trend_coef = jama.coef();
+        assert!(trend_coef > 0.7);
+        assert!(trend_coef > noise_coef);
+        assert!((49.0 - jama.estimate()) < 1.0);
+
+        for t in 0..50 {
+            jama.update(if t % 2 == 0 { 51.0 } else { 49.0 });
+        }
+        assert!(jama.coef() < trend_coef);
+        assert!(jama.coef() < 0.1);
+    }
+
+    #[test]
+    fn test_holt_tracks_linear_trend() {
+        let mut holt = HoltLinear::new(0.3, 0.1, 0.0_f64, 0.0);
+
+        let mut level = 0.0;
+        for t in 0..300 {
+            level = holt.update(2.0 * f64::from(t) + 5.0);
+        }
+
+        let last = 2.0 * 299.0 + 5.0;
+        assert_approx_eq!(last, level);
+        assert_approx_eq!(2.0, holt.trend());
+        assert_approx_eq!(last + 6.0, holt.forecast(3));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_holt_winters_forecasts_next_period() {
+        let period = 12;
+        let series = |t: usize| {
+            let t = t as f64;
+            0.5 * t + 10.0 * (2.0 * core::f64::consts::PI * t / period as f64).sin()
+        };
+        let mut hw = HoltWinters::new(0.3, 0.05, 0.3, period);
+
+        let n = 20 * period;
+        for t in 0..n {
+            hw.update(series(t));
+        }
+
+        assert_approx_eq!(0.5, hw.trend(), 0.05);
+        for h in 1..=period {
+            assert_approx_eq!(series(n - 1 + h), hw.forecast(h), 0.5);
+        }
+    }
+}
@@ -0,0 +1,579 @@
+/*!
+This module implements moving averages over a finite window of the most recent values.
+*/
+
+use alloc::vec::Vec;
+
+use nalgebra::RealField;
+
+/// Implements a simple moving average (SMA), the unweighted mean of the last `window` values.
+///
+/// The values are kept in a ring buffer, and a running sum makes each update O(1).
+/// Until `window` values have been seen, the average of all values so far is returned.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::SimpleMovingAverage;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut sma: SimpleMovingAverage<f64> = SimpleMovingAverage::new(2);
+/// assert_approx_eq!(1.0, sma.update(1.0));
+/// assert_approx_eq!(1.5, sma.update(2.0));
+/// assert_approx_eq!(2.5, sma.update(3.0));
+/// ```
+#[derive(Debug)]
+pub struct SimpleMovingAverage<F: RealField> {
+    window: usize,
+    buffer: Vec<F>,
+    next: usize,
+    sum: F,
+}
+
+impl<F: RealField> SimpleMovingAverage<F> {
+    /// Returns a simple moving average over the last `window` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window must not be empty");
+        SimpleMovingAverage {
+            window,
+            buffer: Vec::with_capacity(window),
+            next: 0,
+            sum: F::zero(),
+        }
+    }
+
+    /// Adds a new value to the moving average and returns the new estimate.
+    pub fn update(&mut self, value: F) -> F {
+        if self.buffer.len() < self.window {
+            self.buffer.push(value);
+        } else {
+            self.sum -= self.buffer[self.next];
+            self.buffer[self.next] = value;
+            self.next = (self.next + 1) % self.window;
+        }
+        self.sum += value;
+        self.estimate()
+    }
+
+    /// Returns the current estimate, or zero if no value has been added yet.
+    pub fn estimate(&self) -> F {
+        if self.buffer.is_empty() {
+            return F::zero();
+        }
+        self.sum / nalgebra::convert(self.buffer.len() as f64)
+    }
+
+    /// Returns the number of values the average is computed over.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+}
+
+/// Implements a weighted moving average, a finite impulse response filter with the given weights.
+///
+/// `weights[0]` is applied to the most recent value, `weights[1]` to the one before, and so on.
+/// The weighted sum is normalized by the sum of the weights. Until as many values as weights
+/// have been seen, only the leading weights are applied to the values so far.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::WeightedMovingAverage;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut wma: WeightedMovingAverage<f64> = WeightedMovingAverage::new(&[3.0, 1.0]);
+/// wma.update(0.0);
+/// assert_approx_eq!(0.75, wma.update(1.0));
+/// ```
+#[derive(Debug)]
+pub struct WeightedMovingAverage<F: RealField> {
+    weights: Vec<F>,
+    buffer: Vec<F>,
+    next: usize,
+}
+
+impl<F: RealField> WeightedMovingAverage<F> {
+    /// Returns a weighted moving average with the given weights, starting with the weight of the
+    /// most recent value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is empty or sums to zero.
+    pub fn new(weights: &[F]) -> Self {
+        assert!(!weights.is_empty(), "weights must not be empty");
+        let sum = weights.iter().fold(F::zero(), |s, &w| s + w);
+        assert!(sum != F::zero(), "weights must not sum to zero");
+        WeightedMovingAverage {
+            weights: weights.to_vec(),
+            buffer: Vec::with_capacity(weights.len()),
+            next: 0,
+        }
+    }
+
+    /// Adds a new value to the moving average and returns the new estimate.
+    pub fn update(&mut self, value: F) -> F {
+        if self.buffer.len() < self.weights.len() {
+            self.buffer.push(value);
+            self.next = self.buffer.len() % self.weights.len();
+        } else {
+            self.buffer[self.next] = value;
+            self.next = (self.next + 1) % self.weights.len();
+        }
+        self.estimate()
+    }
+
+    /// Returns the current estimate, or zero if no value has been added yet.
+    pub fn estimate(&self) -> F {
+        let n = self.buffer.len();
+        if n == 0 {
+            return F::zero();
+        }
+        // Walk the ring buffer backwards from the most recent value.
+        let (sum, weight_sum) = (0..n)
+            .map(|i| self.buffer[(self.next + 2 * n - 1 - i) % n])
+            .zip(self.weights.iter())
+            .fold((F::zero(), F::zero()), |(sum, weight_sum), (x, &w)| {
+                (sum + w * x, weight_sum + w)
+            });
+        sum / weight_sum
+    }
+
+    /// Returns the weights, starting with the weight of the most recent value.
+    pub fn weights(&self) -> &[F] {
+        &self.weights
+    }
+}
+
+/// Implements a running median filter over the last `window` values.
+///
+/// Unlike moving averages, the median completely rejects isolated outliers as long as they make up
+/// less than half of the window. The values are kept in a ring buffer in arrival order and in a
+/// sorted buffer from which the median is read; each update costs O(window).
+/// Until `window` values have been seen, the median of all values so far is returned, where the
+/// median of an even number of values is the mean of the two central ones.
+///
+/// Values must not be NaN.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::MedianFilter;
+///
+/// let mut median: MedianFilter<f64> = MedianFilter::new(3);
+/// median.update(1.0);
+/// median.update(100.0);
+/// assert_eq!(2.0, median.update(2.0));
+/// ```
+#[derive(Debug)]
+pub struct MedianFilter<F: RealField> {
+    window: usize,
+    buffer: Vec<F>,
+    sorted: Vec<F>,
+    next: usize,
+}
+
+impl<F: RealField> MedianFilter<F> {
+    /// Returns a median filter over the last `window` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is not odd.
+    pub fn new(window: usize) -> Self {
+        assert!(window % 2 == 1, "window must be odd");
+        MedianFilter {
+            window,
+            buffer: Vec::with_capacity(window),
+            sorted: Vec::with_capacity(window),
+            next: 0,
+        }
+    }
+
+    /// Adds a new value to the filter and returns the median of the window.
+    pub fn update(&mut self, value: F) -> F {
+        if self.buffer.len() < self.window {
+            self.buffer.push(value);
+        } else {
+            let oldest = core::mem::replace(&mut self.buffer[self.next], value);
+            self.next = (self.next + 1) % self.window;
+            let ix = self.sorted_position(oldest);
+            self.sorted.remove(ix);
+        }
+        let ix = self.sorted_position(value);
+        self.sorted.insert(ix, value);
+        self.estimate()
+    }
+
+    /// Returns the median of the current window, or zero if no value has been added yet.
+    pub fn estimate(&self) -> F {
+        if self.sorted.is_empty() {
+            return F::zero();
+        }
+        median_of_sorted(&self.sorted)
+    }
+
+    /// Returns the number of values the median is computed over.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Returns the values in the current window in ascending order.
+    pub(crate) fn sorted_values(&self) -> &[F] {
+        &self.sorted
+    }
+
+    /// Returns the index of the first element of the sorted buffer not less than `value`.
+    fn sorted_position(&self, value: F) -> usize {
+        self.sorted.partition_point(|&x| x < value)
+    }
+}
+
+/// Implements a Hampel filter, which detects and replaces outliers.
+///
+/// A value is considered an outlier if it deviates from the median of the last `window` values
+/// (including itself) by more than `n_sigma` times the scaled median absolute deviation (MAD) of
+/// the window, `1.4826 * MAD`, a robust estimate of the standard deviation. Outliers are replaced
+/// by the median, all other values are passed through unchanged.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::HampelFilter;
+///
+/// let mut hampel: HampelFilter<f64> = HampelFilter::new(5, 3.0);
+/// for &value in &[1.0, 1.1, 0.9, 1.0] {
+///     assert_eq!(value, hampel.update(value));
+/// }
+/// assert_eq!(1.0, hampel.update(50.0));
+/// assert!(hampel.was_outlier());
+/// ```
+///
+/// # References
+/// * Hampel, "The influence curve and its role in robust estimation".
+///   Journal of the American Statistical Association 69, 1974.
+#[derive(Debug)]
+pub struct HampelFilter<F: RealField> {
+    median: MedianFilter<F>,
+    n_sigma: F,
+    was_outlier: bool,
+    /// Scratch buffer for the absolute deviations from the median, kept to avoid allocating in
+    /// each update.
+    deviations: Vec<F>,
+}
+
+impl<F: RealField> HampelFilter<F> {
+    /// Returns a Hampel filter with the given parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - odd number of values the median and MAD are computed over.
+    /// * `n_sigma` - number of robust standard deviations beyond which a value is an outlier.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is not odd.
+    pub fn new(window: usize, n_sigma: F) -> Self {
+        HampelFilter {
+            median: MedianFilter::new(window),
+            n_sigma,
+            was_outlier: false,
+            deviations: Vec::with_capacity(window),
+        }
+    }
+
+    /// Adds a new value to the filter and returns it, or the median of the window
+    /// if the value is an outlier.
+    pub fn update(&mut self, value: F) -> F {
+        let median = self.median.update(value);
+
+        self.deviations.clear();
+        self.deviations.extend(
+            self.median
+                .sorted_values()
+                .iter()
+                .map(|&x| (x - median).abs()),
+        );
+        self.deviations
+            .sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+        let sigma = median_of_sorted(&self.deviations) * nalgebra::convert(1.4826);
+
+        self.was_outlier = (value - median).abs() > self.n_sigma * sigma;
+        if self.was_outlier {
+            median
+        } else {
+            value
+        }
+    }
+
+    /// Returns whether the last value was detected as an outlier and replaced.
+    pub fn was_outlier(&self) -> bool {
+        self.was_outlier
+    }
+}
+
+/// Tracks the mean, variance and standard deviation of the last `window` values.
+///
+/// The values are kept in a ring buffer, and the mean and the sum of squared deviations are
+/// updated with Welford's online algorithm, extended to remove the value leaving the window, so
+/// each update is O(1) and numerically stable. The variance is the population variance of the
+/// values in the window. Until `window` values have been seen, the statistics of all values so
+/// far are returned.
+///
+/// # Example
+///
+/// ```
+/// use filter::moving_averages::RollingStats;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let mut stats: RollingStats<f64> = RollingStats::new(2);
+/// stats.update(1.0);
+/// stats.update(5.0);
+/// stats.update(3.0);
+/// assert_approx_eq!(4.0, stats.mean());
+/// assert_approx_eq!(1.0, stats.variance());
+/// assert_approx_eq!(1.0, stats.std());
+/// ```
+///
+/// # References
+/// * Welford, "Note on a method for calculating corrected sums of squares and products".
+///   Technometrics 4, 1962.
+#[derive(Debug)]
+pub struct RollingStats<F: RealField> {
+    window: usize,
+    buffer: Vec<F>,
+    next: usize,
+    mean: F,
+    m2: F,
+}
+
+impl<F: RealField> RollingStats<F> {
+    /// Returns a tracker of the statistics of the last `window` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window must not be empty");
+        RollingStats {
+            window,
+            buffer: Vec::with_capacity(window),
+            next: 0,
+            mean: F::zero(),
+            m2: F::zero(),
+        }
+    }
+
+    /// Adds a new value to the window.
+    pub fn update(&mut self, value: F) {
+        if self.buffer.len() < self.window {
+            self.buffer.push(value);
+            let delta = value - self.mean;
+            self.mean += delta / nalgebra::convert(self.buffer.len() as f64);
+            self.m2 += delta * (value - self.mean);
+        } else {
+            let oldest = core::mem::replace(&mut self.buffer[self.next], value);
+            self.next = (self.next + 1) % self.window;
+            let mean = self.mean;
+            self.mean += (value - oldest) / nalgebra::convert(self.window as f64);
+            self.m2 += (value - oldest) * (value - self.mean + oldest - mean);
+            // Rounding can push the sum of squares slightly below zero for constant values.
+            self.m2 = self.m2.max(F::zero());
+        }
+    }
+
+    /// Returns the mean of the window, or zero if no value has been added yet.
+    pub fn mean(&self) -> F {
+        self.mean
+    }
+
+    /// Returns the population variance of the window, or zero if no value has been added yet.
+    pub fn variance(&self) -> F {
+        if self.buffer.is_empty() {
+            return F::zero();
+        }
+        self.m2 / nalgebra::convert(self.buffer.len() as f64)
+    }
+
+    /// Returns the population standard deviation of the window.
+    pub fn std(&self) -> F {
+        self.variance().sqrt()
+    }
+
+    /// Returns the number of values the statistics are computed over.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+}
+
+/// Returns the median of the non-empty, ascending `sorted` values.
+fn median_of_sorted<F: RealField>(sorted: &[F]) -> F {
+    let n = sorted.len();
+    if n % 2 == 1 {
+        sorted[n / 2]
+    } else {
+        let two: F = nalgebra::convert(2.0);
+        (sorted[n / 2 - 1] + sorted[n / 2]) / two
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use crate::test_util;
+
+    use super::*;
+
+    #[test]
+    fn test_sma_constant() {
+        let mut sma = SimpleMovingAverage::new(5);
+
+        for _ in 0..20 {
+            assert_eq!(4.25, sma.update(4.25_f64));
+        }
+    }
+
+    #[test]
+    fn test_sma_ramp_lag() {
+        let mut sma = SimpleMovingAverage::new(5);
+
+        for t in 0..20 {
+            let t = f64::from(t);
+            let estimate = sma.update(t);
+            if t < 5.0 {
+                // Warm-up: average of 0..=t.
+                assert_approx_eq!(t / 2.0, estimate);
+            } else {
+                // The average of a ramp lags by (window - 1) / 2 samples.
+                assert_approx_eq!(t - 2.0, estimate);
+            }
+        }
+    }
+
+    #[test]
+    fn test_median_rejects_spike() {
+        let mut median = MedianFilter::new(5);
+
+        for t in 0..30 {
+            let value = if t == 12 { 1000.0 } else { 3.0_f64 };
+            assert_eq!(3.0, median.update(value));
+        }
+    }
+
+    #[test]
+    fn test_median_warm_up_and_window() {
+        let mut median = MedianFilter::new(3);
+
+        assert_eq!(5.0, median.update(5.0_f64));
+        assert_eq!(3.0, median.update(1.0));
+        assert_eq!(5.0, median.update(9.0));
+        // The window is now [1, 9, 2].
+        assert_eq!(2.0, median.update(2.0));
+        // The window is now [9, 2, 4].
+        assert_eq!(4.0, median.update(4.0));
+    }
+
+    #[test]
+    fn test_median_matches_sorted_window() {
+        let mut median = MedianFilter::new(7);
+        let values: Vec<f64> = (0..100).map(|t| f64::from((t * 37) % 11)).collect();
+
+        for (t, &value) in values.iter().enumerate() {
+            let estimate = median.update(value);
+
+            let mut window = values[t.saturating_sub(6)..=t].to_vec();
+            window.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(window, median.sorted_values());
+            assert_eq!(median_of_sorted(&window), estimate);
+        }
+    }
+
+    #[test]
+    fn test_hampel_replaces_spikes() {
+        let mut hampel = HampelFilter::new(7, 3.0);
+        let spikes = [20, 35, 50];
+
+        for t in 0..60 {
+            let clean = 10.0 + 0.5 * (0.9 * f64::from(t)).sin();
+            let is_spike = spikes.contains(&t);
+            let value = if is_spike { clean + 20.0 } else { clean };
+
+            let filtered = hampel.update(value);
+
+            assert_eq!(is_spike, hampel.was_outlier(), "t = {}", t);
+            if is_spike {
+                assert!((filtered - clean).abs() < 1.0);
+            } else {
+                assert_eq!(value, filtered);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hampel_does_not_allocate() {
+        let mut hampel = HampelFilter::new(5, 3.0);
+        for t in 0..5 {
+            hampel.update(f64::from(t));
+        }
+
+        let allocations = test_util::allocations();
+        for t in 5..50 {
+            hampel.update(if t == 30 { 100.0 } else { f64::from(t) });
+        }
+        assert_eq!(allocations, test_util::allocations());
+    }
+
+    #[test]
+    fn test_wma_uniform_matches_sma() {
+        let mut wma = WeightedMovingAverage::new(&[0.5; 4]);
+        let mut sma = SimpleMovingAverage::new(4);
+
+        for t in 0..20 {
+            let value = (f64::from(t) * 0.7).sin() * 3.0;
+            assert_approx_eq!(sma.update(value), wma.update(value));
+        }
+    }
+
+    #[test]
+    fn test_wma_triangular_lag() {
+        let mut decreasing = WeightedMovingAverage::new(&[5.0, 4.0, 3.0, 2.0, 1.0]);
+        let mut symmetric = WeightedMovingAverage::new(&[1.0, 2.0, 3.0, 2.0, 1.0]);
+
+        for t in 0..20 {
+            let t = f64::from(t);
+            let fast = decreasing.update(t);
+            let centered = symmetric.update(t);
+            if t >= 4.0 {
+                // The lag on a ramp is the center of mass of the weights.
+                assert_approx_eq!(t - 4.0 / 3.0, fast);
+                assert_approx_eq!(t - 2.0, centered);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rolling_stats_match_batch() {
+        let values: Vec<f64> = (0..50)
+            .map(|t| 100.0 + 3.0 * (0.37 * f64::from(t)).sin() + f64::from(t % 7))
+            .collect();
+        let window = 8;
+        let mut stats = RollingStats::new(window);
+
+        for (t, &value) in values.iter().enumerate() {
+            stats.update(value);
+
+            let start = (t + 1).saturating_sub(window);
+            let recent = &values[start..=t];
+            let n = recent.len() as f64;
+            let mean = recent.iter().sum::<f64>() / n;
+            let variance = recent.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+
+            assert_approx_eq!(mean, stats.mean(), 1e-9);
+            assert_approx_eq!(variance, stats.variance(), 1e-9);
+            assert_approx_eq!(variance.sqrt(), stats.std(), 1e-9);
+        }
+    }
+}
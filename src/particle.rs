@@ -0,0 +1,409 @@
+/*!
+Provides a particle filter for non-linear and non-Gaussian problems.
+*/
+use alloc::vec;
+use alloc::vec::Vec;
+
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+
+use crate::kalman::outer;
+
+/// Algorithm used to resample the particles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resampling {
+    /// Systematic resampling, which places all samples at a fixed spacing with a single random
+    /// offset.
+    Systematic,
+    /// Stratified resampling, which draws one sample at random from each of `N` equally sized
+    /// strata.
+    Stratified,
+    /// Residual resampling, which deterministically copies each particle according to the integer
+    /// part of its scaled weight and samples the rest at random.
+    Residual,
+}
+
+/// Implements a particle filter.
+///
+/// The posterior is represented by a set of weighted samples (particles), so unlike the Kalman
+/// filter it can represent arbitrary, e.g., multimodal, distributions. The filter does not depend
+/// on a random number generator; the functions that require randomness take a closure that
+/// provides the samples instead.
+///
+/// # Example
+///
+/// ```
+/// use filter::particle::{ParticleFilter, Resampling};
+/// use nalgebra::Vector1;
+/// use rand::rngs::SmallRng;
+/// use rand::{Rng, SeedableRng};
+///
+/// let mut rng = SmallRng::seed_from_u64(42);
+/// let mut uniform = move || rng.gen::<f64>();
+///
+/// let particles = (0..100).map(|i| Vector1::new(i as f64 / 10.0)).collect();
+/// let mut pf = ParticleFilter::new(particles, Resampling::Systematic);
+///
+/// for _ in 0..5 {
+///     pf.predict(|x| x.clone(), || Vector1::new(0.1 * (uniform() - 0.5)));
+///     pf.update(&Vector1::new(5.0), |x| x.clone(), |z, hx| (-(z - hx).norm_squared()).exp());
+///     pf.resample(&mut uniform);
+/// }
+///
+/// let (mean, _) = pf.estimate();
+/// assert!((mean[0] - 5.0).abs() < 0.2);
+/// ```
+///
+/// # References
+/// * Roger Labbe. "Kalman and Bayesian Filters in Python", chapter 12.
+///   https://github.com/rlabbe/Kalman-and-Bayesian-Filters-in-Python
+#[derive(Debug)]
+pub struct ParticleFilter<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX>,
+{
+    /// Particles, i.e., samples of the state.
+    pub particles: Vec<VectorN<F, DimX>>,
+    /// Normalized weight of each particle.
+    pub weights: Vec<F>,
+    /// Resampling algorithm.
+    pub resampling: Resampling,
+    /// The particles are resampled when the effective sample size drops below this fraction of
+    /// the number of particles.
+    pub resample_threshold: F,
+}
+
+impl<F, DimX> ParticleFilter<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    /// Returns a particle filter with the given initial particles, which are weighted equally.
+    /// The particles are resampled when the effective sample size drops below half the number
+    /// of particles.
+    ///
+    /// # Panics
+    /// Panics if `particles` is empty.
+    pub fn new(particles: Vec<VectorN<F, DimX>>, resampling: Resampling) -> Self {
+        assert!(!particles.is_empty(), "particle filter requires at least one particle");
+        let n: F = nalgebra::convert(particles.len() as f64);
+        let weights = vec![F::one() / n; particles.len()];
+        ParticleFilter {
+            particles,
+            weights,
+            resampling,
+            resample_threshold: nalgebra::convert(0.5),
+        }
+    }
+
+    /// Moves each particle with the process model `fx` and adds a sample of the process noise.
+    pub fn predict<FX, Noise>(&mut self, fx: FX, mut process_noise: Noise)
+        where
+            FX: Fn(&VectorN<F, DimX>) -> VectorN<F, DimX>,
+            Noise: FnMut() -> VectorN<F, DimX>,
+    {
+        for particle in self.particles.iter_mut() {
+            *particle = fx(particle) + process_noise();
+        }
+    }
+
+    /// Weights each particle with the likelihood of measurement (z) given the predicted
+    /// measurement `hx` of that particle, and normalizes the weights.
+    ///
+    /// `measurement_likelihood` is called with the measurement and the predicted measurement.
+    /// If the measurement has zero likelihood for all particles, the weights are reset to be
+    /// equal.
+    pub fn update<DimZ, HX, Likelihood>(
+        &mut self,
+        z: &VectorN<F, DimZ>,
+        hx: HX,
+        measurement_likelihood: Likelihood,
+    )
+        where
+            DimZ: DimName,
+            DefaultAllocator: Allocator<F, DimZ>,
+            HX: Fn(&VectorN<F, DimX>) -> VectorN<F, DimZ>,
+            Likelihood: Fn(&VectorN<F, DimZ>, &VectorN<F, DimZ>) -> F,
+    {
+        for (particle, weight) in self.particles.iter().zip(self.weights.iter_mut()) {
+            *weight *= measurement_likelihood(z, &hx(particle));
+        }
+
+        let sum = self.weights.iter().fold(F::zero(), |acc, w| acc + *w);
+        if sum > F::zero() {
+            self.weights.iter_mut().for_each(|w| *w /= sum);
+        } else {
+            self.reset_weights();
+        }
+    }
+
+    /// Returns the effective sample size of the particles.
+    pub fn neff(&self) -> F {
+        neff(&self.weights)
+    }
+
+    /// Resamples the particles if the effective sample size dropped below the
+    /// `resample_threshold`. Returns whether the particles were resampled.
+    ///
+    /// `uniform` has to return random samples that are uniformly distributed in `[0, 1)`.
+    pub fn resample<U: FnMut() -> F>(&mut self, uniform: U) -> bool {
+        let n: F = nalgebra::convert(self.particles.len() as f64);
+        if self.neff() >= n * self.resample_threshold {
+            return false;
+        }
+
+        let indices = match self.resampling {
+            Resampling::Systematic => systematic_resample(&self.weights, uniform),
+            Resampling::Stratified => stratified_resample(&self.weights, uniform),
+            Resampling::Residual => residual_resample(&self.weights, uniform),
+        };
+        self.particles = indices.iter().map(|i| self.particles[*i].clone()).collect();
+        self.reset_weights();
+        true
+    }
+
+    /// Returns the weighted mean and covariance of the particles.
+    pub fn estimate(&self) -> (VectorN<F, DimX>, MatrixMN<F, DimX, DimX>) {
+        let mut mean = VectorN::<F, DimX>::zeros();
+        for (particle, weight) in self.particles.iter().zip(&self.weights) {
+            mean += particle * *weight;
+        }
+
+        let mut cov = MatrixMN::<F, DimX, DimX>::zeros();
+        for (particle, weight) in self.particles.iter().zip(&self.weights) {
+            cov += outer(&(particle - &mean)) * *weight;
+        }
+
+        (mean, cov)
+    }
+
+    fn reset_weights(&mut self) {
+        let n: F = nalgebra::convert(self.particles.len() as f64);
+        self.weights.iter_mut().for_each(|w| *w = F::one() / n);
+    }
+}
+
+/// Returns the effective sample size of the normalized `weights`, i.e., `1 / sum(w^2)`.
+///
+/// # Example
+///
+/// ```
+/// use filter::particle::neff;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// assert_approx_eq!(neff(&[0.25_f64, 0.25, 0.25, 0.25]), 4.0);
+/// assert_approx_eq!(neff(&[1.0_f64, 0.0, 0.0, 0.0]), 1.0);
+/// ```
+pub fn neff<F: RealField>(weights: &[F]) -> F {
+    F::one() / weights.iter().fold(F::zero(), |acc, w| acc + *w * *w)
+}
+
+/// Performs systematic resampling of the normalized `weights` and returns the indices of the
+/// selected particles.
+///
+/// The samples are placed at the fixed positions `(i + u) / N` for a single random offset `u`.
+/// `uniform` has to return random samples that are uniformly distributed in `[0, 1)`.
+pub fn systematic_resample<F, U>(weights: &[F], mut uniform: U) -> Vec<usize>
+    where
+        F: RealField,
+        U: FnMut() -> F,
+{
+    let offset = uniform();
+    resample_at(weights, |_| offset)
+}
+
+/// Performs stratified resampling of the normalized `weights` and returns the indices of the
+/// selected particles.
+///
+/// The samples are placed at the positions `(i + u_i) / N`, with a separate random offset `u_i`
+/// for each sample. `uniform` has to return random samples that are uniformly distributed
+/// in `[0, 1)`.
+pub fn stratified_resample<F, U>(weights: &[F], mut uniform: U) -> Vec<usize>
+    where
+        F: RealField,
+        U: FnMut() -> F,
+{
+    resample_at(weights, |_| uniform())
+}
+
+/// Performs residual resampling of the normalized `weights` and returns the indices of the
+/// selected particles.
+///
+/// Each particle is first copied `floor(N w_i)` times. The remaining samples are drawn at random
+/// from the distribution of the residual weights. `uniform` has to return random samples that are
+/// uniformly distributed in `[0, 1)`.
+pub fn residual_resample<F, U>(weights: &[F], mut uniform: U) -> Vec<usize>
+    where
+        F: RealField,
+        U: FnMut() -> F,
+{
+    let n = weights.len();
+    let n_f: F = nalgebra::convert(n as f64);
+
+    let mut indices = Vec::with_capacity(n);
+    let mut residual: Vec<F> = Vec::with_capacity(n);
+    for (i, w) in weights.iter().enumerate() {
+        let scaled = *w * n_f;
+        let mut copies = scaled.floor();
+        residual.push(scaled - copies);
+        while copies >= F::one() {
+            indices.push(i);
+            copies -= F::one();
+        }
+    }
+
+    let sum = residual.iter().fold(F::zero(), |acc, r| acc + *r);
+    let mut cumulative = Vec::with_capacity(n);
+    let mut acc = F::zero();
+    for r in residual {
+        acc += r / sum;
+        cumulative.push(acc);
+    }
+
+    while indices.len() < n {
+        let u = uniform();
+        let index = cumulative.iter().position(|c| u < *c).unwrap_or(n - 1);
+        indices.push(index);
+    }
+    indices.truncate(n);
+    indices
+}
+
+/// Selects one particle for each of the positions `(i + offset(i)) / N` from the cumulative sum
+/// of the weights.
+fn resample_at<F, O>(weights: &[F], mut offset: O) -> Vec<usize>
+    where
+        F: RealField,
+        O: FnMut(usize) -> F,
+{
+    let n = weights.len();
+    let n_f: F = nalgebra::convert(n as f64);
+
+    let mut indices = Vec::with_capacity(n);
+    let mut cumulative = weights.first().copied().unwrap_or_else(F::zero);
+    let mut j = 0;
+    for i in 0..n {
+        let position = (nalgebra::convert::<f64, F>(i as f64) + offset(i)) / n_f;
+        while position > cumulative && j < n - 1 {
+            j += 1;
+            cumulative += weights[j];
+        }
+        indices.push(j);
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector1;
+
+    use crate::test_util::uniform;
+
+    use super::*;
+
+    fn run_bimodal(resampling: Resampling) {
+        let mut uniform = uniform(7);
+
+        let particles = (0..1000)
+            .map(|_| Vector1::new(10.0 * uniform() - 5.0))
+            .collect();
+        let mut pf = ParticleFilter::new(particles, resampling);
+
+        // The sign of the state is not observable: x = 2 and x = -2 explain the measurements
+        // equally well. A Kalman filter can only represent one of the modes.
+        for _ in 0..10 {
+            pf.predict(|x| *x, || Vector1::new(0.05 * (uniform() - 0.5)));
+            pf.update(
+                &Vector1::new(4.0),
+                |x| Vector1::new(x[0] * x[0]),
+                |z, hx| (-0.5 * (z - hx).norm_squared()).exp(),
+            );
+            pf.resample(&mut uniform);
+        }
+
+        let mass = |range: core::ops::Range<f64>| {
+            pf.particles
+                .iter()
+                .zip(&pf.weights)
+                .filter(|(p, _)| range.contains(&p[0]))
+                .map(|(_, w)| *w)
+                .sum::<f64>()
+        };
+
+        let positive = mass(1.5..2.5);
+        let negative = mass(-2.5..-1.5);
+        assert!(positive > 0.3);
+        assert!(negative > 0.3);
+        assert!(positive + negative > 0.95);
+
+        let (mean, cov) = pf.estimate();
+        assert!(mean[0].abs() < 1.0);
+        assert!(cov[(0, 0)] > 3.0);
+    }
+
+    #[test]
+    fn test_bimodal_systematic() {
+        run_bimodal(Resampling::Systematic);
+    }
+
+    #[test]
+    fn test_bimodal_residual() {
+        run_bimodal(Resampling::Residual);
+    }
+
+    #[test]
+    fn test_resample_only_when_degenerate() {
+        let particles = vec![Vector1::new(0.0), Vector1::new(1.0), Vector1::new(2.0), Vector1::new(3.0)];
+        let mut pf = ParticleFilter::new(particles, Resampling::Systematic);
+
+        assert!(!pf.resample(uniform(1)));
+
+        pf.weights = vec![0.97, 0.01, 0.01, 0.01];
+        assert!(pf.resample(uniform(1)));
+        assert!(pf.particles.iter().all(|p| p[0] == 0.0));
+        assert!(pf.weights.iter().all(|w| *w == 0.25));
+    }
+
+    fn check_resampler(resample: fn(&[f64], &mut dyn FnMut() -> f64) -> Vec<usize>) {
+        let weights = [0.05, 0.6, 0.05, 0.2, 0.1];
+        let mut uniform = uniform(3);
+
+        let mut counts = [0; 5];
+        for _ in 0..100 {
+            let indices = resample(&weights, &mut uniform);
+            assert_eq!(weights.len(), indices.len());
+            indices.iter().for_each(|i| counts[*i] += 1);
+        }
+
+        // Over 100 runs, each particle is selected close to 500 * w times.
+        for (count, w) in counts.iter().zip(weights.iter()) {
+            assert!((*count as f64 - 500.0 * w).abs() < 30.0);
+        }
+        assert!(counts[1] > counts[3]);
+        assert!(counts[3] > counts[0]);
+    }
+
+    #[test]
+    fn test_systematic_resample() {
+        check_resampler(|w, u| systematic_resample(w, u));
+    }
+
+    #[test]
+    fn test_stratified_resample() {
+        check_resampler(|w, u| stratified_resample(w, u));
+    }
+
+    #[test]
+    fn test_residual_resample() {
+        check_resampler(|w, u| residual_resample(w, u));
+
+        // Particles with N w >= 1 are always copied floor(N w) times.
+        let indices = residual_resample(&[0.05, 0.6, 0.05, 0.2, 0.1], uniform(5));
+        assert!(indices.iter().filter(|i| **i == 1).count() >= 3);
+        assert!(indices.iter().filter(|i| **i == 3).count() >= 1);
+    }
+}
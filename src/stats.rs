@@ -1,12 +1,17 @@
 /*!
 Provides statics related utility functions used in other parts of the library.
 */
-use core::ops::{Add, Mul};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ops::{Add, AddAssign, Mul, MulAssign};
 
-use num_traits::Float;
+use nalgebra::allocator::Allocator;
+use nalgebra::base::dimension::DimName;
+use nalgebra::{DefaultAllocator, MatrixMN, RealField, VectorN};
+use num_traits::{Float, FloatConst};
 
 /// Represents a gaussian distribution with mean and variance..
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct GaussianDistribution<F: Float> {
     /// Mean of the distribution.
     pub mean: F,
@@ -19,6 +24,72 @@ impl<F: Float> GaussianDistribution<F> {
     pub fn new(mean: F, var: F) -> Self {
         GaussianDistribution { mean, var }
     }
+
+    /// Returns the Gaussian distribution with the same mean and variance as the mixture of the
+    /// given `(weight, distribution)` components, i.e., collapses the mixture by moment matching.
+    ///
+    /// The weights are normalized to sum to one. The variance of the mixture is the weighted
+    /// variance of the components plus the spread of their means,
+    /// `sum(w_i (var_i + (mean_i - mean)^2))`.
+    ///
+    /// # Panics
+    /// Panics if the weights sum to zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::stats::GaussianDistribution;
+    ///
+    /// let mixture = [
+    ///     (0.5, GaussianDistribution::new(-1.0, 1.0)),
+    ///     (0.5, GaussianDistribution::new(1.0, 1.0)),
+    /// ];
+    /// let combined = GaussianDistribution::combine(&mixture);
+    /// assert_eq!(0.0, combined.mean);
+    /// assert_eq!(2.0, combined.var);
+    /// ```
+    pub fn combine(components: &[(F, GaussianDistribution<F>)]) -> Self {
+        let total = components.iter().fold(F::zero(), |sum, &(w, _)| sum + w);
+        assert!(total != F::zero(), "weights must not sum to zero");
+
+        let mean = components
+            .iter()
+            .fold(F::zero(), |sum, &(w, g)| sum + w * g.mean)
+            / total;
+        let var = components.iter().fold(F::zero(), |sum, &(w, g)| {
+            let d = g.mean - mean;
+            sum + w * (g.var + d * d)
+        }) / total;
+
+        GaussianDistribution { mean, var }
+    }
+
+    /// Fuses the independent observation `measurement` with the variance `measurement_var` into
+    /// the distribution in place, i.e., replaces it with the product of the distribution and
+    /// `N(measurement, measurement_var)`.
+    ///
+    /// Starting from the uninformative distribution with infinite variance, the first observation
+    /// is taken over as it is, and after `n` observations of equal variance the mean is their
+    /// average and the variance `measurement_var / n`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use filter::stats::GaussianDistribution;
+    ///
+    /// let mut estimate = GaussianDistribution::new(0.0, f64::INFINITY);
+    /// for &z in &[9.0, 11.0, 10.0, 10.0] {
+    ///     estimate.observe(z, 2.0);
+    /// }
+    /// assert_eq!(10.0, estimate.mean);
+    /// assert_eq!(0.5, estimate.var);
+    /// ```
+    pub fn observe(&mut self, measurement: F, measurement_var: F) {
+        // In information form, so that an infinite prior variance contributes nothing.
+        let information = F::one() / self.var + F::one() / measurement_var;
+        self.mean = (self.mean / self.var + measurement / measurement_var) / information;
+        self.var = F::one() / information;
+    }
 }
 
 impl<F: Float> Add for GaussianDistribution<F> {
@@ -42,3 +113,553 @@ impl<F: Float> Mul for GaussianDistribution<F> {
         GaussianDistribution { mean, var }
     }
 }
+
+impl<F: Float> AddAssign for GaussianDistribution<F> {
+    fn add_assign(&mut self, other: GaussianDistribution<F>) {
+        *self = *self + other;
+    }
+}
+
+impl<F: Float> MulAssign for GaussianDistribution<F> {
+    fn mul_assign(&mut self, other: GaussianDistribution<F>) {
+        *self = *self * other;
+    }
+}
+
+/// Represents a multivariate gaussian distribution with mean and covariance.
+///
+/// Like `GaussianDistribution`, the sum of two distributions is the distribution of the sum of
+/// two independent random variables, and the product is the normalized product of the two
+/// densities, i.e., the fusion of two independent estimates of the same quantity.
+///
+/// # Example
+///
+/// ```
+/// use filter::stats::MultivariateGaussian;
+/// use nalgebra::{Matrix2, Vector2};
+///
+/// let radar = MultivariateGaussian::new(Vector2::new(10.0, 0.0), Matrix2::new(1.0, 0.0, 0.0, 4.0));
+/// let camera = MultivariateGaussian::new(Vector2::new(12.0, 0.0), Matrix2::new(4.0, 0.0, 0.0, 1.0));
+///
+/// let fused = radar * camera;
+/// assert!((fused.mean[0] - 10.4_f64).abs() < 1e-12);
+/// assert!((fused.cov[(0, 0)] - 0.8).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultivariateGaussian<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    /// Mean of the distribution.
+    pub mean: VectorN<F, DimX>,
+    /// Covariance matrix of the distribution.
+    pub cov: MatrixMN<F, DimX, DimX>,
+}
+
+impl<F, DimX> MultivariateGaussian<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    /// Create a new multivariate Gaussian distribution.
+    pub fn new(mean: VectorN<F, DimX>, cov: MatrixMN<F, DimX, DimX>) -> Self {
+        MultivariateGaussian { mean, cov }
+    }
+}
+
+impl<F, DimX> Add for MultivariateGaussian<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    type Output = MultivariateGaussian<F, DimX>;
+
+    fn add(self, other: MultivariateGaussian<F, DimX>) -> MultivariateGaussian<F, DimX> {
+        MultivariateGaussian {
+            mean: self.mean + other.mean,
+            cov: self.cov + other.cov,
+        }
+    }
+}
+
+impl<F, DimX> Mul for MultivariateGaussian<F, DimX>
+    where
+        F: RealField,
+        DimX: DimName,
+        DefaultAllocator: Allocator<F, DimX> + Allocator<F, DimX, DimX>,
+{
+    type Output = MultivariateGaussian<F, DimX>;
+
+    /// Returns the product of the two densities, `P = (P1^-1 + P2^-1)^-1` and
+    /// `mean = P (P1^-1 m1 + P2^-1 m2)`, computed in the equivalent form that inverts only
+    /// `P1 + P2`.
+    ///
+    /// # Panics
+    /// Panics if `P1 + P2` is singular.
+    fn mul(self, other: MultivariateGaussian<F, DimX>) -> MultivariateGaussian<F, DimX> {
+        let sum_inv = (&self.cov + &other.cov)
+            .try_inverse()
+            .expect("sum of covariances must be invertible");
+
+        let mean = &other.cov * &sum_inv * &self.mean + &self.cov * &sum_inv * &other.mean;
+        let cov = &self.cov * sum_inv * &other.cov;
+
+        MultivariateGaussian { mean, cov }
+    }
+}
+
+/// Predicts the next state of a scalar Kalman filter by adding the `movement` to the `prior`.
+///
+/// # Example
+///
+/// Tracking a dog that moves one unit per time step, as in chapter 4 of
+/// Roger Labbe's "Kalman and Bayesian Filters in Python":
+///
+/// ```
+/// use filter::stats::{kf_predict, kf_update, GaussianDistribution};
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let process_model = GaussianDistribution::new(1.0, 1.0);
+/// let sensor_var = 2.0;
+/// let zs = [1.354, 1.882, 4.341, 7.156, 6.939, 6.844, 9.847, 12.553, 16.273, 14.8];
+///
+/// let mut x = GaussianDistribution::new(0.0, 20.0_f64.powi(2));
+/// for z in zs.iter() {
+///     let prior = kf_predict(x, process_model);
+///     x = kf_update(prior, GaussianDistribution::new(*z, sensor_var));
+/// }
+///
+/// assert_approx_eq!(x.mean, 15.053, 1e-3);
+/// assert_approx_eq!(x.var, 1.0, 1e-3);
+/// ```
+pub fn kf_predict<F: Float>(
+    prior: GaussianDistribution<F>,
+    movement: GaussianDistribution<F>,
+) -> GaussianDistribution<F> {
+    prior + movement
+}
+
+/// Updates the `prior` of a scalar Kalman filter with the `measurement` by multiplying the two.
+///
+/// See `kf_predict` for an example.
+pub fn kf_update<F: Float>(
+    prior: GaussianDistribution<F>,
+    measurement: GaussianDistribution<F>,
+) -> GaussianDistribution<F> {
+    prior * measurement
+}
+
+/// Returns the difference `a - b` of two angles in radians, normalized into `[-pi, pi)`.
+///
+/// Use it as the residual of angular measurements, e.g., bearings, where plain subtraction
+/// produces large spurious residuals when the angles wrap around.
+///
+/// # Example
+///
+/// ```
+/// use filter::stats::angle_residual;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// let residual = angle_residual(179.0_f64.to_radians(), (-179.0_f64).to_radians());
+/// assert_approx_eq!(residual, (-2.0_f64).to_radians());
+/// ```
+pub fn angle_residual<F: Float + FloatConst>(a: F, b: F) -> F {
+    let two_pi = F::PI() + F::PI();
+    let y = (a - b + F::PI()) % two_pi;
+    if y < F::zero() {
+        y + two_pi - F::PI()
+    } else {
+        y - F::PI()
+    }
+}
+
+/// Returns `ln(sum(exp(values)))`, computed without overflow or underflow by factoring out the
+/// largest value.
+///
+/// This combines probabilities that are stored as logarithms, e.g., log-likelihoods, which would
+/// overflow or round to zero if they were exponentiated directly. Returns negative infinity for
+/// an empty slice, i.e., the logarithm of zero probability.
+///
+/// # Example
+///
+/// ```
+/// use filter::stats::log_sum_exp;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// assert_approx_eq!(log_sum_exp(&[-1000.0, -1000.0]), -1000.0 + 2.0_f64.ln());
+/// ```
+pub fn log_sum_exp<F: Float>(values: &[F]) -> F {
+    let max = values.iter().fold(F::neg_infinity(), |max, &v| max.max(v));
+    if max.is_infinite() {
+        return max;
+    }
+    let sum = values.iter().fold(F::zero(), |sum, &v| sum + (v - max).exp());
+    max + sum.ln()
+}
+
+/// Multiplies the probabilities `p` with the likelihoods whose logarithms are given in
+/// `log_likelihoods` and normalizes the products to sum to one.
+///
+/// The products are formed in log space and normalized like in [`log_sum_exp`], so that an
+/// outlier whose likelihood rounds to zero under every model does not zero out all of the
+/// probabilities. Leaves `p` unchanged and returns `false` if every product is zero.
+#[cfg(feature = "alloc")]
+pub(crate) fn bayes_update_log<F: RealField>(p: &mut [F], log_likelihoods: &[F]) -> bool {
+    let log_posterior = |p: F, log_likelihood: F| p.ln() + log_likelihood;
+
+    let neg_infinity = F::zero().ln();
+    let max = p
+        .iter()
+        .zip(log_likelihoods)
+        .fold(neg_infinity, |max, (&p, &l)| max.max(log_posterior(p, l)));
+    if !max.is_finite() {
+        return false;
+    }
+
+    let mut sum = F::zero();
+    for (p, &l) in p.iter_mut().zip(log_likelihoods) {
+        *p = (log_posterior(*p, l) - max).exp();
+        sum += *p;
+    }
+    p.iter_mut().for_each(|p| *p /= sum);
+    true
+}
+
+/// Returns the value below which a chi-square distributed random variable with `dof` degrees
+/// of freedom falls with probability `p`, i.e., the inverse of its cumulative distribution
+/// function.
+///
+/// This is the threshold for gating measurements on their squared Mahalanobis distance at a
+/// given confidence, with `dof` set to the dimension of the measurement.
+///
+/// # Panics
+/// Panics if `dof` is zero or `p` is not in `[0, 1)`.
+///
+/// # Example
+///
+/// ```
+/// use filter::stats::chi2_ppf;
+/// use assert_approx_eq::assert_approx_eq;
+///
+/// assert_approx_eq!(chi2_ppf(2, 0.95_f64), 5.991, 1e-3);
+/// ```
+pub fn chi2_ppf<F: Float>(dof: usize, p: F) -> F {
+    assert!(dof > 0, "degrees of freedom must be positive");
+    assert!(p >= F::zero() && p < F::one(), "p must be in [0, 1)");
+
+    let a = F::from(dof).unwrap() / F::from(2.0).unwrap();
+    let ln_gamma_a = ln_gamma_half_integer(dof);
+    let cdf = |x: F| regularized_gamma_p(a, x / F::from(2.0).unwrap(), ln_gamma_a);
+
+    let mut high = F::from(dof).unwrap();
+    while cdf(high) < p {
+        high = high + high;
+    }
+
+    // Bisection, until the interval does not shrink anymore.
+    let mut low = F::zero();
+    loop {
+        let mid = (low + high) / F::from(2.0).unwrap();
+        if mid <= low || mid >= high {
+            return mid;
+        }
+        if cdf(mid) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+}
+
+/// Returns the normalized autocorrelation of the `innovations` of a filter at the lags
+/// `0..=lags`.
+///
+/// The autocorrelation at lag `k` is `sum(y_t^T y_(t+k)) / sum(y_t^T y_t)`, where the mean of
+/// the innovations is subtracted from each `y_t`, so it is 1 at lag 0. The innovations of a
+/// well-tuned, optimal filter are white: their autocorrelation beyond lag 0 is close to zero,
+/// within about `±2 / sqrt(innovations.len())` at a 95% confidence. Significant correlations
+/// indicate that the filter does not extract all information from the measurements, e.g.,
+/// because the process noise `Q` is too small or the model is wrong.
+///
+/// Returns zeros if the innovations do not vary.
+///
+/// # Panics
+/// Panics if `lags` is not smaller than the number of innovations.
+///
+/// # Example
+///
+/// ```
+/// use filter::stats::innovation_autocorrelation;
+/// use nalgebra::Vector1;
+///
+/// let innovations: Vec<_> = [1.0, -1.0, 1.0, -1.0].iter().map(|&y| Vector1::new(y)).collect();
+/// let autocorrelation = innovation_autocorrelation(&innovations, 1);
+/// assert_eq!(vec![1.0, -0.75], autocorrelation);
+/// ```
+///
+/// # References
+/// * Mehra, "On the identification of variances and adaptive Kalman filtering".
+///   IEEE Transactions on Automatic Control 15, 1970.
+#[cfg(feature = "alloc")]
+pub fn innovation_autocorrelation<F, DimZ>(innovations: &[VectorN<F, DimZ>], lags: usize) -> Vec<F>
+    where
+        F: RealField,
+        DimZ: DimName,
+        DefaultAllocator: Allocator<F, DimZ>,
+{
+    let n = innovations.len();
+    assert!(lags < n, "lags must be smaller than the number of innovations");
+
+    let mean = innovations
+        .iter()
+        .fold(VectorN::<F, DimZ>::zeros(), |sum, y| sum + y)
+        / nalgebra::convert::<f64, F>(n as f64);
+    let centered: Vec<_> = innovations.iter().map(|y| y - &mean).collect();
+
+    let covariance = |lag: usize| {
+        centered[..n - lag]
+            .iter()
+            .zip(&centered[lag..])
+            .fold(F::zero(), |sum, (a, b)| sum + a.dot(b))
+    };
+    let variance = covariance(0);
+    (0..=lags)
+        .map(|lag| {
+            if variance > F::zero() {
+                covariance(lag) / variance
+            } else {
+                F::zero()
+            }
+        })
+        .collect()
+}
+
+/// Returns `ln(Gamma(n / 2))`, computed exactly from `Gamma(1/2) = sqrt(pi)`, `Gamma(1) = 1`
+/// and `Gamma(x + 1) = x Gamma(x)`.
+fn ln_gamma_half_integer<F: Float>(n: usize) -> F {
+    let two = F::from(2.0).unwrap();
+    let (mut ln_gamma, mut k) = if n % 2 == 1 {
+        (F::from(core::f64::consts::PI).unwrap().ln() / two, 1)
+    } else {
+        (F::zero(), 2)
+    };
+    while k < n {
+        ln_gamma = ln_gamma + (F::from(k).unwrap() / two).ln();
+        k += 2;
+    }
+    ln_gamma
+}
+
+/// Returns the regularized lower incomplete gamma function `P(a, x)`, using the series
+/// expansion for `x < a + 1` and the continued fraction of `Q(a, x) = 1 - P(a, x)` otherwise.
+fn regularized_gamma_p<F: Float>(a: F, x: F, ln_gamma_a: F) -> F {
+    if x <= F::zero() {
+        return F::zero();
+    }
+    let prefactor = (a * x.ln() - x - ln_gamma_a).exp();
+
+    if x < a + F::one() {
+        let mut term = F::one() / a;
+        let mut sum = term;
+        let mut n = a;
+        while term > sum * F::epsilon() {
+            n = n + F::one();
+            term = term * x / n;
+            sum = sum + term;
+        }
+        sum * prefactor
+    } else {
+        // Modified Lentz's method.
+        let tiny = F::min_positive_value() / F::epsilon();
+        let mut b = x + F::one() - a;
+        let mut c = F::one() / tiny;
+        let mut d = F::one() / b;
+        let mut h = d;
+        let mut i = F::one();
+        loop {
+            let an = -i * (i - a);
+            b = b + F::from(2.0).unwrap();
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = F::one() / d;
+            let delta = d * c;
+            h = h * delta;
+            if (delta - F::one()).abs() <= F::epsilon() {
+                break;
+            }
+            i = i + F::one();
+        }
+        F::one() - prefactor * h
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+    use nalgebra::{Matrix2, Vector2, U2};
+
+    use super::*;
+
+    #[test]
+    fn test_angle_residual() {
+        let deg = |d: f64| d.to_radians();
+
+        assert_approx_eq!(angle_residual(deg(179.0), deg(-179.0)), deg(-2.0));
+        assert_approx_eq!(angle_residual(deg(-179.0), deg(179.0)), deg(2.0));
+        assert_approx_eq!(angle_residual(deg(10.0), deg(350.0)), deg(20.0));
+        assert_approx_eq!(angle_residual(deg(30.0), deg(10.0)), deg(20.0));
+        assert_approx_eq!(angle_residual(deg(720.0 + 5.0), deg(0.0)), deg(5.0));
+    }
+
+    #[test]
+    fn test_chi2_ppf() {
+        assert_approx_eq!(chi2_ppf(1, 0.95), 3.841_459, 1e-5);
+        assert_approx_eq!(chi2_ppf(2, 0.95), 5.991_465, 1e-5);
+        assert_approx_eq!(chi2_ppf(3, 0.99), 11.344_867, 1e-5);
+        assert_approx_eq!(chi2_ppf(4, 0.5), 3.356_694, 1e-5);
+        assert_approx_eq!(chi2_ppf(6, 0.95), 12.591_587, 1e-5);
+        assert_approx_eq!(chi2_ppf(2, 0.0), 0.0);
+        assert_approx_eq!(chi2_ppf(3, 0.95_f32), 7.814_728, 1e-4);
+    }
+
+    #[test]
+    fn test_multivariate_gaussian_fusion() {
+        let estimate = MultivariateGaussian::new(
+            Vector2::new(3.0, -1.0),
+            Matrix2::new(
+                2.0, 0.5,
+                0.5, 1.0,
+            ),
+        );
+
+        let fused = estimate.clone() * estimate.clone();
+        assert!((fused.mean - estimate.mean).norm() < 1e-12);
+        assert!((fused.cov - estimate.cov * 0.5).norm() < 1e-12);
+
+        let sum = estimate.clone() + estimate.clone();
+        assert_eq!(sum.mean, estimate.mean * 2.0);
+        assert_eq!(sum.cov, estimate.cov * 2.0);
+    }
+
+    #[test]
+    fn test_multivariate_gaussian_matches_scalar() {
+        let a = GaussianDistribution::new(1.0, 2.0);
+        let b = GaussianDistribution::new(4.0, 3.0);
+        let product = a * b;
+
+        let diagonal = |g: GaussianDistribution<f64>| -> MultivariateGaussian<f64, U2> {
+            MultivariateGaussian::new(
+                Vector2::repeat(g.mean),
+                Matrix2::from_diagonal_element(g.var),
+            )
+        };
+        let fused = diagonal(a) * diagonal(b);
+
+        for i in 0..2 {
+            assert_approx_eq!(fused.mean[i], product.mean);
+            assert_approx_eq!(fused.cov[(i, i)], product.var);
+        }
+        assert_approx_eq!(fused.cov[(0, 1)], 0.0);
+    }
+
+    #[test]
+    fn test_assign_operators() {
+        let a = GaussianDistribution::new(1.0, 2.0);
+        let b = GaussianDistribution::new(3.0, 0.5);
+
+        let mut product = a;
+        product *= b;
+        assert_eq!((a * b).mean, product.mean);
+        assert_eq!((a * b).var, product.var);
+
+        let mut sum = a;
+        sum += b;
+        assert_eq!((a + b).mean, sum.mean);
+        assert_eq!((a + b).var, sum.var);
+    }
+
+    #[test]
+    fn test_combine() {
+        let a = GaussianDistribution::new(2.0, 0.5);
+        let b = GaussianDistribution::new(-4.0, 3.0);
+        // Unnormalized weights 0.3 and 0.7.
+        let combined = GaussianDistribution::combine(&[(0.6, a), (1.4, b)]);
+
+        let mean = 0.3 * 2.0 + 0.7 * -4.0;
+        let second_moment = 0.3 * (0.5 + 2.0 * 2.0) + 0.7 * (3.0 + 4.0 * 4.0);
+        assert_approx_eq!(mean, combined.mean);
+        assert_approx_eq!(second_moment - mean * mean, combined.var);
+
+        let single = GaussianDistribution::combine(&[(1.0, a)]);
+        assert_approx_eq!(a.mean, single.mean);
+        assert_approx_eq!(a.var, single.var);
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut estimate = GaussianDistribution::new(0.0, f64::INFINITY);
+        for n in 1..=100 {
+            estimate.observe(5.0, 4.0);
+            assert_approx_eq!(5.0, estimate.mean);
+            assert_approx_eq!(4.0 / n as f64, estimate.var);
+        }
+
+        // Observing is the product with the distribution of the measurement.
+        let prior = GaussianDistribution::new(1.0, 2.0);
+        let mut observed = prior;
+        observed.observe(3.0, 0.5);
+        let product = prior * GaussianDistribution::new(3.0, 0.5);
+        assert_approx_eq!(product.mean, observed.mean);
+        assert_approx_eq!(product.var, observed.var);
+    }
+
+    #[test]
+    fn test_log_sum_exp() {
+        let values = [0.5, -1.0, 2.0, 0.0];
+        let naive = values.iter().map(|v: &f64| v.exp()).sum::<f64>().ln();
+        assert_approx_eq!(log_sum_exp(&values), naive);
+        assert_approx_eq!(log_sum_exp(&[3.0]), 3.0);
+
+        assert_approx_eq!(log_sum_exp(&[1000.0, 1000.0]), 1000.0 + 2.0_f64.ln());
+        assert_approx_eq!(log_sum_exp(&[-1000.0, -1001.0]), -1000.0 + (1.0 + (-1.0_f64).exp()).ln());
+        assert_approx_eq!(log_sum_exp(&[800.0, -800.0]), 800.0);
+        assert_approx_eq!(log_sum_exp(&[80.0_f32, 80.0]), 80.0 + 2.0_f32.ln(), 1e-4);
+
+        assert_eq!(log_sum_exp::<f64>(&[]), f64::NEG_INFINITY);
+        assert_eq!(log_sum_exp(&[f64::NEG_INFINITY, f64::NEG_INFINITY]), f64::NEG_INFINITY);
+        assert_eq!(log_sum_exp(&[f64::INFINITY, 0.0]), f64::INFINITY);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_innovation_autocorrelation() {
+        // Uniform noise in (-1, 1).
+        let mut uniform = crate::test_util::uniform(42);
+        let mut noise = || 2.0 * uniform() - 1.0;
+        let white: Vec<_> = (0..2000).map(|_| Vector2::new(noise(), noise())).collect();
+
+        let autocorrelation = innovation_autocorrelation(&white, 10);
+        assert_eq!(11, autocorrelation.len());
+        assert_approx_eq!(1.0, autocorrelation[0]);
+        let bound = 3.0 / (white.len() as f64).sqrt();
+        for &rho in &autocorrelation[1..] {
+            assert!(rho.abs() < bound, "{} exceeds {}", rho, bound);
+        }
+
+        // A moving average of white noise is correlated at lag 1.
+        let smoothed: Vec<_> = white.windows(2).map(|w| (w[0] + w[1]) / 2.0).collect();
+        let autocorrelation = innovation_autocorrelation(&smoothed, 2);
+        assert_approx_eq!(0.5, autocorrelation[1], 0.1);
+        assert!(autocorrelation[2].abs() < bound);
+    }
+}